@@ -8,7 +8,11 @@ use serde::{Deserialize, Serialize};
 pub mod consts {
     pub const NAMESPACE: &str = "ark";
 
+    /// The session template resolved on binding, e.g. to unbind the node with the same one
+    pub const ANNOTATION_BIND_TEMPLATE: &str = "ark.ulagbulag.io/bind.template";
+
     pub const LABEL_BIND_BY_USER: &str = "ark.ulagbulag.io/bind.user";
+    pub const LABEL_BIND_GPU: &str = "ark.ulagbulag.io/bind.gpu";
     pub const LABEL_BIND_NAMESPACE: &str = "ark.ulagbulag.io/bind.namespace";
     pub const LABEL_BIND_NODE: &str = "ark.ulagbulag.io/bind.node";
     pub const LABEL_BIND_PERSISTENT: &str = "ark.ulagbulag.io/bind.persistent";
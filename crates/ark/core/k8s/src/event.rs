@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::{
+    runtime::events::{Event, EventType, Recorder, Reporter},
+    Client, Resource,
+};
+use tracing::{instrument, Level};
+
+pub struct EventRecorder {
+    inner: Recorder,
+}
+
+impl EventRecorder {
+    pub fn new<K>(kube: Client, reporter: impl Into<Reporter>, owner: &K) -> Self
+    where
+        K: Resource<DynamicType = ()>,
+    {
+        Self::with_reference(kube, reporter, owner.object_ref(&()))
+    }
+
+    pub fn with_reference(
+        kube: Client,
+        reporter: impl Into<Reporter>,
+        reference: ObjectReference,
+    ) -> Self {
+        Self {
+            inner: Recorder::new(kube, reporter.into(), reference),
+        }
+    }
+
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn normal(&self, reason: &str, action: &str, note: Option<&str>) -> Result<()> {
+        self.publish(EventType::Normal, reason, action, note).await
+    }
+
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn warning(&self, reason: &str, action: &str, note: Option<&str>) -> Result<()> {
+        self.publish(EventType::Warning, reason, action, note).await
+    }
+
+    async fn publish(
+        &self,
+        type_: EventType,
+        reason: &str,
+        action: &str,
+        note: Option<&str>,
+    ) -> Result<()> {
+        let event = Event {
+            type_,
+            reason: reason.into(),
+            note: note.map(Into::into),
+            action: action.into(),
+            secondary: None,
+        };
+
+        self.inner
+            .publish(event)
+            .await
+            .map_err(|error| anyhow!("failed to publish an event ({reason}): {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::core::v1::{ConfigMap, Event as CoreEvent};
+    use kube::{
+        api::{DeleteParams, ListParams, ObjectMeta, PostParams},
+        Api, ResourceExt,
+    };
+
+    use super::*;
+
+    #[::tokio::test]
+    #[ignore = "requires a running kubernetes cluster"]
+    async fn record_event_on_referenced_object() {
+        let kube = Client::try_default()
+            .await
+            .expect("failed to init k8s client");
+        let namespace = kube.default_namespace().to_string();
+
+        // Step 1. Create an owner object
+        let api = Api::<ConfigMap>::namespaced(kube.clone(), &namespace);
+        let owner = ConfigMap {
+            metadata: ObjectMeta {
+                generate_name: Some("ark-core-k8s-event-".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let owner = api
+            .create(&PostParams::default(), &owner)
+            .await
+            .expect("failed to create an owner object");
+        let name = owner.name_any();
+
+        // Step 2. Record an event
+        let recorder = EventRecorder::new(kube.clone(), "ark-core-k8s", &owner);
+        recorder
+            .normal("Tested", "Test", Some("recorded by ark-core-k8s"))
+            .await
+            .expect("failed to record an event");
+
+        // Step 3. Find the event from the owner object
+        let events = Api::<CoreEvent>::namespaced(kube, &namespace)
+            .list(&ListParams::default().fields(&format!(
+                "involvedObject.kind=ConfigMap,involvedObject.name={name}"
+            )))
+            .await
+            .expect("failed to list events")
+            .items;
+
+        api.delete(&name, &DeleteParams::default())
+            .await
+            .expect("failed to cleanup the owner object");
+
+        assert!(events
+            .iter()
+            .any(|event| event.reason.as_deref() == Some("Tested")));
+    }
+}
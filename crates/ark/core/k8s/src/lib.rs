@@ -2,6 +2,8 @@
 pub mod data;
 #[cfg(feature = "domain")]
 pub mod domain;
+#[cfg(feature = "event")]
+pub mod event;
 #[cfg(feature = "manager")]
 pub mod manager;
 #[cfg(feature = "name")]
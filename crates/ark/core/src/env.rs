@@ -1,4 +1,6 @@
-use anyhow::{anyhow, Result};
+use std::{collections::BTreeMap, env::VarError, fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use tracing::{instrument, Level};
 
@@ -55,3 +57,208 @@ where
 
     ::std::env::var(key).map_err(|_| anyhow!("failed to find the environment variable: {key}"))
 }
+
+/// Load a typed config from the environment variables, reporting all the
+/// missing or invalid variables at once.
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    data: Option<BTreeMap<String, String>>,
+    errors: Vec<String>,
+}
+
+impl ConfigLoader {
+    /// Load the variables from the given data (e.g. of a `ConfigMap`) instead of the environment.
+    pub fn from_data(data: BTreeMap<String, String>) -> Self {
+        Self {
+            data: Some(data),
+            errors: Vec::default(),
+        }
+    }
+
+    /// Infer a required variable.
+    ///
+    /// On error, the default value is returned and the error is deferred to [`Self::finish`].
+    pub fn infer<R>(&mut self, key: &str) -> R
+    where
+        R: Default + FromStr,
+        <R as FromStr>::Err: fmt::Display,
+    {
+        self.try_infer(key).unwrap_or_default()
+    }
+
+    /// Infer a required variable which has no default value.
+    ///
+    /// On error, `None` is returned and the error is deferred to [`Self::finish`].
+    pub fn try_infer<R>(&mut self, key: &str) -> Option<R>
+    where
+        R: FromStr,
+        <R as FromStr>::Err: fmt::Display,
+    {
+        match self.read(key) {
+            Ok(Some(value)) => self.parse(key, &value),
+            Ok(None) => {
+                let kind = self.kind();
+                self.errors
+                    .push(format!("failed to find the {kind}: {key}"));
+                None
+            }
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Infer an optional variable, treating a missing or empty value as `None`.
+    pub fn infer_opt<R>(&mut self, key: &str) -> Option<R>
+    where
+        R: FromStr,
+        <R as FromStr>::Err: fmt::Display,
+    {
+        match self.read(key) {
+            Ok(Some(value)) if value.is_empty() => None,
+            Ok(Some(value)) => self.parse(key, &value),
+            Ok(None) => None,
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    pub fn infer_or_else<R>(&mut self, key: &str, default: impl FnOnce() -> R) -> R
+    where
+        R: FromStr,
+        <R as FromStr>::Err: fmt::Display,
+    {
+        self.infer_opt(key).unwrap_or_else(default)
+    }
+
+    /// Validate a loaded field, e.g. its range.
+    pub fn validate(&mut self, key: &str, valid: bool, reason: impl fmt::Display) {
+        if !valid {
+            let kind = self.kind();
+            self.errors
+                .push(format!("invalid {kind} ({key}): {reason}"));
+        }
+    }
+
+    /// Report all the collected errors, if any.
+    pub fn finish(self) -> Result<()> {
+        let kind = self.kind();
+        let Self { data: _, errors } = self;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            bail!(
+                "failed to load {len} {kind}(s): {errors}",
+                len = errors.len(),
+                errors = errors.join("; "),
+            )
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match &self.data {
+            Some(_) => "configuration variable",
+            None => "environment variable",
+        }
+    }
+
+    fn read(&self, key: &str) -> Result<Option<String>, String> {
+        match &self.data {
+            Some(data) => Ok(data.get(key).cloned()),
+            None => match ::std::env::var(key) {
+                Ok(value) => Ok(Some(value)),
+                Err(VarError::NotPresent) => Ok(None),
+                Err(error) => Err(format!(
+                    "failed to read the environment variable ({key}): {error}"
+                )),
+            },
+        }
+    }
+
+    fn parse<R>(&mut self, key: &str, value: &str) -> Option<R>
+    where
+        R: FromStr,
+        <R as FromStr>::Err: fmt::Display,
+    {
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(error) => {
+                let kind = self.kind();
+                self.errors
+                    .push(format!("failed to parse the {kind} ({key}): {error}"));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_loader_aggregates_errors() {
+        ::std::env::set_var("ARK_CORE_TEST_CONFIG_VALID", "42");
+        ::std::env::set_var("ARK_CORE_TEST_CONFIG_INVALID", "not-a-number");
+        ::std::env::set_var("ARK_CORE_TEST_CONFIG_OUT_OF_RANGE", "0");
+        ::std::env::remove_var("ARK_CORE_TEST_CONFIG_MISSING");
+
+        let mut loader = ConfigLoader::default();
+        let valid: u32 = loader.infer("ARK_CORE_TEST_CONFIG_VALID");
+        let _: u32 = loader.infer("ARK_CORE_TEST_CONFIG_INVALID");
+        let _: u32 = loader.infer("ARK_CORE_TEST_CONFIG_MISSING");
+        let optional: Option<u32> = loader.infer_opt("ARK_CORE_TEST_CONFIG_MISSING");
+        let defaulted = loader.infer_or_else("ARK_CORE_TEST_CONFIG_MISSING", || 7u32);
+        let ranged: u32 = loader.infer("ARK_CORE_TEST_CONFIG_OUT_OF_RANGE");
+        loader.validate(
+            "ARK_CORE_TEST_CONFIG_OUT_OF_RANGE",
+            ranged > 0,
+            "should be positive",
+        );
+
+        assert_eq!(valid, 42);
+        assert_eq!(optional, None);
+        assert_eq!(defaulted, 7);
+
+        // All the problems are reported in a single error
+        let error = loader.finish().expect_err("should fail").to_string();
+        assert!(error.starts_with("failed to load 3 environment variable(s)"));
+        assert!(error.contains("ARK_CORE_TEST_CONFIG_INVALID"));
+        assert!(error.contains("ARK_CORE_TEST_CONFIG_MISSING"));
+        assert!(error.contains("ARK_CORE_TEST_CONFIG_OUT_OF_RANGE"));
+    }
+
+    #[test]
+    fn config_loader_from_data() {
+        let data = [
+            ("valid".to_string(), "42".to_string()),
+            ("empty".to_string(), String::default()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut loader = ConfigLoader::from_data(data);
+        let valid: Option<u32> = loader.try_infer("valid");
+        let optional: Option<u32> = loader.infer_opt("empty");
+        let missing: Option<u32> = loader.try_infer("missing");
+
+        assert_eq!(valid, Some(42));
+        assert_eq!(optional, None);
+        assert_eq!(missing, None);
+
+        let error = loader.finish().expect_err("should fail").to_string();
+        assert_eq!(
+            error,
+            "failed to load 1 configuration variable(s): failed to find the configuration variable: missing",
+        );
+    }
+
+    #[test]
+    fn config_loader_ok() {
+        let loader = ConfigLoader::default();
+        assert!(loader.finish().is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+use std::{error, io};
+
+use ::kube::Error;
+
+/// Return whether the failed kubernetes request may succeed when retried.
+///
+/// Conflicts, throttling, server timeouts and transient transport failures are retryable.
+pub fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::Api(response) => {
+            matches!(response.code, 409 | 429 | 500 | 503 | 504)
+                || matches!(
+                    response.reason.as_str(),
+                    "Conflict" | "ServerTimeout" | "Timeout" | "TooManyRequests"
+                )
+        }
+        Error::HyperError(error) => !error.is_parse() && !error.is_user(),
+        Error::Service(error) => is_retryable_source(&**error),
+        Error::ReadEvents(error) => is_retryable_io(error),
+        _ => false,
+    }
+}
+
+/// Look for a transport failure through the chain of the middleware error.
+fn is_retryable_source(error: &(dyn error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<io::Error>() {
+            return is_retryable_io(error);
+        }
+        source = error.source();
+    }
+    false
+}
+
+fn is_retryable_io(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ::kube::error::ErrorResponse;
+
+    use super::*;
+
+    fn api_error(code: u16, reason: &str) -> Error {
+        Error::Api(ErrorResponse {
+            status: "Failure".into(),
+            message: String::default(),
+            reason: reason.into(),
+            code,
+        })
+    }
+
+    #[test]
+    fn api_retryable() {
+        assert!(is_retryable(&api_error(409, "Conflict")));
+        assert!(is_retryable(&api_error(429, "TooManyRequests")));
+        assert!(is_retryable(&api_error(504, "Timeout")));
+        assert!(is_retryable(&api_error(500, "ServerTimeout")));
+    }
+
+    #[test]
+    fn api_fatal() {
+        assert!(!is_retryable(&api_error(400, "BadRequest")));
+        assert!(!is_retryable(&api_error(403, "Forbidden")));
+        assert!(!is_retryable(&api_error(404, "NotFound")));
+        assert!(!is_retryable(&api_error(422, "Invalid")));
+    }
+
+    #[test]
+    fn transport_retryable() {
+        let error = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert!(is_retryable(&Error::Service(Box::new(error))));
+
+        let error = io::Error::from(io::ErrorKind::TimedOut);
+        assert!(is_retryable(&Error::ReadEvents(error)));
+    }
+
+    #[test]
+    fn transport_fatal() {
+        let error = io::Error::from(io::ErrorKind::InvalidData);
+        assert!(!is_retryable(&Error::ReadEvents(error)));
+        assert!(!is_retryable(&Error::TlsRequired));
+
+        // The middleware errors other than the transport ones are fatal
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(!is_retryable(&Error::Service(Box::new(error))));
+        assert!(!is_retryable(&Error::Service(
+            "invalid header value".into()
+        )));
+    }
+}
@@ -1,5 +1,11 @@
 pub mod env;
+#[cfg(feature = "kube")]
+pub mod kube;
 pub mod result;
+#[cfg(feature = "retry")]
+pub mod retry;
 #[cfg(feature = "signal")]
 pub mod signal;
+#[cfg(feature = "stream")]
+pub mod stream;
 pub mod tracer;
@@ -0,0 +1,175 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use tokio::time::sleep;
+use tracing::debug;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Randomize each delay into `[delay / 2, delay]`
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Return the backoff delay after the given (0-based) failed attempt, without jitter.
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.try_into().unwrap_or(u32::MAX))
+            .unwrap_or(u32::MAX);
+        self.base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Return the backoff delay after the given (0-based) failed attempt, with jitter if enabled.
+    pub fn delay_with_jitter(&self, attempt: usize) -> Duration {
+        let delay = self.delay(attempt);
+        if self.jitter && !delay.is_zero() {
+            let half = delay / 2;
+            half + ::rand::thread_rng().gen_range(Duration::ZERO..=delay - half)
+        } else {
+            delay
+        }
+    }
+}
+
+pub async fn retry_with_backoff<F, Fut, T, E>(policy: &RetryPolicy, op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    retry_with_backoff_if(policy, |_| true, op).await
+}
+
+pub async fn retry_with_backoff_if<P, F, Fut, T, E>(
+    policy: &RetryPolicy,
+    is_retryable: P,
+    mut op: F,
+) -> Result<T, E>
+where
+    P: Fn(&E) -> bool,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => break Ok(value),
+            Err(error) if attempt + 1 < policy.max_attempts && is_retryable(&error) => {
+                let delay = policy.delay_with_jitter(attempt);
+                attempt += 1;
+                debug!(
+                    "Retrying in {delay:?} ({attempt}/{max})",
+                    max = policy.max_attempts
+                );
+                sleep(delay).await;
+            }
+            Err(error) => break Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const POLICY_IMMEDIATE: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::ZERO,
+        max_delay: Duration::ZERO,
+        jitter: false,
+    };
+
+    #[test]
+    fn delay_growth() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: false,
+        };
+
+        let delays: Vec<_> = (0..6).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis),
+        );
+        assert_eq!(policy.delay(usize::MAX), policy.max_delay);
+    }
+
+    #[test]
+    fn delay_with_jitter_bounds() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..8 {
+            let delay = policy.delay(attempt);
+            let jittered = policy.delay_with_jitter(attempt);
+            assert!(jittered >= delay / 2 && jittered <= delay);
+        }
+    }
+
+    #[::tokio::test]
+    async fn retry_until_success() {
+        let attempts = AtomicUsize::default();
+        let result: Result<usize, ()> = retry_with_backoff(&POLICY_IMMEDIATE, || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                attempt @ 2 => Ok(attempt),
+                _ => Err(()),
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(2));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[::tokio::test]
+    async fn retry_exhausted() {
+        let attempts = AtomicUsize::default();
+        let result: Result<(), ()> = retry_with_backoff(&POLICY_IMMEDIATE, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(())
+        })
+        .await;
+
+        assert_eq!(result, Err(()));
+        assert_eq!(
+            attempts.load(Ordering::SeqCst),
+            POLICY_IMMEDIATE.max_attempts
+        );
+    }
+
+    #[::tokio::test]
+    async fn retry_fatal() {
+        let attempts = AtomicUsize::default();
+        let result: Result<(), bool> = retry_with_backoff_if(
+            &POLICY_IMMEDIATE,
+            |&is_retryable| is_retryable,
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(false)
+            },
+        )
+        .await;
+
+        assert_eq!(result, Err(false));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
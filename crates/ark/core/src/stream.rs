@@ -0,0 +1,92 @@
+use std::future::Future;
+
+use async_stream::try_stream;
+use futures::Stream;
+
+/// Build a lazy stream over a cursor-paginated endpoint.
+///
+/// The `loader` is called with the cursor of the last item of the previous page
+/// (`None` on the first call) and the page size.
+/// The stream ends when a page is shorter than the page size.
+pub fn paginate<F, Fut, P, T, C, E>(
+    loader: F,
+    id_picker: P,
+    page_size: usize,
+) -> impl Stream<Item = Result<T, E>>
+where
+    F: Fn(Option<C>, usize) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, E>>,
+    P: Fn(&T) -> C,
+{
+    try_stream! {
+        let mut start = None;
+
+        loop {
+            let items = loader(start, page_size).await?;
+            start = items.last().map(&id_picker);
+            let len = items.len();
+
+            for item in items {
+                yield item;
+            }
+
+            if len == 0 || len != page_size {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    async fn collect(items: &[usize], page_size: usize) -> (Vec<usize>, Vec<Option<usize>>) {
+        let cursors = Mutex::new(vec![]);
+        let loader = |start: Option<usize>, limit: usize| {
+            cursors.lock().unwrap().push(start);
+            let offset = start.map(|start| start + 1).unwrap_or_default();
+            let page = items.iter().copied().skip(offset).take(limit).collect();
+            async move { Ok::<_, ()>(page) }
+        };
+
+        let output = paginate(loader, |&item| item, page_size)
+            .try_collect()
+            .await
+            .unwrap();
+        (output, cursors.into_inner().unwrap())
+    }
+
+    #[::tokio::test]
+    async fn paginate_empty() {
+        let (output, cursors) = collect(&[], 3).await;
+        assert!(output.is_empty());
+        assert_eq!(cursors, [None]);
+    }
+
+    #[::tokio::test]
+    async fn paginate_single_page() {
+        let (output, cursors) = collect(&[0, 1], 3).await;
+        assert_eq!(output, [0, 1]);
+        assert_eq!(cursors, [None]);
+    }
+
+    #[::tokio::test]
+    async fn paginate_multi_page() {
+        let items: Vec<_> = (0..7).collect();
+        let (output, cursors) = collect(&items, 3).await;
+        assert_eq!(output, items);
+        assert_eq!(cursors, [None, Some(2), Some(5)]);
+    }
+
+    #[::tokio::test]
+    async fn paginate_error() {
+        let loader = |_: Option<usize>, _| async { Err::<Vec<usize>, _>("failed") };
+        let result: Result<Vec<_>, _> = paginate(loader, |&item| item, 3).try_collect().await;
+        assert_eq!(result, Err("failed"));
+    }
+}
@@ -4,9 +4,16 @@ use std::{env, ffi::OsStr};
 use opentelemetry_otlp as otlp;
 use tracing::{debug, dispatcher, Subscriber};
 use tracing_subscriber::{
-    layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, Layer, Registry,
+    filter::filter_fn, layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, Layer,
+    Registry,
 };
 
+/// The target of the metric events (e.g. `monotonic_counter.*`), which are
+/// exported by the metrics layer but never printed.
+///
+/// They are emitted at `DEBUG`, so enable them with e.g. `RUST_LOG=INFO,metrics=DEBUG`.
+pub const TARGET_METRICS: &str = "metrics";
+
 fn init_once_opentelemetry(export: bool) {
     #[cfg(feature = "otlp")]
     use opentelemetry_sdk::runtime::Tokio as Runtime;
@@ -43,6 +50,7 @@ fn init_once_opentelemetry(export: bool) {
         S: Subscriber + for<'span> LookupSpan<'span>,
     {
         ::tracing_subscriber::fmt::layer()
+            .with_filter(filter_fn(|metadata| metadata.target() != TARGET_METRICS))
     }
 
     #[cfg(feature = "logs")]
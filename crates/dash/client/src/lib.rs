@@ -1,16 +1,18 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, time::Duration};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use ark_api::SessionRef;
-use ark_core::result::Result as SessionResult;
+use ark_core::{result::Result as SessionResult, retry::RetryPolicy};
 use dash_api::{job::DashJobCrd, model::ModelCrd, task::TaskCrd};
-use dash_provider_api::job::Payload;
+use dash_provider_api::{batch::BatchGet, job::Payload};
 use derivative::Derivative;
-use reqwest::{Client, Method, Url};
+use futures::{future, stream, Stream, StreamExt};
+use reqwest::{redirect::Policy, Client, Method, Url};
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{instrument, Level};
+use tokio::time::sleep;
+use tracing::{instrument, warn, Level};
 use vine_api::user_session::{UserSession, UserSessionCommandBatch};
 
 #[derive(Clone, Derivative)]
@@ -20,6 +22,7 @@ pub struct DashClient {
     client: Client,
     host: Url,
     namespace: Option<String>,
+    require_namespace: bool,
 }
 
 impl DashClient {
@@ -28,6 +31,7 @@ impl DashClient {
             client,
             host,
             namespace: namespace.into(),
+            require_namespace: false,
         }
     }
 
@@ -41,13 +45,66 @@ impl DashClient {
             .map_err(Into::into)
     }
 
+    pub fn with_host_and_options<Host>(
+        host: Host,
+        namespace: impl Into<Option<String>>,
+        options: DashClientOptions,
+    ) -> Result<Self>
+    where
+        Host: TryInto<Url>,
+        <Host as TryInto<Url>>::Error: 'static + Send + Sync + Error,
+    {
+        let client = options.build_client()?;
+        host.try_into()
+            .map(|host| Self::new(client, host, namespace))
+            .map_err(Into::into)
+    }
+
     pub fn to_namespaced(&self, namespace: impl Into<Option<String>>) -> Self {
         Self {
             client: self.client.clone(),
             host: self.host.clone(),
             namespace: namespace.into(),
+            require_namespace: self.require_namespace,
         }
     }
+
+    /// Reject any request without a namespace, instead of running it cluster-wide.
+    pub fn require_namespace(mut self) -> Self {
+        self.require_namespace = true;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DashClientOptions {
+    /// Maximum number of redirects to follow; `Some(0)` disables redirects
+    pub max_redirects: Option<usize>,
+    pub user_agent: Option<String>,
+}
+
+impl DashClientOptions {
+    fn build_client(self) -> Result<Client> {
+        let Self {
+            max_redirects,
+            user_agent,
+        } = self;
+
+        let mut builder = Client::builder();
+        if let Some(max_redirects) = max_redirects {
+            builder = builder.redirect(match max_redirects {
+                0 => Policy::none(),
+                max_redirects => Policy::limited(max_redirects),
+            });
+        }
+        if let Some(user_agent) = user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        builder
+            .build()
+            .map_err(|error| anyhow!("failed to build dash http client: {error}"))
+    }
 }
 
 impl DashClient {
@@ -56,6 +113,11 @@ impl DashClient {
         self.get(format!("/task/{name}/")).await
     }
 
+    #[instrument(level = Level::INFO, err(Display))]
+    pub async fn get_tasks(&self, names: &[&str]) -> Result<BatchGet<TaskCrd>> {
+        self.post("/batch/task/", Some(names)).await
+    }
+
     #[instrument(level = Level::INFO, err(Display))]
     pub async fn get_task_list(&self) -> Result<Vec<ObjectRef>> {
         self.get("/task/").await
@@ -111,6 +173,11 @@ impl DashClient {
         self.get(format!("/model/{name}/")).await
     }
 
+    #[instrument(level = Level::INFO, err(Display))]
+    pub async fn get_models(&self, names: &[&str]) -> Result<BatchGet<ModelCrd>> {
+        self.post("/batch/model/", Some(names)).await
+    }
+
     #[instrument(level = Level::INFO, err(Display))]
     pub async fn get_model_task_list(&self, name: &str) -> Result<Vec<TaskCrd>> {
         self.get(format!("/model/{name}/task/")).await
@@ -143,6 +210,57 @@ impl DashClient {
         self.get("/batch/user/session/").await
     }
 
+    /// Watch the user session list, yielding the initial list and then every changed one.
+    ///
+    /// A failed refresh is yielded as an error, and retried with backoff.
+    #[instrument(level = Level::INFO, err(Display))]
+    pub async fn watch_user_sessions(
+        &self,
+    ) -> Result<impl '_ + Stream<Item = Result<Vec<SessionRef<'static>>>>> {
+        const INTERVAL: Duration = Duration::from_secs(5);
+        const POLICY: RetryPolicy = RetryPolicy {
+            max_attempts: usize::MAX,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+        };
+
+        async fn load(client: &DashClient) -> Result<Vec<SessionRef<'static>>> {
+            let mut sessions = client.get_user_session_list().await?;
+            sessions.sort();
+            Ok(sessions)
+        }
+
+        // NOTE: the gateway has no streaming endpoint, so the list is polled
+        let sessions = load(self).await?;
+        let updates = stream::unfold(
+            (sessions.clone(), 0usize),
+            move |(mut last, mut failures)| async move {
+                loop {
+                    sleep(match failures {
+                        0 => INTERVAL,
+                        failures => POLICY.delay_with_jitter(failures - 1),
+                    })
+                    .await;
+
+                    match load(self).await {
+                        Ok(sessions) if sessions == last => failures = 0,
+                        Ok(sessions) => {
+                            last = sessions.clone();
+                            break Some((Ok(sessions), (last, 0)));
+                        }
+                        Err(error) => {
+                            failures += 1;
+                            warn!("failed to refresh user sessions ({failures} time(s)): {error}");
+                            break Some((Err(error), (last, failures)));
+                        }
+                    }
+                }
+            },
+        );
+        Ok(stream::once(future::ready(Ok(sessions))).chain(updates))
+    }
+
     #[instrument(level = Level::INFO, err(Display))]
     pub async fn post_user_exec<T>(&self, command: &[T]) -> Result<()>
     where
@@ -152,6 +270,19 @@ impl DashClient {
         self.post("/user/desktop/exec/", Some(command)).await
     }
 
+    #[instrument(level = Level::INFO, err(Display))]
+    pub async fn post_user_exec_for<T>(&self, user_name: &str, command: &[T]) -> Result<()>
+    where
+        T: AsRef<str> + Serialize,
+        [T]: fmt::Debug,
+    {
+        self.post(
+            format!("/batch/user/{user_name}/desktop/exec/"),
+            Some(command),
+        )
+        .await
+    }
+
     #[instrument(level = Level::INFO, err(Display))]
     pub async fn post_user_exec_broadcast<Command, UserName>(
         &self,
@@ -203,6 +334,13 @@ impl DashClient {
         Req: ?Sized + Serialize,
         Res: DeserializeOwned,
     {
+        if self.require_namespace && self.namespace.is_none() {
+            bail!(
+                "namespace is required, but not given: {method} {path}",
+                path = path.as_ref(),
+            )
+        }
+
         let mut request = self.client.request(method, self.get_url(path));
         if let Some(data) = data {
             request = request.json(data);
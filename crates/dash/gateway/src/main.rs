@@ -44,6 +44,7 @@ async fn main() {
                 .service(health)
                 .service(crate::routes::task::get)
                 .service(crate::routes::task::get_list)
+                .service(crate::routes::task::post_batch_get)
                 .service(crate::routes::job::batch::post)
                 .service(crate::routes::job::single::delete)
                 .service(crate::routes::job::single::get)
@@ -56,7 +57,8 @@ async fn main() {
                 .service(crate::routes::model::get_task_list)
                 .service(crate::routes::model::get_item)
                 .service(crate::routes::model::get_item_list)
-                .service(crate::routes::model::get_list);
+                .service(crate::routes::model::get_list)
+                .service(crate::routes::model::post_batch_get);
             let app = ::vine_plugin::register(app);
             app.wrap(cors)
                 .wrap(middleware::NormalizePath::new(
@@ -1,6 +1,6 @@
 use actix_web::{
-    get,
-    web::{Data, Path},
+    get, post,
+    web::{Data, Json, Path},
     HttpRequest, HttpResponse, Responder,
 };
 use ark_core::result::Result;
@@ -8,8 +8,10 @@ use dash_provider::{
     input::Name,
     storage::{KubernetesStorageClient, Storage, StorageClient},
 };
+use dash_provider_api::batch::BatchGet;
+use futures::future::join_all;
 use kube::Client;
-use tracing::{instrument, Level};
+use tracing::{instrument, warn, Level};
 use vine_api::user_session::UserSession;
 use vine_rbac::auth::AuthUserSession;
 
@@ -109,3 +111,38 @@ pub async fn get_item_list(
     let result = client.list(&name.0).await;
     HttpResponse::from(Result::from(result))
 }
+
+#[instrument(level = Level::INFO, skip(request, kube))]
+#[post("/batch/model")]
+pub async fn post_batch_get(
+    request: HttpRequest,
+    kube: Data<Client>,
+    names: Json<Vec<Name>>,
+) -> impl Responder {
+    let kube = kube.as_ref();
+    let namespace = match UserSession::from_request(&kube, &request).await {
+        Ok(session) => session.namespace,
+        Err(error) => return HttpResponse::from(Result::<()>::Err(error.to_string())),
+    };
+
+    let client = KubernetesStorageClient {
+        namespace: &namespace,
+        kube,
+    };
+    let items = join_all(names.0.into_iter().map(|Name(name)| {
+        let client = &client;
+        async move {
+            match client.load_model(&name).await {
+                Ok(item) => (name, Some(item)),
+                Err(error) => {
+                    warn!("failed to load model ({name}): {error}; skipping...");
+                    (name, None)
+                }
+            }
+        }
+    }))
+    .await;
+
+    let result: BatchGet<_> = items.into_iter().collect();
+    HttpResponse::from(Result::Ok(result))
+}
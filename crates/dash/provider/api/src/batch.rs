@@ -0,0 +1,31 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchGet<T> {
+    /// Found items, ordered as requested
+    pub items: Vec<T>,
+    /// Requested names which could not be loaded
+    #[serde(default)]
+    pub missing: Vec<String>,
+}
+
+impl<T> FromIterator<(String, Option<T>)> for BatchGet<T> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Option<T>)>,
+    {
+        let mut batch = Self {
+            items: Vec::default(),
+            missing: Vec::default(),
+        };
+        for (name, item) in iter {
+            match item {
+                Some(item) => batch.items.push(item),
+                None => batch.missing.push(name),
+            }
+        }
+        batch
+    }
+}
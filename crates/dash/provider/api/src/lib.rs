@@ -1,3 +1,4 @@
+pub mod batch;
 pub mod data;
 pub mod job;
 
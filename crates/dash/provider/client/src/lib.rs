@@ -3,14 +3,17 @@ use std::collections::BTreeMap;
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 use dash_api::{
-    job::{DashJobCrd, DashJobSpec},
+    job::{DashJobCrd, DashJobSpec, DashJobState},
     task::TaskCrd,
 };
 use dash_provider_api::{
     job::{TaskActorJobMetadata, TaskChannelKindJob},
     TaskChannelKind,
 };
-use futures::{AsyncBufReadExt, Stream, TryStreamExt};
+use futures::{
+    stream::{abortable, select_all, AbortHandle, FuturesOrdered},
+    AsyncBufReadExt, Stream, StreamExt, TryStreamExt,
+};
 use itertools::Itertools;
 use k8s_openapi::api::core::v1::Pod;
 use kube::{
@@ -18,6 +21,7 @@ use kube::{
     core::ObjectMeta,
     Api, Client, ResourceExt,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::{instrument, Level};
 use vine_api::user_session::UserSession;
@@ -54,6 +58,40 @@ impl<'a> DashProviderClient<'a> {
         self.create_raw(&task, value).await
     }
 
+    /// Create the jobs concurrently, returning the results in the given order.
+    #[cfg(feature = "dash-provider")]
+    #[instrument(level = Level::INFO, skip_all, fields(items = items.len()))]
+    pub async fn create_batch(
+        &self,
+        items: &[(String, BTreeMap<String, Value>)],
+    ) -> Vec<Result<DashJobCrd>> {
+        const MAX_CONCURRENCY: usize = 8;
+
+        ::futures::stream::iter(items)
+            .map(|(task_name, value)| self.create(task_name, value.clone()))
+            .buffered(MAX_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Create the job, returning only its reference for polling with [`Self::get_status`].
+    #[cfg(feature = "dash-provider")]
+    #[instrument(level = Level::INFO, skip(self, value), err(Display))]
+    pub async fn create_ref(
+        &self,
+        task_name: &str,
+        value: BTreeMap<String, Value>,
+    ) -> Result<::dash_client::ObjectRef> {
+        self.create(task_name, value)
+            .await
+            .map(|job| ::dash_client::ObjectRef {
+                name: job.name_any(),
+                namespace: job
+                    .namespace()
+                    .unwrap_or_else(|| self.session.namespace.clone()),
+            })
+    }
+
     #[instrument(level = Level::INFO, skip_all, fields(task_name = %task.name_any()), err(Display))]
     pub async fn create_raw(
         &self,
@@ -110,6 +148,31 @@ impl<'a> DashProviderClient<'a> {
         }
     }
 
+    /// Delete all jobs of the task, returning the number of deleted jobs.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn delete_by_task(&self, task_name: &str) -> Result<u32> {
+        let dp = DeleteParams::default();
+        let lp = ListParams {
+            label_selector: Some(format!(
+                "{key}={value}",
+                key = DashJobCrd::LABEL_TARGET_TASK,
+                value = task_name,
+            )),
+            ..Default::default()
+        };
+        self.api
+            .delete_collection(&dp, &lp)
+            .await
+            // NOTE: the jobs have finalizers, so the deleting objects are returned
+            .map(|deleted| {
+                deleted
+                    .left()
+                    .map(|list| list.items.len().try_into().unwrap_or(u32::MAX))
+                    .unwrap_or_default()
+            })
+            .map_err(|error| anyhow!("failed to delete jobs ({task_name}): {error}"))
+    }
+
     #[instrument(level = Level::INFO, skip(self), err(Display))]
     async fn force_delete(&self, task_name: &str, job_name: &str) -> Result<()> {
         let dp = DeleteParams::default();
@@ -164,70 +227,187 @@ impl<'a> DashProviderClient<'a> {
             .map_err(|error| anyhow!("failed to list jobs ({task_name}): {error}"))
     }
 
+    /// Return the phase of the job, taking the backing pod into account.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn get_status(&self, task_name: &str, job_name: &str) -> Result<DashJobPhase> {
+        let job = match self.get(task_name, job_name).await? {
+            Some(job) => job,
+            None => bail!("no such job: {task_name:?} => {job_name:?}"),
+        };
+        let state = match job.status.as_ref() {
+            Some(status) => status.state,
+            None => return Ok(DashJobPhase::Pending),
+        };
+
+        match state {
+            DashJobState::Pending => Ok(DashJobPhase::Pending),
+            DashJobState::Completed => Ok(DashJobPhase::Succeeded),
+            DashJobState::Deleting => Ok(DashJobPhase::Unknown),
+            DashJobState::Error | DashJobState::Running => {
+                let pod = match self.get_pods(task_name, job_name, job).await {
+                    Ok(JobPods { pods, .. }) => pods.into_iter().next(),
+                    // NOTE: the job may not be backed by a K8S job
+                    Err(_) => None,
+                };
+                let pod_status = pod.and_then(|pod| pod.status);
+                let pod_phase = pod_status.as_ref().and_then(|status| status.phase.clone());
+
+                match (state, pod_phase.as_deref()) {
+                    (_, Some("Failed")) | (DashJobState::Error, _) => Ok(DashJobPhase::Failed {
+                        reason: pod_status
+                            .and_then(|status| status.message.or(status.reason))
+                            .unwrap_or_else(|| state.to_string()),
+                    }),
+                    (_, Some("Succeeded")) => Ok(DashJobPhase::Succeeded),
+                    (_, Some("Pending")) => Ok(DashJobPhase::Pending),
+                    (_, Some("Running") | None) => Ok(DashJobPhase::Running),
+                    (_, Some(_)) => Ok(DashJobPhase::Unknown),
+                }
+            }
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self), err(Display))]
     pub async fn get_stream_logs(
         &self,
         task_name: &str,
         job_name: &str,
     ) -> Result<impl Stream<Item = Result<String, ::std::io::Error>>> {
-        match self.get(task_name, job_name).await? {
-            Some(job) => {
-                match job
-                    .status
-                    .and_then(|status| status.channel)
-                    .map(|channel| channel.actor)
-                {
-                    Some(TaskChannelKind::Job(TaskChannelKindJob {
-                        metadata:
-                            TaskActorJobMetadata {
-                                container,
-                                label_selector,
-                            },
-                        ..
-                    })) => {
-                        let api =
-                            Api::<Pod>::namespaced(self.client.clone(), &self.session.namespace);
+        let job = match self.get(task_name, job_name).await? {
+            Some(job) => job,
+            None => bail!("no such job: {task_name:?} => {job_name:?}"),
+        };
+        let JobPods {
+            api,
+            container,
+            pods,
+        } = self.get_pods(task_name, job_name, job).await?;
+        let pod_name = match pods.first() {
+            Some(pod) => pod.name_any(),
+            None => bail!("no such jod's pod: {task_name:?} => {job_name:?}"),
+        };
 
-                        let lp = ListParams {
-                            label_selector: label_selector.match_labels.map(|match_labels| {
-                                match_labels
-                                    .into_iter()
-                                    .map(|(key, value)| format!("{key}={value}"))
-                                    .join(",")
-                            }),
-                            ..Default::default()
-                        };
-                        let pod_name = match api.list(&lp).await {
-                            Ok(list) if !list.items.is_empty() => list.items[0].name_any(),
-                            Ok(_) => {
-                                bail!("no such jod's pod: {task_name:?} => {job_name:?}")
-                            }
-                            Err(error) => bail!(
-                                "failed to find job's pod ({task_name} => {job_name}): {error}"
-                            ),
-                        };
+        let lp = LogParams {
+            container,
+            follow: true,
+            pretty: true,
+            ..Default::default()
+        };
+        api.log_stream(&pod_name, &lp)
+            .await
+            .map(|stream| stream.lines())
+            .map_err(|error| anyhow!("failed to get job logs ({task_name} => {job_name}): {error}"))
+    }
 
-                        let lp = LogParams {
-                            container: container.clone(),
-                            follow: true,
-                            pretty: true,
-                            ..Default::default()
-                        };
-                        api.log_stream(&pod_name, &lp)
-                            .await
-                            .map(|stream| stream.lines())
-                            .map_err(|error| {
-                                anyhow!(
-                                    "failed to get job logs ({task_name} => {job_name}): {error}"
-                                )
-                            })
+    /// Follow the logs of all pods of the job, prefixing each line with the pod name.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn get_stream_logs_all(
+        &self,
+        task_name: &str,
+        job_name: &str,
+    ) -> Result<impl Stream<Item = Result<String, ::std::io::Error>>> {
+        let job = match self.get(task_name, job_name).await? {
+            Some(job) => job,
+            None => bail!("no such job: {task_name:?} => {job_name:?}"),
+        };
+        let JobPods {
+            api,
+            container,
+            pods,
+        } = self.get_pods(task_name, job_name, job).await?;
+        if pods.is_empty() {
+            bail!("no such jod's pod: {task_name:?} => {job_name:?}")
+        }
+
+        let lp = LogParams {
+            container,
+            follow: true,
+            pretty: true,
+            ..Default::default()
+        };
+        let streams = pods
+            .iter()
+            .map(|pod| pod.name_any())
+            .map(|pod_name| {
+                let api = &api;
+                let lp = &lp;
+                async move {
+                    match api.log_stream(&pod_name, lp).await {
+                        Ok(stream) => Ok(stream
+                            .lines()
+                            .map_ok(move |line| format!("[{pod_name}] {line}"))
+                            .boxed()),
+                        Err(error) => bail!(
+                            "failed to get job logs ({task_name} => {job_name} => {pod_name}): {error}"
+                        ),
                     }
-                    None => {
-                        bail!("only the K8S job can be watched: {task_name:?} => {job_name:?}")
+                }
+            })
+            .collect::<FuturesOrdered<_>>()
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(select_all(streams))
+    }
+
+    /// Follow the job logs with a handle to stop following them.
+    ///
+    /// Aborting the handle ends the stream on its next poll, so the consumer
+    /// drops it and releases the underlying apiserver connection.
+    /// Dropping the stream without the handle still works as a fallback.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn get_stream_logs_with_cancel(
+        &self,
+        task_name: &str,
+        job_name: &str,
+    ) -> Result<(
+        impl Stream<Item = Result<String, ::std::io::Error>>,
+        AbortHandle,
+    )> {
+        self.get_stream_logs(task_name, job_name)
+            .await
+            .map(abortable)
+    }
+
+    #[instrument(level = Level::INFO, skip(self, job), err(Display))]
+    async fn get_pods(&self, task_name: &str, job_name: &str, job: DashJobCrd) -> Result<JobPods> {
+        match job
+            .status
+            .and_then(|status| status.channel)
+            .map(|channel| channel.actor)
+        {
+            Some(TaskChannelKind::Job(TaskChannelKindJob {
+                metadata:
+                    TaskActorJobMetadata {
+                        container,
+                        label_selector,
+                    },
+                ..
+            })) => {
+                let api = Api::<Pod>::namespaced(self.client.clone(), &self.session.namespace);
+
+                let lp = ListParams {
+                    label_selector: label_selector.match_labels.map(|match_labels| {
+                        match_labels
+                            .into_iter()
+                            .map(|(key, value)| format!("{key}={value}"))
+                            .join(",")
+                    }),
+                    ..Default::default()
+                };
+                match api.list(&lp).await {
+                    Ok(list) => Ok(JobPods {
+                        api,
+                        container,
+                        pods: list.items,
+                    }),
+                    Err(error) => {
+                        bail!("failed to find job's pod ({task_name} => {job_name}): {error}")
                     }
                 }
             }
-            None => bail!("no such job: {task_name:?} => {job_name:?}"),
+            None => {
+                bail!("only the K8S job can be watched: {task_name:?} => {job_name:?}")
+            }
         }
     }
 
@@ -254,3 +434,19 @@ impl<'a> DashProviderClient<'a> {
         }
     }
 }
+
+struct JobPods {
+    api: Api<Pod>,
+    container: Option<String>,
+    pods: Vec<Pod>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "phase")]
+pub enum DashJobPhase {
+    Pending,
+    Running,
+    Succeeded,
+    Failed { reason: String },
+    Unknown,
+}
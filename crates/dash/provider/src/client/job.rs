@@ -1,8 +1,13 @@
-use std::future::Future;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    path::PathBuf,
+};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use dash_api::task::{TaskActorJobSpec, TaskActorSourceSpec};
 use dash_provider_api::job::{TaskActorJobMetadata, TaskChannelKindJob, TemplateRef};
+use k8s_openapi::api::core::v1::Namespace;
 use kube::{
     api::{DeleteParams, Patch, PatchParams, PostParams},
     core::DynamicObject,
@@ -10,8 +15,9 @@ use kube::{
     Api, Client,
 };
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use tera::{Context, Tera};
-use tracing::{instrument, Level};
+use tracing::{info, instrument, Level};
 
 use crate::storage::KubernetesStorageClient;
 
@@ -78,6 +84,56 @@ impl TaskActorJobClient {
         })
     }
 
+    /// Load the templates matching any of the globs, named by their file names.
+    ///
+    /// If several templates share a file name, the one matching the earliest glob takes precedence.
+    pub fn from_globs<I>(
+        metadata: TaskActorJobMetadata,
+        namespace: String,
+        kube: Client,
+        globs: I,
+        use_prefix: bool,
+    ) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut files = Vec::default();
+        let mut names = BTreeSet::default();
+        for pattern in globs {
+            let pattern = pattern.as_ref();
+            let mut paths = ::glob::glob(pattern)
+                .map_err(|error| anyhow!("invalid template glob {pattern:?}: {error}"))?
+                .collect::<Result<Vec<PathBuf>, _>>()
+                .map_err(|error| anyhow!("failed to read template glob {pattern:?}: {error}"))?;
+            paths.sort();
+
+            for path in paths.into_iter().filter(|path| path.is_file()) {
+                let name = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) => name.to_string(),
+                    None => bail!("invalid template file name: {path:?}"),
+                };
+                if names.insert(name.clone()) {
+                    files.push((path, Some(name)));
+                }
+            }
+        }
+
+        let mut tera = Tera::default();
+        tera.add_template_files(files)
+            .map_err(|error| anyhow!("failed to parse templates: {error}"))?;
+        tera.autoescape_on(vec![".yaml.j2"]);
+
+        Ok(Self {
+            kube,
+            metadata,
+            name: Default::default(),
+            namespace,
+            tera,
+            use_prefix,
+        })
+    }
+
     pub fn from_raw_content(
         kube: Client,
         metadata: TaskActorJobMetadata,
@@ -109,6 +165,11 @@ impl TaskActorJobClient {
         self.namespace.as_str()
     }
 
+    /// Return the names of the loaded templates.
+    pub fn template_names(&self) -> impl Iterator<Item = &str> {
+        self.tera.get_template_names()
+    }
+
     #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
     pub async fn exists<Spec>(&self, input: &SessionContext<Spec>) -> Result<bool>
     where
@@ -131,6 +192,25 @@ impl TaskActorJobClient {
         Ok(true)
     }
 
+    /// Return whether all the templates are already applied with the same content.
+    #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
+    pub async fn is_applied_named<Spec>(
+        &self,
+        name: &str,
+        input: &SessionContext<Spec>,
+    ) -> Result<bool>
+    where
+        Spec: Serialize,
+    {
+        for template in self.load_template(name, input).await? {
+            match template.api.get_opt(&template.name).await? {
+                Some(applied) if get_hash(&applied) == get_hash(&template.template) => continue,
+                Some(_) | None => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
     #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
     pub async fn create<Spec>(&self, input: &SessionContext<Spec>) -> Result<TaskChannelKindJob>
     where
@@ -151,6 +231,49 @@ impl TaskActorJobClient {
         self.execute_with(name, input, try_create).await
     }
 
+    /// Validate the templates on the apiserver without persisting them,
+    /// returning the would-be-applied objects.
+    ///
+    /// The namespaced objects are skipped while their namespace is pending,
+    /// as the apiserver cannot validate them before it is created.
+    #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
+    pub async fn dry_run_named<Spec>(
+        &self,
+        name: &str,
+        input: &SessionContext<Spec>,
+    ) -> Result<Vec<DynamicObject>>
+    where
+        Spec: Serialize,
+    {
+        let api = Api::<Namespace>::all(self.kube.clone());
+        let mut namespaces = BTreeMap::default();
+
+        let mut objects = vec![];
+        for template in self.load_template(name, input).await? {
+            if let Some(namespace) = template.namespace.as_ref() {
+                let exists = match namespaces.get(namespace) {
+                    Some(&exists) => exists,
+                    None => {
+                        let exists = api.get_opt(namespace).await?.is_some();
+                        namespaces.insert(namespace.clone(), exists);
+                        exists
+                    }
+                };
+                if !exists {
+                    info!(
+                        "skipping the dry-run of {name:?}: namespace {namespace:?} is pending",
+                        name = template.name,
+                    );
+                    continue;
+                }
+            }
+
+            let exists = template.api.get_opt(&template.name).await?.is_some();
+            objects.push(apply(template, exists, true).await?);
+        }
+        Ok(objects)
+    }
+
     #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
     pub async fn delete<Spec>(&self, input: &SessionContext<Spec>) -> Result<TaskChannelKindJob>
     where
@@ -171,6 +294,24 @@ impl TaskActorJobClient {
         self.execute_with(name, input, try_delete).await
     }
 
+    /// Render the templates without touching the cluster.
+    #[instrument(level = Level::INFO, skip(self, input), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
+    pub fn render_named<Spec>(
+        &self,
+        name: &str,
+        input: &SessionContext<Spec>,
+    ) -> Result<Vec<DynamicObject>>
+    where
+        Spec: Serialize,
+    {
+        let context = Context::from_serialize(input)?;
+        let templates = self.tera.render(name, &context)?;
+        ::serde_yaml::Deserializer::from_str(&templates)
+            .map(::serde::Deserialize::deserialize)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
     #[instrument(level = Level::INFO, skip(self, input, f), fields(metadata.name = %input.metadata.name, metadata.namespace = %input.metadata.namespace), err(Display))]
     async fn execute_with<Spec, F, Fut>(
         &self,
@@ -208,16 +349,20 @@ impl TaskActorJobClient {
     where
         Spec: Serialize,
     {
-        let context = Context::from_serialize(input)?;
-        let templates = self.tera.render(name, &context)?;
-        let templates: Vec<DynamicObject> = ::serde_yaml::Deserializer::from_str(&templates)
-            .map(::serde::Deserialize::deserialize)
-            .collect::<Result<_, _>>()?;
+        let templates = self.render_named(name, input)?;
 
         // create templates
 
         let mut apis = vec![];
         for mut template in templates {
+            // Mark the content to detect the unchanged templates
+            let hash = format!("{:x}", Sha256::digest(::serde_json::to_vec(&template)?));
+            template
+                .metadata
+                .annotations
+                .get_or_insert_with(Default::default)
+                .insert(ANNOTATION_TEMPLATE_HASH.into(), hash);
+
             let name = {
                 let prefix = &input.metadata.name;
                 let name = template.metadata.name.get_or_insert_with(|| prefix.clone());
@@ -278,14 +423,18 @@ impl TaskActorJobClient {
             );
 
             // Use the discovered kind in an Api, and Controller with the ApiResource as its DynamicType
-            let api: Api<DynamicObject> = match caps.scope {
-                Scope::Cluster => Api::all_with(self.kube.clone(), &ar),
-                Scope::Namespaced => Api::namespaced_with(self.kube.clone(), namespace, &ar),
+            let (api, namespace) = match caps.scope {
+                Scope::Cluster => (Api::all_with(self.kube.clone(), &ar), None),
+                Scope::Namespaced => (
+                    Api::namespaced_with(self.kube.clone(), namespace, &ar),
+                    Some(namespace.clone()),
+                ),
             };
             apis.push(Template {
                 api,
                 immutable,
                 name: name.clone(),
+                namespace,
                 template,
             });
         }
@@ -293,11 +442,24 @@ impl TaskActorJobClient {
     }
 }
 
+const ANNOTATION_TEMPLATE_HASH: &str = "dash.ulagbulag.io/template-hash";
+
+fn get_hash(object: &DynamicObject) -> Option<&str> {
+    object
+        .metadata
+        .annotations
+        .as_ref()?
+        .get(ANNOTATION_TEMPLATE_HASH)
+        .map(|hash| hash.as_str())
+}
+
 #[derive(Debug)]
 struct Template {
     api: Api<DynamicObject>,
     immutable: bool,
     name: String,
+    /// The namespace of the object, if it is namespaced
+    namespace: Option<String>,
     template: DynamicObject,
 }
 
@@ -311,13 +473,19 @@ impl From<&Template> for TemplateRef {
 
 #[instrument(level = Level::INFO, skip(template), fields(template.name = %template.name), err(Display))]
 async fn try_create(template: Template, exists: bool) -> Result<()> {
+    apply(template, exists, false).await.map(|_| ())
+}
+
+#[instrument(level = Level::INFO, skip(template), fields(template.name = %template.name), err(Display))]
+async fn apply(template: Template, exists: bool, dry_run: bool) -> Result<DynamicObject> {
     if exists {
         // Skip applying to immutable resources
         if template.immutable {
-            return Ok(());
+            return Ok(template.template);
         }
 
         let pp = PatchParams {
+            dry_run,
             field_manager: Some(crate::NAME.into()),
             force: true,
             ..Default::default()
@@ -327,19 +495,17 @@ async fn try_create(template: Template, exists: bool) -> Result<()> {
             .api
             .patch(&template.name, &pp, &Patch::Apply(&template.template))
             .await
-            .map(|_| ())
             .map_err(Into::into)
     } else {
         let pp = PostParams {
+            dry_run,
             field_manager: Some(crate::NAME.into()),
-            ..Default::default()
         };
 
         template
             .api
             .create(&pp, &template.template)
             .await
-            .map(|_| ())
             .map_err(Into::into)
     }
 }
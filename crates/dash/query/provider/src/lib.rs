@@ -2,29 +2,37 @@ mod arrow;
 mod function;
 
 use std::{
-    collections::{btree_map::Keys, BTreeMap},
-    ops,
+    collections::{BTreeMap, BTreeSet},
+    fmt, ops,
     sync::Arc,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
 use clap::Parser;
 use dash_api::{
     function::{FunctionCrd, FunctionSpec, FunctionState},
+    model::ModelCrd,
     model_storage_binding::{ModelStorageBindingCrd, ModelStorageBindingState},
-    storage::ModelStorageKindSpec,
+    storage::{ModelStorageKind, ModelStorageKindSpec},
 };
 use dash_pipe_api::storage::StorageS3Args;
 pub use dash_pipe_provider::{deltalake, Name};
 use dash_pipe_provider::{
     deltalake::{
-        arrow::{compute::concat_batches, datatypes::Schema, record_batch::RecordBatch},
-        datafusion::execution::context::SessionContext,
+        arrow::{
+            compute::concat_batches, datatypes::Schema, record_batch::RecordBatch,
+            util::pretty::pretty_format_batches,
+        },
+        datafusion::{datasource::empty::EmptyTable, execution::context::SessionContext},
         delta_datafusion::DataFusionMixins,
         DeltaTable,
     },
     messengers::{init_messenger, Messenger, MessengerArgs},
-    schema::arrow::decoder::TryIntoTableDecoder,
+    schema::{
+        arrow::decoder::TryIntoTableDecoder,
+        deltalake::{FieldColumns, ToField},
+    },
     storage::{
         deltalake::{StorageSessionContext, StorageTableState},
         Stream,
@@ -40,6 +48,7 @@ use inflector::Inflector;
 use itertools::Itertools;
 use kube::{api::ListParams, Api, Client, ResourceExt};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::RwLock;
 use tracing::{info, instrument, warn, Level};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Parser)]
@@ -47,6 +56,17 @@ pub struct QueryClientArgs {
     #[command(flatten)]
     pub messenger: MessengerArgs,
 
+    /// Register the uninitialized models as empty tables, if their schemas are known
+    #[arg(long, env = "DASH_QUERY_INCLUDE_UNINITED")]
+    #[serde(default)]
+    pub include_uninited: bool,
+
+    /// Register the models on their first reference in a query, instead of on startup.
+    /// The models of the functions are still registered on startup.
+    #[arg(long, env = "DASH_QUERY_LAZY")]
+    #[serde(default)]
+    pub lazy: bool,
+
     /// Set a target namespace
     #[arg(long, env = "DASH_NAMESPACE", value_name = "NAMESPACE")]
     pub namespace: Option<String>,
@@ -55,7 +75,17 @@ pub struct QueryClientArgs {
 #[derive(Clone)]
 pub struct QueryClient {
     ctx: SessionContext,
-    tables: BTreeMap<String, Arc<DeltaTable>>,
+    loader: Option<LazyModelLoader>,
+    tables: Arc<RwLock<BTreeMap<String, Arc<DeltaTable>>>>,
+    unsupported: Arc<RwLock<BTreeMap<String, UnsupportedModel>>>,
+}
+
+#[derive(Clone)]
+struct LazyModelLoader {
+    include_uninited: bool,
+    kube: Client,
+    messenger: Arc<dyn Messenger>,
+    namespace: String,
 }
 
 impl QueryClient {
@@ -71,50 +101,273 @@ impl QueryClient {
 
         let ctx = SessionContext::default();
         let mut tables = BTreeMap::default();
+        let mut unsupported = BTreeMap::default();
 
         // load messenger
-        let messenger = init_messenger(&args.messenger).await?;
+        let messenger: Arc<dyn Messenger> = init_messenger(&args.messenger).await?.into();
 
         // load models
-        for (model, storage, args) in load_models(&kube, namespace).await? {
-            if tables.contains_key(&model) {
-                continue;
-            }
-
-            info!("Loading model: {model}");
-            let args = args.await?;
-            let (name, table, state) = ctx.register_table_with_name(&args, &model, None).await?;
-
-            match state {
-                StorageTableState::Inited => {
-                    tables.insert(name, table);
-                }
-                StorageTableState::Uninited => {
-                    warn!("Model {model:?} is not inited yet on {storage:?}; skipping...");
-                }
-            }
-        }
+        // NOTE: on lazy mode, only the models of the functions are loaded,
+        //       so that the queries calling the functions alone can be planned
+        let function_models = if args.lazy {
+            Some(load_function_models(&kube, namespace).await?)
+        } else {
+            None
+        };
+        register_models(
+            &ctx,
+            &mut tables,
+            &mut unsupported,
+            &kube,
+            namespace,
+            args.include_uninited,
+            |model| match &function_models {
+                Some(models) => models.contains(&model.to_snake_case()),
+                None => true,
+            },
+        )
+        .await?;
 
         // load functions after loading models
         for function in load_functions(&kube, messenger.as_ref(), &tables, namespace).await? {
             ctx.register_udf(function.into());
         }
 
-        Ok(Self { ctx, tables })
+        let loader = args.lazy.then(|| LazyModelLoader {
+            include_uninited: args.include_uninited,
+            kube,
+            messenger,
+            namespace: namespace.to_string(),
+        });
+        Ok(Self {
+            ctx,
+            loader,
+            tables: Arc::new(RwLock::new(tables)),
+            unsupported: Arc::new(RwLock::new(unsupported)),
+        })
+    }
+
+    /// Return the names of the registered tables.
+    ///
+    /// On lazy mode, only the tables of the functions and the ones referenced so far are listed.
+    pub async fn list_table_names(&self) -> Vec<String> {
+        self.tables.read().await.keys().cloned().collect()
     }
 
-    pub fn list_table_names(&self) -> Keys<'_, String, Arc<DeltaTable>> {
-        self.tables.keys()
+    /// Return the models which are skipped as their storages are not queryable.
+    ///
+    /// On lazy mode, only the models referenced so far are listed.
+    pub async fn list_unsupported_models(&self) -> Vec<UnsupportedModel> {
+        self.unsupported.read().await.values().cloned().collect()
+    }
+
+    pub async fn resolve_table_name(&self, name: &str) -> Result<String> {
+        let tables = self.tables.read().await;
+        resolve_table_name(&tables, name)
+            .map(ToString::to_string)
+            .ok_or_else(|| {
+                anyhow!(
+                    "no such table: {name:?}; available tables: [{names}]",
+                    names = tables.keys().join(", "),
+                )
+            })
     }
 
     #[instrument(level = Level::INFO, skip(self), err(Display))]
     pub async fn sql(&self, sql: &str) -> Result<DataFrame> {
+        if let Some(loader) = &self.loader {
+            self.register_referenced_models(loader, sql).await?;
+        }
+        self.register_table_aliases(sql).await?;
+
         self.ctx
             .sql(sql)
             .await
             .map_err(|error| anyhow!("failed to query object metadata: {error}"))
     }
 
+    /// Query the tables as of the given Delta version or timestamp.
+    ///
+    /// The referenced tables are registered on a separate context,
+    /// so the latest snapshots remain untouched.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn sql_as_of(&self, sql: &str, as_of: TableVersion) -> Result<DataFrame> {
+        if let Some(loader) = &self.loader {
+            self.register_referenced_models(loader, sql).await?;
+        }
+
+        let ctx = SessionContext::default();
+        for function in self.ctx.state().scalar_functions().values() {
+            ctx.register_udf(function.as_ref().clone());
+        }
+
+        let tables = self.tables.read().await;
+        for reference in self.get_referenced_table_names(sql)? {
+            let Some((name, table)) =
+                resolve_table_name(&tables, &reference).and_then(|name| tables.get_key_value(name))
+            else {
+                continue;
+            };
+
+            let mut table = table.as_ref().clone();
+            match as_of {
+                TableVersion::Version(version) => table.load_version(version).await,
+                TableVersion::Timestamp(timestamp) => table.load_with_datetime(timestamp).await,
+            }
+            .map_err(|error| {
+                anyhow!(
+                    "failed to load table {name:?} as of {as_of}; the requested version may predate the table's history: {error}"
+                )
+            })?;
+            // NOTE: registered as referenced, as the planner looks it up
+            ctx.register_table(reference.as_str(), Arc::new(table))
+                .map_err(|error| {
+                    anyhow!("failed to register table {name:?} as of {as_of}: {error}")
+                })?;
+        }
+
+        ctx.sql(sql)
+            .await
+            .map_err(|error| anyhow!("failed to query object metadata as of {as_of}: {error}"))
+    }
+
+    fn get_referenced_table_names(&self, sql: &str) -> Result<Vec<String>> {
+        let state = self.ctx.state();
+        let statement = state
+            .sql_to_statement(sql, "generic")
+            .map_err(|error| anyhow!("failed to parse query: {error}"))?;
+        let references = state
+            .resolve_table_references(&statement)
+            .map_err(|error| anyhow!("failed to resolve referenced tables: {error}"))?;
+
+        Ok(references
+            .iter()
+            .map(|reference| reference.table())
+            .unique()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    /// Register the referenced tables under the names the planner looks up,
+    /// e.g. `mymodel` of `SELECT * FROM MyModel` for the `my_model` table.
+    async fn register_table_aliases(&self, sql: &str) -> Result<()> {
+        let tables = self.tables.read().await;
+        for reference in self.get_referenced_table_names(sql)? {
+            let Some(name) = resolve_table_name(&tables, &reference) else {
+                continue;
+            };
+            if name == reference || self.ctx.table_exist(reference.as_str())? {
+                continue;
+            }
+
+            let table = self
+                .ctx
+                .table_provider(name)
+                .await
+                .map_err(|error| anyhow!("failed to load table {name:?}: {error}"))?;
+            self.ctx
+                .register_table(reference.as_str(), table)
+                .map_err(|error| {
+                    anyhow!("failed to register table {name:?} as {reference:?}: {error}")
+                })?;
+        }
+        Ok(())
+    }
+
+    #[instrument(level = Level::INFO, skip(self, loader), err(Display))]
+    async fn register_referenced_models(&self, loader: &LazyModelLoader, sql: &str) -> Result<()> {
+        let references = self.get_referenced_table_names(sql)?;
+
+        let mut tables = self.tables.write().await;
+        let mut unsupported = self.unsupported.write().await;
+        let missing: Vec<_> = references
+            .into_iter()
+            .filter(|name| resolve_table_name(&tables, name).is_none())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        // load models
+        let LazyModelLoader {
+            include_uninited,
+            kube,
+            messenger,
+            namespace,
+        } = loader;
+        register_models(
+            &self.ctx,
+            &mut tables,
+            &mut unsupported,
+            kube,
+            namespace,
+            *include_uninited,
+            |model| {
+                let model = normalize_table_name(model);
+                missing
+                    .iter()
+                    .any(|name| normalize_table_name(name) == model)
+            },
+        )
+        .await?;
+
+        let unknown: Vec<_> = missing
+            .iter()
+            .filter(|name| resolve_table_name(&tables, name).is_none())
+            .collect();
+        if let Some(model) = unknown.iter().find_map(|name| {
+            let name = normalize_table_name(name);
+            unsupported
+                .values()
+                .find(|model| normalize_table_name(&model.name) == name)
+        }) {
+            bail!("{model}")
+        }
+        if !unknown.is_empty() {
+            bail!(
+                "no such table(s): [{unknown}]; are the models ready?",
+                unknown = unknown.iter().join(", "),
+            )
+        }
+
+        // reload functions after loading models
+        for function in load_functions(kube, messenger.as_ref(), &tables, namespace).await? {
+            self.ctx.register_udf(function.into());
+        }
+        Ok(())
+    }
+
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn explain(&self, sql: &str) -> Result<String> {
+        self.explain_with(sql, false).await
+    }
+
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn explain_analyze(&self, sql: &str) -> Result<String> {
+        self.explain_with(sql, true).await
+    }
+
+    async fn explain_with(&self, sql: &str, analyze: bool) -> Result<String> {
+        let sql = if analyze {
+            format!("EXPLAIN ANALYZE {sql}")
+        } else {
+            format!("EXPLAIN {sql}")
+        };
+
+        let records = self
+            .sql(&sql)
+            .await?
+            .collect()
+            .await
+            .map_err(|error| anyhow!("failed to explain query plan: {error}"))?;
+
+        pretty_format_batches(&records)
+            .map(|plan| plan.to_string())
+            .map_err(|error| anyhow!("failed to render query plan: {error}"))
+    }
+
+    /// Decode each record batch into values as it arrives, so that the rows
+    /// can be consumed before the query completes.
     #[instrument(level = Level::INFO, skip(self), err(Display))]
     pub async fn sql_and_decode<Value>(&self, sql: &str) -> Result<Stream<Value>>
     where
@@ -147,6 +400,36 @@ impl QueryClient {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TableVersion {
+    Version(i64),
+    Timestamp(DateTime<Utc>),
+}
+
+impl fmt::Display for TableVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Version(version) => write!(f, "version {version}"),
+            Self::Timestamp(timestamp) => write!(f, "{timestamp}"),
+        }
+    }
+}
+
+/// A model which cannot be queried, as its storage kind is not supported yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedModel {
+    pub name: String,
+    pub kind: ModelStorageKind,
+}
+
+impl fmt::Display for UnsupportedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { name, kind } = self;
+        write!(f, "model {name} uses {kind} storage which isn't queryable")
+    }
+}
+
 impl ops::Deref for QueryClient {
     type Target = SessionContext;
 
@@ -161,11 +444,14 @@ async fn load_models<'a>(
     namespace: &'a str,
 ) -> Result<
     impl Iterator<
-            Item = (
-                String,
-                String,
-                impl Future<Output = Result<StorageS3Args>> + 'a,
-            ),
+            Item = Result<
+                (
+                    String,
+                    String,
+                    impl Future<Output = Result<StorageS3Args>> + 'a,
+                ),
+                UnsupportedModel,
+            >,
         > + 'a,
 > {
     let api = Api::<ModelStorageBindingCrd>::namespaced(kube.clone(), namespace);
@@ -192,11 +478,10 @@ async fn load_models<'a>(
             let storage = match storage.kind {
                 ModelStorageKindSpec::ObjectStorage(spec) => spec,
                 storage => {
-                    warn!(
-                        "Sorry, but the {kind:?} is not supported yet: {model_name}",
-                        kind = storage.to_kind(),
-                    );
-                    return None;
+                    return Some(Err(UnsupportedModel {
+                        name: model_name,
+                        kind: storage.to_kind(),
+                    }))
                 }
             };
 
@@ -226,10 +511,135 @@ async fn load_models<'a>(
                 }
             };
 
-            Some((model_name, storage_name, args))
+            Some(Ok((model_name, storage_name, args)))
         }))
 }
 
+#[instrument(level = Level::INFO, skip(ctx, tables, unsupported, kube, filter), err(Display))]
+async fn register_models(
+    ctx: &SessionContext,
+    tables: &mut BTreeMap<String, Arc<DeltaTable>>,
+    unsupported: &mut BTreeMap<String, UnsupportedModel>,
+    kube: &Client,
+    namespace: &str,
+    include_uninited: bool,
+    filter: impl Fn(&str) -> bool,
+) -> Result<()> {
+    for item in load_models(kube, namespace).await? {
+        let (model, storage, args) = match item {
+            Ok(item) => item,
+            Err(model) => {
+                if filter(&model.name) {
+                    warn!("Sorry, but the {model} yet; skipping...");
+                    unsupported.insert(model.name.clone(), model);
+                }
+                continue;
+            }
+        };
+        if tables.contains_key(&model) || !filter(&model) {
+            continue;
+        }
+
+        info!("Loading model: {model}");
+        let args = args.await?;
+        let (name, table, state) = ctx.register_table_with_name(&args, &model, None).await?;
+
+        match state {
+            StorageTableState::Inited => {
+                tables.insert(name, table);
+            }
+            StorageTableState::Uninited if include_uninited => {
+                match load_model_schema(kube, namespace, &model).await? {
+                    Some(schema) => {
+                        info!("Model {model:?} is not inited yet on {storage:?}; registering as empty...");
+                        ctx.deregister_table(name.as_str())?;
+                        ctx.register_table(name.as_str(), Arc::new(EmptyTable::new(schema)))?;
+                        tables.insert(name, table);
+                    }
+                    None => {
+                        warn!("Model {model:?} is not inited yet on {storage:?} and its schema is unknown; skipping...");
+                    }
+                }
+            }
+            StorageTableState::Uninited => {
+                warn!("Model {model:?} is not inited yet on {storage:?}; skipping...");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[instrument(level = Level::INFO, skip(kube), err(Display))]
+async fn load_model_schema(
+    kube: &Client,
+    namespace: &str,
+    model: &str,
+) -> Result<Option<Arc<Schema>>> {
+    let api = Api::<ModelCrd>::namespaced(kube.clone(), namespace);
+    let fields = match api
+        .get_opt(model)
+        .await?
+        .and_then(|model| model.status)
+        .and_then(|status| status.fields)
+    {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+
+    let fields = fields
+        .to_data_columns()?
+        .iter()
+        .map(ToField::to_field)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(Arc::new(Schema::new(fields))))
+}
+
+fn resolve_table_name<'a>(
+    tables: &'a BTreeMap<String, Arc<DeltaTable>>,
+    name: &str,
+) -> Option<&'a str> {
+    if let Some((key, _)) = tables.get_key_value(name) {
+        return Some(key);
+    }
+
+    let name_snake_case = name.to_snake_case();
+    if let Some((key, _)) = tables.get_key_value(&name_snake_case) {
+        return Some(key);
+    }
+
+    let name_normalized = normalize_table_name(name);
+    tables
+        .keys()
+        .find(|key| normalize_table_name(key) == name_normalized)
+        .map(|key| key.as_str())
+}
+
+/// Compare the table names regardless of their cases and separators,
+/// as the planner lowercases the unquoted identifiers, e.g. `MyModel` into `mymodel`.
+fn normalize_table_name(name: &str) -> String {
+    name.to_snake_case().replace('_', "")
+}
+
+/// Return the snake-cased names of the input and output models of the ready functions.
+#[instrument(level = Level::INFO, skip(kube), err(Display))]
+async fn load_function_models(kube: &Client, namespace: &str) -> Result<BTreeSet<String>> {
+    let api = Api::<FunctionCrd>::namespaced(kube.clone(), namespace);
+    let lp = ListParams::default();
+    let functions = api.list(&lp).await?.items;
+
+    Ok(functions
+        .into_iter()
+        .filter(|function| {
+            function
+                .status
+                .as_ref()
+                .is_some_and(|status| matches!(status.state, FunctionState::Ready))
+        })
+        .flat_map(|function| [function.spec.input, function.spec.output])
+        .map(|model| model.to_snake_case())
+        .collect())
+}
+
 #[instrument(level = Level::INFO, skip(kube, messenger, tables), err(Display))]
 async fn load_functions(
     kube: &Client,
@@ -241,7 +651,7 @@ async fn load_functions(
         tables: &BTreeMap<String, Arc<DeltaTable>>,
         name: &str,
     ) -> Option<Arc<Schema>> {
-        let table = tables.get(&name.to_snake_case())?;
+        let table = tables.get(resolve_table_name(tables, name)?)?;
         match table
             .snapshot()
             .and_then(|snapshot| snapshot.arrow_schema())
@@ -297,3 +707,69 @@ async fn load_functions(
         .into_iter()
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn build_client(name: &str) -> QueryClient {
+        let table = deltalake::DeltaOps::new_in_memory()
+            .create()
+            .with_table_name(name)
+            .with_columns([deltalake::kernel::StructField::new(
+                "id",
+                deltalake::kernel::DataType::Primitive(deltalake::kernel::PrimitiveType::Long),
+                false,
+            )])
+            .await
+            .expect("failed to create a table");
+        let table = Arc::new(table);
+
+        let ctx = SessionContext::default();
+        ctx.register_table(name, table.clone())
+            .expect("failed to register a table");
+
+        let tables = [(name.to_string(), table)].into_iter().collect();
+        QueryClient {
+            ctx,
+            loader: None,
+            tables: Arc::new(RwLock::new(tables)),
+            unsupported: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sql_mixed_case_table_name() {
+        let client = build_client("my_model").await;
+
+        for sql in [
+            "SELECT id FROM MyModel",
+            "SELECT id FROM MY_MODEL",
+            "SELECT id FROM my_model",
+        ] {
+            let df = client.sql(sql).await.expect(sql);
+            let columns: Vec<_> = df
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| field.name().clone())
+                .collect();
+            assert_eq!(columns, ["id"], "{sql}");
+
+            let records = df.collect().await.expect(sql);
+            assert_eq!(records.iter().map(RecordBatch::num_rows).sum::<usize>(), 0);
+        }
+
+        // The planner looks up the referenced table as lowercased
+        assert!(client.ctx.table_exist("mymodel").unwrap());
+        assert_eq!(client.list_table_names().await, ["my_model"]);
+    }
+
+    #[tokio::test]
+    async fn sql_unknown_table_name() {
+        let client = build_client("my_model").await;
+
+        assert!(client.sql("SELECT id FROM OtherModel").await.is_err());
+        assert!(client.resolve_table_name("OtherModel").await.is_err());
+    }
+}
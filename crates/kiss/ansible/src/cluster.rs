@@ -42,6 +42,30 @@ impl<'a> ClusterState<'a> {
         })
     }
 
+    /// Build the cluster state from the given boxes, without calling the apiserver.
+    pub fn from_boxes(
+        config: &'a KissConfig,
+        owner: &'a BoxSpec,
+        boxes: &[BoxCrd],
+        load_workers: bool,
+    ) -> Self {
+        let cluster_name = &owner.group.cluster_name;
+        Self {
+            config: Cow::Borrowed(config),
+            control_planes: ClusterBoxGroup::from_control_planes(boxes.to_vec(), cluster_name),
+            owner_group: Cow::Borrowed(&owner.group),
+            owner_uuid: owner.machine.uuid,
+            workers: if load_workers {
+                Some(ClusterBoxGroup::from_worker_nodes(
+                    boxes.to_vec(),
+                    cluster_name,
+                ))
+            } else {
+                None
+            },
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(kube), err(Display))]
     pub async fn load_current_cluster(
         kube: &'a Client,
@@ -228,46 +252,42 @@ impl ClusterBoxGroup {
     async fn load_control_planes(kube: &Client, cluster_name: &str) -> Result<Self, Error> {
         let api = Api::<BoxCrd>::all(kube.clone());
         let lp = ListParams::default();
-        Ok(Self {
-            nodes: api
-                .list(&lp)
-                .await?
-                .items
-                .into_iter()
-                .filter(|r#box| {
-                    r#box.spec.group.cluster_name == cluster_name
-                        && r#box.spec.group.role == BoxGroupRole::ControlPlane
-                })
-                .map(|r#box| {
-                    let key = ClusterBoxState::from_box(&r#box);
-                    let value = r#box;
-                    (key, value)
-                })
-                .collect(),
-        })
+        let boxes = api.list(&lp).await?.items;
+        Ok(Self::from_control_planes(boxes, cluster_name))
     }
 
     #[instrument(level = Level::INFO, err(Display), skip(kube))]
     async fn load_worker_nodes(kube: &Client, cluster_name: &str) -> Result<Self, Error> {
         let api = Api::<BoxCrd>::all(kube.clone());
         let lp = ListParams::default();
-        Ok(Self {
-            nodes: api
-                .list(&lp)
-                .await?
-                .items
+        let boxes = api.list(&lp).await?.items;
+        Ok(Self::from_worker_nodes(boxes, cluster_name))
+    }
+
+    fn from_control_planes(boxes: Vec<BoxCrd>, cluster_name: &str) -> Self {
+        Self::from_boxes(boxes, |group| {
+            group.cluster_name == cluster_name && group.role == BoxGroupRole::ControlPlane
+        })
+    }
+
+    fn from_worker_nodes(boxes: Vec<BoxCrd>, cluster_name: &str) -> Self {
+        Self::from_boxes(boxes, |group| {
+            group.cluster_name == cluster_name && group.role != BoxGroupRole::ControlPlane
+        })
+    }
+
+    fn from_boxes(boxes: Vec<BoxCrd>, filter: impl Fn(&BoxGroupSpec) -> bool) -> Self {
+        Self {
+            nodes: boxes
                 .into_iter()
-                .filter(|r#box| {
-                    r#box.spec.group.cluster_name == cluster_name
-                        && r#box.spec.group.role != BoxGroupRole::ControlPlane
-                })
+                .filter(|r#box| filter(&r#box.spec.group))
                 .map(|r#box| {
                     let key = ClusterBoxState::from_box(&r#box);
                     let value = r#box;
                     (key, value)
                 })
                 .collect(),
-        })
+        }
     }
 
     fn contains(&self, uuid: Uuid) -> bool {
@@ -1,7 +1,8 @@
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
-use anyhow::{anyhow, Error, Result};
-use ipnet::Ipv4Net;
+use anyhow::{anyhow, Result};
+use ark_core::env::ConfigLoader;
+use ipnet::{Ipv4Net, Ipv6Net};
 use k8s_openapi::api::core::v1::ConfigMap;
 use kube::{Api, Client};
 use tracing::{instrument, Level};
@@ -10,6 +11,9 @@ use tracing::{instrument, Level};
 pub struct KissConfig {
     pub allow_critical_commands: bool,
     pub allow_pruning_network_interfaces: bool,
+    pub ansible_field_manager: String,
+    pub ansible_service_account: String,
+    pub ansible_starting_deadline_seconds: i64,
     pub bootstrapper_network_dns_server_ns1: Ipv4Addr,
     pub bootstrapper_network_dns_server_ns2: Ipv4Addr,
     pub etcd_nodes_max: usize,
@@ -26,64 +30,104 @@ pub struct KissConfig {
     pub network_ipv4_dhcp_range_end: Ipv4Addr,
     pub network_ipv4_gateway: Ipv4Addr,
     pub network_ipv4_subnet: Ipv4Net,
+    pub network_ipv6_dhcp_range_begin: Option<Ipv6Addr>,
+    pub network_ipv6_dhcp_range_end: Option<Ipv6Addr>,
+    pub network_ipv6_gateway: Option<Ipv6Addr>,
+    pub network_ipv6_subnet: Option<Ipv6Net>,
     pub network_nameserver_incluster_ipv4: Ipv4Addr,
     pub os_default: String,
     pub os_kernel: String,
 }
 
 impl KissConfig {
+    pub const DEFAULT_ANSIBLE_FIELD_MANAGER: &'static str = "kiss-ansible";
+    pub const DEFAULT_ANSIBLE_SERVICE_ACCOUNT: &'static str = "ansible-playbook";
+    pub const DEFAULT_ANSIBLE_STARTING_DEADLINE_SECONDS: i64 = 180; // 3m
+
     #[instrument(level = Level::INFO, skip(kube), err(Display))]
     pub async fn try_default(kube: &Client) -> Result<Self> {
         let ns = ::kiss_api::consts::NAMESPACE;
         let api = Api::<ConfigMap>::namespaced(kube.clone(), ns);
-        let config = api.get("kiss-config").await?;
+        let configmap = api.get("kiss-config").await?;
+
+        let mut config = ConfigLoader::from_data(configmap.data.unwrap_or_default());
+        let allow_critical_commands = config.infer("allow_critical_commands");
+        let allow_pruning_network_interfaces = config.infer("allow_pruning_network_interfaces");
+        let ansible_field_manager = config.infer_or_else("ansible_field_manager", || {
+            Self::DEFAULT_ANSIBLE_FIELD_MANAGER.into()
+        });
+        let ansible_service_account = config.infer_or_else("ansible_service_account", || {
+            Self::DEFAULT_ANSIBLE_SERVICE_ACCOUNT.into()
+        });
+        let ansible_starting_deadline_seconds = config
+            .infer_or_else("ansible_starting_deadline_seconds", || {
+                Self::DEFAULT_ANSIBLE_STARTING_DEADLINE_SECONDS
+            });
+        let bootstrapper_network_dns_server_ns1 =
+            config.try_infer("bootstrapper_network_dns_server_ns1");
+        let bootstrapper_network_dns_server_ns2 =
+            config.try_infer("bootstrapper_network_dns_server_ns2");
+        let etcd_nodes_max = config.infer("etcd_nodes_max");
+        let group_enable_default_cluster = config.infer("group_enable_default_cluster");
+        let group_enforce_ansible_control_planes =
+            config.infer("group_enforce_ansible_control_planes");
+        let group_force_reset = config.infer("group_force_reset");
+        let group_force_reset_os = config.infer("group_force_reset_os");
+        let group_reset_storage = config.infer("group_reset_storage");
+        let kiss_cluster_name = config.infer("kiss_cluster_name");
+        let kubespray_image = config.infer("kubespray_image");
+        let network_interface_mtu_size = config.infer("network_interface_mtu_size");
+        let network_ipv4_dhcp_duration = config.infer("network_ipv4_dhcp_duration");
+        let network_ipv4_dhcp_range_begin = config.try_infer("network_ipv4_dhcp_range_begin");
+        let network_ipv4_dhcp_range_end = config.try_infer("network_ipv4_dhcp_range_end");
+        let network_ipv4_gateway = config.try_infer("network_ipv4_gateway");
+        let network_ipv4_subnet = config.try_infer("network_ipv4_subnet");
+        let network_ipv6_dhcp_range_begin = config.infer_opt("network_ipv6_dhcp_range_begin");
+        let network_ipv6_dhcp_range_end = config.infer_opt("network_ipv6_dhcp_range_end");
+        let network_ipv6_gateway = config.infer_opt("network_ipv6_gateway");
+        let network_ipv6_subnet = config.infer_opt("network_ipv6_subnet");
+        let network_nameserver_incluster_ipv4 =
+            config.try_infer("network_nameserver_incluster_ipv4");
+        let os_default = config.infer("os_default");
+        let os_kernel = config.infer("os_kernel");
+        config.finish()?;
 
         Ok(Self {
-            allow_critical_commands: infer(&config, "allow_critical_commands")?,
-            allow_pruning_network_interfaces: infer(&config, "allow_pruning_network_interfaces")?,
-            bootstrapper_network_dns_server_ns1: infer(
-                &config,
-                "bootstrapper_network_dns_server_ns1",
-            )?,
-            bootstrapper_network_dns_server_ns2: infer(
-                &config,
-                "bootstrapper_network_dns_server_ns2",
-            )?,
-            etcd_nodes_max: infer(&config, "etcd_nodes_max")?,
-            group_enable_default_cluster: infer(&config, "group_enable_default_cluster")?,
-            group_enforce_ansible_control_planes: infer(
-                &config,
-                "group_enforce_ansible_control_planes",
-            )?,
-            group_force_reset: infer(&config, "group_force_reset")?,
-            group_force_reset_os: infer(&config, "group_force_reset_os")?,
-            group_reset_storage: infer(&config, "group_reset_storage")?,
-            kiss_cluster_name: infer(&config, "kiss_cluster_name")?,
-            kubespray_image: infer(&config, "kubespray_image")?,
-            network_interface_mtu_size: infer(&config, "network_interface_mtu_size")?,
-            network_ipv4_dhcp_duration: infer(&config, "network_ipv4_dhcp_duration")?,
-            network_ipv4_dhcp_range_begin: infer(&config, "network_ipv4_dhcp_range_begin")?,
-            network_ipv4_dhcp_range_end: infer(&config, "network_ipv4_dhcp_range_end")?,
-            network_ipv4_gateway: infer(&config, "network_ipv4_gateway")?,
-            network_ipv4_subnet: infer(&config, "network_ipv4_subnet")?,
-            network_nameserver_incluster_ipv4: infer(&config, "network_nameserver_incluster_ipv4")?,
-            os_default: infer(&config, "os_default")?,
-            os_kernel: infer(&config, "os_kernel")?,
+            allow_critical_commands,
+            allow_pruning_network_interfaces,
+            ansible_field_manager,
+            ansible_service_account,
+            ansible_starting_deadline_seconds,
+            bootstrapper_network_dns_server_ns1: required(bootstrapper_network_dns_server_ns1)?,
+            bootstrapper_network_dns_server_ns2: required(bootstrapper_network_dns_server_ns2)?,
+            etcd_nodes_max,
+            group_enable_default_cluster,
+            group_enforce_ansible_control_planes,
+            group_force_reset,
+            group_force_reset_os,
+            group_reset_storage,
+            kiss_cluster_name,
+            kubespray_image,
+            network_interface_mtu_size,
+            network_ipv4_dhcp_duration,
+            network_ipv4_dhcp_range_begin: required(network_ipv4_dhcp_range_begin)?,
+            network_ipv4_dhcp_range_end: required(network_ipv4_dhcp_range_end)?,
+            network_ipv4_gateway: required(network_ipv4_gateway)?,
+            network_ipv4_subnet: required(network_ipv4_subnet)?,
+            network_ipv6_dhcp_range_begin,
+            network_ipv6_dhcp_range_end,
+            network_ipv6_gateway,
+            network_ipv6_subnet,
+            network_nameserver_incluster_ipv4: required(network_nameserver_incluster_ipv4)?,
+            os_default,
+            os_kernel,
         })
     }
 }
 
-pub fn infer<K: AsRef<str>, R>(config: &ConfigMap, key: K) -> Result<R>
-where
-    R: ::core::str::FromStr,
-    <R as ::core::str::FromStr>::Err: Into<Error> + Send + Sync + 'static,
-{
-    let key = key.as_ref();
-
-    config
-        .data
-        .as_ref()
-        .and_then(|data| data.get(key))
-        .ok_or_else(|| anyhow!("failed to find the configuration variable: {key}"))
-        .and_then(|e| e.parse().map_err(Into::into))
+/// Unwrap a variable loaded by [`ConfigLoader::try_infer`].
+///
+/// The missing variables are already reported by [`ConfigLoader::finish`].
+fn required<R>(value: Option<R>) -> Result<R> {
+    value.ok_or_else(|| anyhow!("failed to load the configuration variable"))
 }
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, bail, Result};
+
+const MACROS: &[&str] = &[
+    "@annually",
+    "@daily",
+    "@hourly",
+    "@midnight",
+    "@monthly",
+    "@weekly",
+    "@yearly",
+];
+
+const MONTHS: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+const WEEKDAYS: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+struct CronField {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    aliases: &'static [&'static str],
+    allow_any: bool,
+}
+
+const FIELDS: [CronField; 5] = [
+    CronField {
+        name: "minute",
+        min: 0,
+        max: 59,
+        aliases: &[],
+        allow_any: false,
+    },
+    CronField {
+        name: "hour",
+        min: 0,
+        max: 23,
+        aliases: &[],
+        allow_any: false,
+    },
+    CronField {
+        name: "day of month",
+        min: 1,
+        max: 31,
+        aliases: &[],
+        allow_any: true,
+    },
+    CronField {
+        name: "month",
+        min: 1,
+        max: 12,
+        aliases: MONTHS,
+        allow_any: false,
+    },
+    CronField {
+        name: "day of week",
+        min: 0,
+        max: 7,
+        aliases: WEEKDAYS,
+        allow_any: true,
+    },
+];
+
+/// Validate a kubernetes `CronJob` schedule, naming the offending field on failure.
+pub fn validate(schedule: &str) -> Result<()> {
+    let schedule = schedule.trim();
+    if schedule.starts_with('@') {
+        return if MACROS.contains(&schedule.to_lowercase().as_str()) {
+            Ok(())
+        } else {
+            bail!("invalid cron schedule {schedule:?}: unknown macro")
+        };
+    }
+
+    let values: Vec<_> = schedule.split_whitespace().collect();
+    if values.len() != FIELDS.len() {
+        bail!(
+            "invalid cron schedule {schedule:?}: expected {expected} fields, but given {given}",
+            expected = FIELDS.len(),
+            given = values.len(),
+        );
+    }
+
+    FIELDS.iter().zip(values).try_for_each(|(field, value)| {
+        field.validate(value).map_err(|error| {
+            anyhow!(
+                "invalid cron schedule {schedule:?}: invalid {name} field {value:?}: {error}",
+                name = field.name,
+            )
+        })
+    })
+}
+
+impl CronField {
+    fn validate(&self, value: &str) -> Result<()> {
+        if self.allow_any && value == "?" {
+            return Ok(());
+        }
+        value
+            .split(',')
+            .try_for_each(|item| self.validate_item(item))
+    }
+
+    fn validate_item(&self, item: &str) -> Result<()> {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+
+        if let Some(step) = step {
+            match step.parse::<u32>() {
+                Ok(step) if step > 0 => (),
+                _ => bail!("invalid step: {step:?}"),
+            }
+        }
+
+        if range == "*" {
+            return Ok(());
+        }
+        match range.split_once('-') {
+            Some((begin, end)) => {
+                let begin = self.parse_value(begin)?;
+                let end = self.parse_value(end)?;
+                if begin > end {
+                    bail!("invalid range: {begin} > {end}");
+                }
+                Ok(())
+            }
+            None => self.parse_value(range).map(|_| ()),
+        }
+    }
+
+    fn parse_value(&self, value: &str) -> Result<u32> {
+        let offset = if self.aliases == MONTHS { 1 } else { 0 };
+        let value = match self
+            .aliases
+            .iter()
+            .position(|alias| alias.eq_ignore_ascii_case(value))
+        {
+            Some(index) => index as u32 + offset,
+            None => value
+                .parse()
+                .map_err(|_| anyhow!("not a number: {value:?}"))?,
+        };
+
+        if (self.min..=self.max).contains(&value) {
+            Ok(value)
+        } else {
+            bail!(
+                "out of range [{min}, {max}]: {value}",
+                min = self.min,
+                max = self.max,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_valid_schedules() {
+        for schedule in [
+            "@hourly",
+            "*/5 * * * *",
+            "0 0 1 JAN-MAR MON-FRI",
+            "0,30 9-17 ? * 1-5/2",
+        ] {
+            assert!(validate(schedule).is_ok(), "{schedule}");
+        }
+    }
+
+    #[test]
+    fn validate_invalid_schedules() {
+        for (schedule, field) in [
+            ("61 * * * *", "minute"),
+            ("* 24 * * *", "hour"),
+            ("* * 0 * *", "day of month"),
+            ("* * * FOO *", "month"),
+            ("* * * * 1-0", "day of week"),
+            ("*/0 * * * *", "minute"),
+        ] {
+            let error = validate(schedule).unwrap_err().to_string();
+            assert!(error.contains(field), "{schedule}: {error}");
+        }
+
+        assert!(validate("@fortnightly").is_err());
+        assert!(validate("* * * *").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 pub mod cluster;
 mod config;
+pub mod cron;
 pub mod job;
 
+use std::net::IpAddr;
+
 use anyhow::Result;
 use inflector::Inflector;
 use k8s_openapi::{
@@ -9,7 +12,7 @@ use k8s_openapi::{
         batch::v1::{CronJob, CronJobSpec, Job, JobSpec, JobTemplateSpec},
         core::v1::{
             ConfigMapKeySelector, ConfigMapVolumeSource, Container, EnvVar, EnvVarSource,
-            KeyToPath, PodDNSConfig, PodSpec, PodTemplateSpec, ResourceRequirements,
+            KeyToPath, Node, PodDNSConfig, PodSpec, PodTemplateSpec, ResourceRequirements,
             SecretKeySelector, SecretVolumeSource, Toleration, Volume, VolumeMount,
         },
     },
@@ -17,11 +20,13 @@ use k8s_openapi::{
 };
 use kiss_api::r#box::{BoxCrd, BoxGroupRole, BoxGroupSpec, BoxPowerType, BoxState};
 use kube::{
-    api::{DeleteParams, ListParams, PostParams},
+    api::{DeleteParams, ListParams, Patch, PatchParams, PostParams},
     core::ObjectMeta,
-    Api, Client, Error,
+    Api, Client, Error, ResourceExt,
 };
-use tracing::{info, instrument, Level};
+use serde_json::json;
+use tracing::{debug, info, instrument, warn, Level};
+use uuid::Uuid;
 
 pub struct AnsibleClient {
     pub kiss: self::config::KissConfig,
@@ -31,8 +36,12 @@ impl AnsibleClient {
     pub const LABEL_BOX_NAME: &'static str = "kiss.ulagbulag.io/box_name";
     pub const LABEL_BOX_MACHINE_UUID: &'static str = "kiss.ulagbulag.io/box_machine_uuid";
     pub const LABEL_COMPLETED_STATE: &'static str = "kiss.ulagbulag.io/completed_state";
+    /// Shared by all the jobs of a logical operation, e.g. reset → join → configure
+    pub const LABEL_CORRELATION_ID: &'static str = "kiss.ulagbulag.io/correlation_id";
     pub const LABEL_JOB_NAME: &'static str = "kiss.ulagbulag.io/job_name";
     pub const LABEL_JOB_IS_CRITICAL: &'static str = "kiss.ulagbulag.io/is_critical";
+    /// Overrides the box's address to connect via SSH, e.g. on a management VLAN
+    pub const LABEL_SSH_HOST: &'static str = "kiss.ulagbulag.io/ssh_host";
     pub const LABEL_VERIFY_BIND_GROUP: &'static str = "kiss.ulagbulag.io/verify-bind-group";
 
     #[instrument(level = Level::INFO, skip_all, err(Display))]
@@ -43,28 +52,23 @@ impl AnsibleClient {
     }
 
     #[instrument(level = Level::INFO, skip(self, kube, job), err(Display))]
-    pub async fn spawn(&self, kube: &Client, job: AnsibleJob<'_>) -> Result<bool, Error> {
+    pub async fn spawn(&self, kube: &Client, job: AnsibleJob<'_>) -> Result<SpawnOutcome, Error> {
+        // validate the job before touching any resources
+        if let Some(schedule) = job.cron {
+            self::cron::validate(schedule).map_err(|error| Error::Service(error.into()))?;
+        }
+
         let ns = ::kiss_api::consts::NAMESPACE;
         let box_name = job.r#box.spec.machine.uuid.to_string();
-        let box_status = job.r#box.status.as_ref();
+        let correlation_id = job.correlation_id.unwrap_or_else(Uuid::new_v4);
+        let job = AnsibleJob {
+            correlation_id: Some(correlation_id),
+            ..job
+        };
         let name = format!("box-{}-{}", &job.task, &box_name);
 
-        let verify_bind_group = job
-            .r#box
-            .metadata
-            .labels
-            .as_ref()
-            .and_then(|labels| labels.get(Self::LABEL_VERIFY_BIND_GROUP))
-            .and_then(|value| value.parse().ok())
-            .unwrap_or(true);
-
-        let bind_group = job
-            .r#box
-            .status
-            .as_ref()
-            .and_then(|status| status.bind_group.as_ref());
         let group = &job.r#box.spec.group;
-        let reset = self.kiss.group_force_reset || verify_bind_group && bind_group != Some(group);
+        let reset = self.is_reset(&job);
 
         let priority_class_name = match group.role {
             BoxGroupRole::ControlPlane => "system-cluster-critical",
@@ -94,6 +98,58 @@ impl AnsibleClient {
             }
         }
 
+        // NOTE: the critical jobs survive the deletion above, so their stale
+        // completed states should not be applied to the reprovisioned box.
+        if reset {
+            let lp = ListParams {
+                label_selector: Some(format!(
+                    "{}={box_name},{}",
+                    AnsibleClient::LABEL_BOX_NAME,
+                    AnsibleClient::LABEL_COMPLETED_STATE,
+                )),
+                ..Default::default()
+            };
+            let label = Self::LABEL_COMPLETED_STATE;
+            let patch = Patch::Merge(json!({
+                "metadata": {
+                    "labels": {
+                        label: null,
+                    },
+                },
+            }));
+            let pp = PatchParams {
+                field_manager: Some(self.kiss.ansible_field_manager.clone()),
+                ..Default::default()
+            };
+
+            // clear the completed states of all remaining cronjobs
+            {
+                let api = Api::<CronJob>::namespaced(kube.clone(), ns);
+                for cronjob in api.list_metadata(&lp).await? {
+                    api.patch(&cronjob.name_any(), &pp, &patch).await?;
+                }
+            }
+            // clear the completed states of all remaining jobs
+            {
+                let api = Api::<Job>::namespaced(kube.clone(), ns);
+                for job in api.list_metadata(&lp).await? {
+                    api.patch(&job.name_any(), &pp, &patch).await?;
+                }
+            }
+        }
+
+        // the box's node should exist to run the job on itself
+        let affinity = if job.run_on_target {
+            let api = Api::<Node>::all(kube.clone());
+            if api.get_opt(&box_name).await?.is_none() {
+                let error = format!("failed to find the target node: {box_name}");
+                return Err(Error::Service(error.into()));
+            }
+            crate::job::affinity_on_node(&box_name)
+        } else {
+            crate::job::affinity()
+        };
+
         // realize mutual exclusivity (QUEUE)
         let cluster_state =
             self::cluster::ClusterState::load(kube, &self.kiss, &job.r#box.spec, job.use_workers)
@@ -107,11 +163,17 @@ impl AnsibleClient {
                     &box_name,
                     &job.r#box.spec.group.cluster_name,
                 );
-                return Ok(false);
+                return Ok(SpawnOutcome::DeferredNotJoinable);
             }
         }
 
         // define the object
+        let env = self.build_env(&job, &cluster_state);
+        debug!(
+            "non-secret env of {name}: {env:?}",
+            env = env.non_secret_env().collect::<Vec<_>>(),
+        );
+
         let metadata = ObjectMeta {
             name: Some(name.clone()),
             namespace: Some(ns.into()),
@@ -122,6 +184,10 @@ impl AnsibleClient {
                         Self::LABEL_BOX_MACHINE_UUID.into(),
                         job.r#box.spec.machine.uuid.to_string(),
                     )),
+                    Some((
+                        Self::LABEL_CORRELATION_ID.into(),
+                        correlation_id.to_string(),
+                    )),
                     Some((
                         Self::LABEL_JOB_IS_CRITICAL.into(),
                         job.is_critical.to_string(),
@@ -148,7 +214,7 @@ impl AnsibleClient {
                     ..Default::default()
                 }),
                 spec: Some(PodSpec {
-                    affinity: Some(crate::job::affinity()),
+                    affinity: Some(affinity),
                     dns_config: Some(PodDNSConfig {
                         nameservers: Some(vec![
                             self.kiss.bootstrapper_network_dns_server_ns1.to_string(),
@@ -159,7 +225,7 @@ impl AnsibleClient {
                     host_network: Some(true),
                     priority_class_name: Some(priority_class_name.into()),
                     restart_policy: Some("OnFailure".into()),
-                    service_account: Some("ansible-playbook".into()),
+                    service_account: Some(self.kiss.ansible_service_account.clone()),
                     tolerations: if job.is_critical {
                         Some(vec![
                             Toleration {
@@ -194,323 +260,7 @@ impl AnsibleClient {
                             "/root/ansible/hosts.yaml".into(),
                             format!("/opt/playbook/{}", group.role.to_playbook()),
                         ]),
-                        env: Some(vec![
-                            EnvVar {
-                                name: "ansible_host".into(),
-                                value: Some(job.r#box.spec.machine.hostname()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "ansible_host_id".into(),
-                                value: Some(box_name.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "ansible_host_uuid".into(),
-                                value: Some(job.r#box.spec.machine.uuid.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "ansible_ssh_host".into(),
-                                value: box_status
-                                    .and_then(|status| status.access.management())
-                                    .map(|interface| interface.address.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "ansible_ssh_private_key_file".into(),
-                                value: Some("/root/.ssh/id_ed25519".into()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "ansible_user".into(),
-                                value_from: Some(EnvVarSource {
-                                    config_map_key_ref: Some(ConfigMapKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "auth_ssh_username".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_allow_critical_commands".into(),
-                                value: Some(self.kiss.allow_critical_commands.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_allow_pruning_network_interfaces".into(),
-                                value: Some(self.kiss.allow_pruning_network_interfaces.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_ansible_task_name".into(),
-                                value: Some(job.task.into()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_control_planes".into(),
-                                value: Some(
-                                    if matches!(job.new_state, None | Some(BoxState::Joining)) {
-                                        cluster_state.get_control_planes_as_string()
-                                    } else {
-                                        Default::default()
-                                    },
-                                ),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_etcd_nodes".into(),
-                                value: Some(
-                                    if matches!(job.new_state, None | Some(BoxState::Joining)) {
-                                        cluster_state.get_etcd_nodes_as_string()
-                                    } else {
-                                        Default::default()
-                                    },
-                                ),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_name".into(),
-                                value: Some(group.cluster_name.clone()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_name_snake_case".into(),
-                                value: Some(group.cluster_name.to_snake_case()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_domain".into(),
-                                value: Some(group.cluster_domain()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_is_default".into(),
-                                value: Some(group.is_default().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_is_new".into(),
-                                value: Some(cluster_state.is_new().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_cluster_worker_nodes".into(),
-                                value: Some(if job.use_workers {
-                                    cluster_state.get_worker_nodes_as_string()
-                                } else {
-                                    Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_enable_default_cluster".into(),
-                                value: Some(self.kiss.group_enable_default_cluster.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_force_reset".into(),
-                                value: Some(reset.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_force_reset_os".into(),
-                                value: Some(self.kiss.group_force_reset_os.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_reset_storage".into(),
-                                value: Some(self.kiss.group_reset_storage.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_role".into(),
-                                value: Some(group.role.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_role_is_domain_specific".into(),
-                                value: Some(group.role.is_domain_specific().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_group_role_is_member".into(),
-                                value: Some(group.role.is_member().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_interface_mtu_size".into(),
-                                value: Some(self.kiss.network_interface_mtu_size.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_dhcp_duration".into(),
-                                value: Some(self.kiss.network_ipv4_dhcp_duration.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_dhcp_range_begin".into(),
-                                value: Some(self.kiss.network_ipv4_dhcp_range_begin.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_dhcp_range_end".into(),
-                                value: Some(self.kiss.network_ipv4_dhcp_range_end.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_gateway".into(),
-                                value: Some(self.kiss.network_ipv4_gateway.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_subnet".into(),
-                                value: Some(self.kiss.network_ipv4_subnet.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_subnet_address".into(),
-                                value: Some(self.kiss.network_ipv4_subnet.network().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_subnet_mask".into(),
-                                value: Some(self.kiss.network_ipv4_subnet.netmask().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_ipv4_subnet_mask_prefix".into(),
-                                value: Some(self.kiss.network_ipv4_subnet.prefix_len().to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_nameserver_incluster_ipv4".into(),
-                                value: Some(
-                                    self.kiss.network_nameserver_incluster_ipv4.to_string(),
-                                ),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_wireless_wifi_key_mgmt".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "network_wireless_wifi_key_mgmt".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_wireless_wifi_key_psk".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "network_wireless_wifi_key_psk".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_network_wireless_wifi_ssid".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "network_wireless_wifi_ssid".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_os_default".into(),
-                                value: Some(self.kiss.os_default.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_os_kernel".into(),
-                                value: Some(self.kiss.os_kernel.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_intel_amt_host".into(),
-                                value: job
-                                    .r#box
-                                    .spec
-                                    .power
-                                    .as_ref()
-                                    .filter(|power| matches!(power.r#type, BoxPowerType::IntelAMT))
-                                    .and_then(|power| power.address.as_ref())
-                                    .map(|address| address.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_intel_amt_username".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "power_intel_amt_username".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_intel_amt_password".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "power_intel_amt_password".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_ipmi_host".into(),
-                                value: job
-                                    .r#box
-                                    .spec
-                                    .power
-                                    .as_ref()
-                                    .filter(|power| matches!(power.r#type, BoxPowerType::Ipmi))
-                                    .and_then(|power| power.address.as_ref())
-                                    .map(|address| address.to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_ipmi_username".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "power_ipmi_username".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "kiss_power_ipmi_password".into(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: "kiss-config".into(),
-                                        key: "power_ipmi_password".into(),
-                                        ..Default::default()
-                                    }),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            },
-                        ]),
+                        env: Some(env.into_inner()),
                         resources: Some(job.resource_type.into()),
                         volume_mounts: Some(vec![
                             VolumeMount {
@@ -601,7 +351,7 @@ impl AnsibleClient {
         };
         let pp = PostParams {
             dry_run: false,
-            field_manager: Some("kiss-ansible".into()),
+            field_manager: Some(self.kiss.ansible_field_manager.clone()),
         };
 
         match job.cron {
@@ -612,7 +362,9 @@ impl AnsibleClient {
                     spec: Some(CronJobSpec {
                         concurrency_policy: Some("Replace".into()),
                         schedule: schedule.into(),
-                        starting_deadline_seconds: Some(180 /* 3m */),
+                        starting_deadline_seconds: Some(
+                            self.kiss.ansible_starting_deadline_seconds,
+                        ),
                         job_template: JobTemplateSpec {
                             metadata: Some(metadata),
                             spec: Some(spec),
@@ -634,8 +386,461 @@ impl AnsibleClient {
             }
         }
 
-        info!("spawned a job: {name}");
-        Ok(true)
+        info!("spawned a job: {name} (correlation id: {correlation_id})");
+        Ok(SpawnOutcome::Spawned {
+            name,
+            correlation_id,
+        })
+    }
+
+    /// Build the environment variables of an ansible job without spawning it,
+    /// e.g. for auditing a provisioning run.
+    ///
+    /// Nothing is sent to the apiserver; the cluster state is given by the caller,
+    /// e.g. with [`ClusterState::from_boxes`](self::cluster::ClusterState::from_boxes).
+    pub fn build_env(
+        &self,
+        job: &AnsibleJob<'_>,
+        cluster_state: &self::cluster::ClusterState<'_>,
+    ) -> AnsibleJobEnv {
+        let box_name = job.r#box.spec.machine.uuid.to_string();
+        let box_status = job.r#box.status.as_ref();
+        let group = &job.r#box.spec.group;
+        let reset = self.is_reset(job);
+
+        let ssh_host = job
+            .r#box
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(Self::LABEL_SSH_HOST))
+            .and_then(|value| match value.parse::<IpAddr>() {
+                Ok(address) => Some(address),
+                Err(error) => {
+                    warn!("failed to parse the ssh host of {box_name}: {error}");
+                    None
+                }
+            })
+            .or_else(|| {
+                box_status
+                    .and_then(|status| status.access.management())
+                    .map(|interface| interface.address)
+            });
+
+        AnsibleJobEnv(vec![
+            EnvVar {
+                name: "ansible_host".into(),
+                value: Some(job.r#box.spec.machine.hostname()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "ansible_host_id".into(),
+                value: Some(box_name),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "ansible_host_uuid".into(),
+                value: Some(job.r#box.spec.machine.uuid.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "ansible_ssh_host".into(),
+                value: ssh_host.map(|address| address.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "ansible_ssh_private_key_file".into(),
+                value: Some("/root/.ssh/id_ed25519".into()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "ansible_user".into(),
+                value_from: Some(EnvVarSource {
+                    config_map_key_ref: Some(ConfigMapKeySelector {
+                        name: "kiss-config".into(),
+                        key: "auth_ssh_username".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_correlation_id".into(),
+                value: job.correlation_id.map(|id| id.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_allow_critical_commands".into(),
+                value: Some(self.kiss.allow_critical_commands.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_allow_pruning_network_interfaces".into(),
+                value: Some(self.kiss.allow_pruning_network_interfaces.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_ansible_task_name".into(),
+                value: Some(job.task.into()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_control_planes".into(),
+                value: Some(if matches!(job.new_state, None | Some(BoxState::Joining)) {
+                    cluster_state.get_control_planes_as_string()
+                } else {
+                    Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_etcd_nodes".into(),
+                value: Some(if matches!(job.new_state, None | Some(BoxState::Joining)) {
+                    cluster_state.get_etcd_nodes_as_string()
+                } else {
+                    Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_name".into(),
+                value: Some(group.cluster_name.clone()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_name_snake_case".into(),
+                value: Some(group.cluster_name.to_snake_case()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_domain".into(),
+                value: Some(group.cluster_domain()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_is_default".into(),
+                value: Some(group.is_default().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_is_new".into(),
+                value: Some(cluster_state.is_new().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_cluster_worker_nodes".into(),
+                value: Some(if job.use_workers {
+                    cluster_state.get_worker_nodes_as_string()
+                } else {
+                    Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_enable_default_cluster".into(),
+                value: Some(self.kiss.group_enable_default_cluster.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_force_reset".into(),
+                value: Some(reset.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_force_reset_os".into(),
+                value: Some(self.kiss.group_force_reset_os.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_reset_storage".into(),
+                value: Some(self.kiss.group_reset_storage.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_role".into(),
+                value: Some(group.role.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_role_is_domain_specific".into(),
+                value: Some(group.role.is_domain_specific().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_group_role_is_member".into(),
+                value: Some(group.role.is_member().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_interface_mtu_size".into(),
+                value: Some(self.kiss.network_interface_mtu_size.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_dhcp_duration".into(),
+                value: Some(self.kiss.network_ipv4_dhcp_duration.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_dhcp_range_begin".into(),
+                value: Some(self.kiss.network_ipv4_dhcp_range_begin.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_dhcp_range_end".into(),
+                value: Some(self.kiss.network_ipv4_dhcp_range_end.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_gateway".into(),
+                value: Some(self.kiss.network_ipv4_gateway.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_subnet".into(),
+                value: Some(self.kiss.network_ipv4_subnet.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_subnet_address".into(),
+                value: Some(self.kiss.network_ipv4_subnet.network().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_subnet_mask".into(),
+                value: Some(self.kiss.network_ipv4_subnet.netmask().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv4_subnet_mask_prefix".into(),
+                value: Some(self.kiss.network_ipv4_subnet.prefix_len().to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_dhcp_range_begin".into(),
+                value: Some(to_string_or_empty(self.kiss.network_ipv6_dhcp_range_begin)),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_dhcp_range_end".into(),
+                value: Some(to_string_or_empty(self.kiss.network_ipv6_dhcp_range_end)),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_gateway".into(),
+                value: Some(to_string_or_empty(self.kiss.network_ipv6_gateway)),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_subnet".into(),
+                value: Some(to_string_or_empty(self.kiss.network_ipv6_subnet)),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_subnet_address".into(),
+                value: Some(to_string_or_empty(
+                    self.kiss.network_ipv6_subnet.map(|subnet| subnet.network()),
+                )),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_subnet_mask".into(),
+                value: Some(to_string_or_empty(
+                    self.kiss.network_ipv6_subnet.map(|subnet| subnet.netmask()),
+                )),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_ipv6_subnet_mask_prefix".into(),
+                value: Some(to_string_or_empty(
+                    self.kiss
+                        .network_ipv6_subnet
+                        .map(|subnet| subnet.prefix_len()),
+                )),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_nameserver_incluster_ipv4".into(),
+                value: Some(self.kiss.network_nameserver_incluster_ipv4.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_wireless_wifi_key_mgmt".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "network_wireless_wifi_key_mgmt".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_wireless_wifi_key_psk".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "network_wireless_wifi_key_psk".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_network_wireless_wifi_ssid".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "network_wireless_wifi_ssid".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_os_default".into(),
+                value: Some(self.kiss.os_default.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_os_kernel".into(),
+                value: Some(self.kiss.os_kernel.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_intel_amt_host".into(),
+                value: job
+                    .r#box
+                    .spec
+                    .power
+                    .as_ref()
+                    .filter(|power| matches!(power.r#type, BoxPowerType::IntelAMT))
+                    .and_then(|power| power.address.as_ref())
+                    .map(|address| address.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_intel_amt_username".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "power_intel_amt_username".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_intel_amt_password".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "power_intel_amt_password".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_ipmi_host".into(),
+                value: job
+                    .r#box
+                    .spec
+                    .power
+                    .as_ref()
+                    .filter(|power| matches!(power.r#type, BoxPowerType::Ipmi))
+                    .and_then(|power| power.address.as_ref())
+                    .map(|address| address.to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_ipmi_username".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "power_ipmi_username".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "kiss_power_ipmi_password".into(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: "kiss-config".into(),
+                        key: "power_ipmi_password".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ])
+    }
+
+    fn is_reset(&self, job: &AnsibleJob<'_>) -> bool {
+        let verify_bind_group = job
+            .r#box
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(Self::LABEL_VERIFY_BIND_GROUP))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(true);
+
+        let bind_group = job
+            .r#box
+            .status
+            .as_ref()
+            .and_then(|status| status.bind_group.as_ref());
+        let group = &job.r#box.spec.group;
+        self.kiss.group_force_reset || verify_bind_group && bind_group != Some(group)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpawnOutcome {
+    Spawned {
+        name: String,
+        correlation_id: Uuid,
+    },
+    /// The target cluster is not ready to join a new box
+    DeferredNotJoinable,
+}
+
+impl SpawnOutcome {
+    pub const fn is_spawned(&self) -> bool {
+        matches!(self, Self::Spawned { .. })
+    }
+}
+
+/// The environment variables passed to an ansible job.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnsibleJobEnv(Vec<EnvVar>);
+
+impl AnsibleJobEnv {
+    /// Return the plaintext env pairs only, e.g. for auditing a provisioning run.
+    ///
+    /// The values sourced from the secrets or the config maps are excluded.
+    pub fn non_secret_env(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .filter_map(|env| match (&env.value, &env.value_from) {
+                (Some(value), None) => Some((env.name.as_str(), value.as_str())),
+                _ => None,
+            })
+    }
+
+    pub fn into_inner(self) -> Vec<EnvVar> {
+        self.0
     }
 }
 
@@ -647,7 +852,11 @@ pub struct AnsibleJob<'a> {
     pub new_state: Option<BoxState>,
     pub is_critical: bool,
     pub resource_type: AnsibleResourceType,
+    /// Run the playbook on the target box's own node.
+    pub run_on_target: bool,
     pub use_workers: bool,
+    /// Generated on spawn if not given; reuse it to group the jobs of a logical operation.
+    pub correlation_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -701,3 +910,142 @@ impl From<AnsibleResourceType> for ResourceRequirements {
         }
     }
 }
+
+fn to_string_or_empty<T>(value: Option<T>) -> String
+where
+    T: ToString,
+{
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn build_config() -> self::config::KissConfig {
+        self::config::KissConfig {
+            allow_critical_commands: false,
+            allow_pruning_network_interfaces: false,
+            ansible_field_manager: "kiss-ansible".into(),
+            ansible_service_account: "ansible-playbook".into(),
+            ansible_starting_deadline_seconds: 180,
+            bootstrapper_network_dns_server_ns1: "1.1.1.1".parse().unwrap(),
+            bootstrapper_network_dns_server_ns2: "1.0.0.1".parse().unwrap(),
+            etcd_nodes_max: 3,
+            group_enable_default_cluster: true,
+            group_enforce_ansible_control_planes: false,
+            group_force_reset: false,
+            group_force_reset_os: false,
+            group_reset_storage: false,
+            kiss_cluster_name: "default".into(),
+            kubespray_image: "kubespray:latest".into(),
+            network_interface_mtu_size: 1500,
+            network_ipv4_dhcp_duration: "7d".into(),
+            network_ipv4_dhcp_range_begin: "10.32.0.1".parse().unwrap(),
+            network_ipv4_dhcp_range_end: "10.47.255.254".parse().unwrap(),
+            network_ipv4_gateway: "10.47.255.254".parse().unwrap(),
+            network_ipv4_subnet: "10.32.0.0/12".parse().unwrap(),
+            network_ipv6_dhcp_range_begin: None,
+            network_ipv6_dhcp_range_end: None,
+            network_ipv6_gateway: None,
+            network_ipv6_subnet: None,
+            network_nameserver_incluster_ipv4: "10.64.0.3".parse().unwrap(),
+            os_default: "rocky9".into(),
+            os_kernel: "edge".into(),
+        }
+    }
+
+    fn build_box(uuid: Uuid, role: &str, address: &str, correlation_id: Option<Uuid>) -> BoxCrd {
+        ::serde_json::from_value(json!({
+            "apiVersion": "kiss.ulagbulag.io/v1alpha1",
+            "kind": "Box",
+            "metadata": {
+                "name": uuid.to_string(),
+            },
+            "spec": {
+                "group": {
+                    "clusterName": "default",
+                    "role": role,
+                },
+                "machine": {
+                    "uuid": uuid,
+                },
+            },
+            "status": {
+                "state": "Running",
+                "access": {
+                    "primary": {
+                        "address": address,
+                    },
+                },
+                "bindGroup": {
+                    "clusterName": "default",
+                    "role": role,
+                },
+                "correlationId": correlation_id,
+                "lastUpdated": "2024-01-01T00:00:00Z",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn build_env_of_box_with_job() {
+        let config = build_config();
+        let client = AnsibleClient {
+            kiss: config.clone(),
+        };
+
+        // NOTE: the box already has a job of the same name, which is never touched on preview
+        let correlation_id = Uuid::new_v4();
+        let control_plane = build_box(Uuid::new_v4(), "ControlPlane", "10.32.0.1", None);
+        let target = build_box(
+            Uuid::new_v4(),
+            "GenericWorker",
+            "10.32.0.2",
+            Some(correlation_id),
+        );
+        let boxes = [control_plane.clone(), target.clone()];
+        let cluster_state =
+            self::cluster::ClusterState::from_boxes(&config, &target.spec, &boxes, false);
+
+        let job = AnsibleJob {
+            cron: None,
+            task: "join",
+            r#box: &target,
+            new_group: None,
+            new_state: Some(BoxState::Joining),
+            is_critical: false,
+            resource_type: AnsibleResourceType::Normal,
+            run_on_target: false,
+            use_workers: false,
+            correlation_id: target.status.as_ref().unwrap().correlation_id,
+        };
+        let env = client.build_env(&job, &cluster_state);
+        let env: BTreeMap<_, _> = env.non_secret_env().collect();
+
+        let box_name = target.spec.machine.uuid.to_string();
+        let correlation_id = correlation_id.to_string();
+        let control_planes = format!(
+            "kube_control_plane:{}:10.32.0.1",
+            control_plane.spec.machine.uuid,
+        );
+        assert_eq!(env.get("ansible_host_id"), Some(&box_name.as_str()));
+        assert_eq!(env.get("ansible_ssh_host"), Some(&"10.32.0.2"));
+        assert_eq!(
+            env.get("kiss_correlation_id"),
+            Some(&correlation_id.as_str())
+        );
+        assert_eq!(
+            env.get("kiss_cluster_control_planes"),
+            Some(&control_planes.as_str()),
+        );
+        assert_eq!(env.get("kiss_group_force_reset"), Some(&"false"));
+
+        // The secrets are excluded
+        assert!(!env.contains_key("ansible_user"));
+        assert!(!env.contains_key("kiss_power_ipmi_password"));
+    }
+}
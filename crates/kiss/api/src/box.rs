@@ -114,6 +114,9 @@ pub struct BoxStatus {
     pub access: BoxAccessSpec,
     #[serde(default)]
     pub bind_group: Option<BoxGroupSpec>,
+    /// Shared by all the jobs of the current logical operation, e.g. reset → join → configure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
     pub last_updated: DateTime<Utc>,
 }
 
@@ -165,6 +168,11 @@ impl BoxState {
         }
     }
 
+    /// Whether the box should be reset, beginning a new logical operation.
+    pub const fn is_reset(&self) -> bool {
+        matches!(self, Self::GroupChanged | Self::Failed | Self::Disconnected)
+    }
+
     pub const fn next(&self) -> Self {
         match self {
             Self::New => Self::Commissioning,
@@ -1,11 +1,14 @@
 use anyhow::{bail, Result};
 use clap::{Parser, ValueEnum};
 use futures::{stream::FuturesUnordered, TryStreamExt};
-use kiss_ansible::{cluster::ClusterState, AnsibleClient, AnsibleJob, AnsibleResourceType};
+use kiss_ansible::{
+    cluster::ClusterState, AnsibleClient, AnsibleJob, AnsibleResourceType, SpawnOutcome,
+};
 use kiss_api::r#box::BoxCrd;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 use tracing::{info, instrument, Level};
+use uuid::Uuid;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Parser)]
 #[serde(rename_all = "kebab-case")]
@@ -24,14 +27,22 @@ impl ClusterUpgradeArgs {
         let kube = ::kube::Client::try_default().await?;
         let client = AnsibleClient::try_default(&kube).await?;
 
+        // NOTE: all the jobs of an upgrade share the same correlation id
+        let correlation_id = Uuid::new_v4();
+        info!("Upgrading cluster {group} (correlation id: {correlation_id})");
+
         info!("Selecting default cluster");
         let cluster = ClusterState::load_current_cluster(&kube, group.has_workers()).await?;
 
         let status = match group {
             ClusterUpgradeGroupType::ControlPlane => {
-                self.run_control_plane(&kube, client, cluster).await?
+                self.run_control_plane(&kube, client, cluster, correlation_id)
+                    .await?
+            }
+            ClusterUpgradeGroupType::Workers => {
+                self.run_workers(&kube, client, cluster, correlation_id)
+                    .await?
             }
-            ClusterUpgradeGroupType::Workers => self.run_workers(&kube, client, cluster).await?,
         };
 
         match status {
@@ -54,11 +65,12 @@ impl ClusterUpgradeArgs {
         kube: &::kube::Client,
         client: AnsibleClient,
         cluster: ClusterState<'a>,
+        correlation_id: Uuid,
     ) -> Result<ClusterUpgradeStatus> {
         let first_node = cluster.get_first_control_plane()?;
-        let job = create_job(first_node);
+        let job = create_job(first_node, correlation_id);
 
-        if client.spawn(kube, job).await? {
+        if client.spawn(kube, job).await?.is_spawned() {
             Ok(ClusterUpgradeStatus::Completed)
         } else {
             Ok(ClusterUpgradeStatus::Failed)
@@ -71,18 +83,19 @@ impl ClusterUpgradeArgs {
         kube: &::kube::Client,
         client: AnsibleClient,
         cluster: ClusterState<'a>,
+        correlation_id: Uuid,
     ) -> Result<ClusterUpgradeStatus> {
         let status: Vec<_> = cluster
             .get_worker_nodes()?
-            .map(create_job)
+            .map(|target_box| create_job(target_box, correlation_id))
             .map(|job| client.spawn(kube, job))
             .collect::<FuturesUnordered<_>>()
             .try_collect()
             .await?;
 
-        if status.iter().all(|&e| e) {
+        if status.iter().all(SpawnOutcome::is_spawned) {
             Ok(ClusterUpgradeStatus::Completed)
-        } else if status.iter().any(|&e| e) {
+        } else if status.iter().any(SpawnOutcome::is_spawned) {
             Ok(ClusterUpgradeStatus::PartiallyCompleted)
         } else {
             Ok(ClusterUpgradeStatus::Failed)
@@ -109,7 +122,7 @@ enum ClusterUpgradeStatus {
     Failed,
 }
 
-fn create_job(target_box: &BoxCrd) -> AnsibleJob {
+fn create_job(target_box: &BoxCrd, correlation_id: Uuid) -> AnsibleJob {
     AnsibleJob {
         cron: None,
         task: "upgrade",
@@ -118,6 +131,8 @@ fn create_job(target_box: &BoxCrd) -> AnsibleJob {
         new_state: None,
         is_critical: true,
         resource_type: AnsibleResourceType::Normal,
+        run_on_target: false,
         use_workers: false,
+        correlation_id: Some(correlation_id),
     }
 }
@@ -54,6 +54,8 @@ async fn get_new(client: Data<Client>, Query(query): Query<BoxNewQuery>) -> impl
                         },
                         state: BoxState::New,
                         bind_group: r#box.status.as_ref().and_then(|status| status.bind_group.as_ref()).cloned(),
+                        // NOTE: the unset id is skipped, keeping the current operation's one
+                        correlation_id: None,
                         last_updated: Utc::now(),
                     },
                 }));
@@ -90,6 +92,7 @@ async fn get_new(client: Data<Client>, Query(query): Query<BoxNewQuery>) -> impl
                         },
                         state: BoxState::New,
                         bind_group: None,
+                        correlation_id: None,
                         last_updated: Utc::now(),
                     },
                 }));
@@ -144,6 +147,7 @@ async fn post_commission(
                                 .and_then(|status| status.bind_group.as_ref())
                                 .cloned()
                         },
+                        correlation_id: None,
                         last_updated: Utc::now(),
                     },
                 }));
@@ -5,7 +5,7 @@ use ark_core_k8s::manager::Manager;
 use async_trait::async_trait;
 use chrono::Utc;
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
-use kiss_ansible::{AnsibleClient, AnsibleJob, AnsibleResourceType};
+use kiss_ansible::{AnsibleClient, AnsibleJob, AnsibleResourceType, SpawnOutcome};
 use kiss_api::r#box::{BoxCrd, BoxGroupRole, BoxState, BoxStatus};
 use kube::{
     api::{Patch, PatchParams},
@@ -14,6 +14,7 @@ use kube::{
 };
 use serde_json::json;
 use tracing::{info, instrument, warn, Level};
+use uuid::Uuid;
 
 #[derive(Default)]
 pub struct Ctx {}
@@ -139,6 +140,7 @@ impl ::ark_core_k8s::manager::Ctx for Ctx {
                         access: status.map(|status| status.access.clone()).unwrap_or_default(),
                         state: BoxState::Running,
                         bind_group: status.and_then(|status| status.bind_group.clone()),
+                        correlation_id: status.and_then(|status| status.correlation_id),
                         last_updated: Utc::now(),
                     },
                 }));
@@ -157,8 +159,17 @@ impl ::ark_core_k8s::manager::Ctx for Ctx {
 
         // spawn an Ansible job
         if old_state != new_state || new_state.cron().is_some() {
+            // NOTE: a logical operation begins on resetting the box,
+            // so that the jobs from the reset to the join share the same id
+            let correlation_id = match status.and_then(|status| status.correlation_id) {
+                Some(correlation_id) if old_state == new_state || !new_state.is_reset() => {
+                    correlation_id
+                }
+                _ => Uuid::new_v4(),
+            };
+
             if let Some(task) = new_state.as_task() {
-                let is_spawned = ansible
+                let outcome = ansible
                     .spawn(
                         &manager.kube,
                         AnsibleJob {
@@ -178,18 +189,23 @@ impl ::ark_core_k8s::manager::Ctx for Ctx {
                                 | BoxState::Failed
                                 | BoxState::Disconnected => AnsibleResourceType::Minimal,
                             },
+                            run_on_target: false,
                             use_workers: false,
+                            correlation_id: Some(correlation_id),
                         },
                     )
                     .await?;
 
                 // If there is a problem spawning a job, check back after a few minutes
-                if !is_spawned {
-                    info!("Cannot spawn an Ansible job; waiting: {}", &name);
-                    return Ok(Action::requeue(
-                        #[allow(clippy::identity_op)]
-                        Duration::from_secs(1 * 60),
-                    ));
+                match outcome {
+                    SpawnOutcome::Spawned { .. } => {}
+                    SpawnOutcome::DeferredNotJoinable => {
+                        info!("Cannot spawn an Ansible job (cluster is not joinable); waiting: {name}");
+                        return Ok(Action::requeue(
+                            #[allow(clippy::identity_op)]
+                            Duration::from_secs(1 * 60),
+                        ));
+                    }
                 }
             }
 
@@ -215,6 +231,7 @@ impl ::ark_core_k8s::manager::Ctx for Ctx {
                     access: status.map(|status| status.access.clone()).unwrap_or_default(),
                     state: new_state,
                     bind_group: bind_group.cloned(),
+                    correlation_id: Some(correlation_id),
                     last_updated: Utc::now(),
                 },
             }));
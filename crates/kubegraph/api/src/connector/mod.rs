@@ -7,7 +7,7 @@ pub mod local;
 #[cfg(feature = "connector-prometheus")]
 pub mod prometheus;
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -53,9 +53,13 @@ where
                 // Collect all new/updated resource scopes
                 let mut new_connectors = Vec::default();
                 let mut new_scopes = BTreeMap::default();
+                let mut ttls = BTreeMap::default();
                 for cr in connectors {
                     let scope = GraphScope::from_resource::<NetworkConnectorCrd>(&cr);
                     let version = cr.metadata.resource_version.clone();
+                    if let Some(ttl) = cr.spec.ttl {
+                        ttls.insert(scope.clone(), ttl);
+                    }
 
                     if scopes
                         .get(&scope) // updated
@@ -85,7 +89,12 @@ where
                         match events
                             .into_iter()
                             .map(|event| match event {
-                                NetworkConnectorEvent::Applied(data) => vm.graph_db().insert(data),
+                                NetworkConnectorEvent::Applied(data) => {
+                                    match ttls.get(&data.scope).copied() {
+                                        Some(ttl) => vm.graph_db().insert_with_ttl(data, ttl),
+                                        None => vm.graph_db().insert(data),
+                                    }
+                                }
                                 NetworkConnectorEvent::Deleted(scope) => {
                                     vm.graph_db().remove(scope)
                                 }
@@ -196,6 +205,10 @@ enum NetworkConnectorEvent {
 pub struct NetworkConnectorSpec {
     #[serde(flatten)]
     pub kind: NetworkConnectorKind,
+
+    /// The lifetime of the pulled graphs; they never expire if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<Duration>,
 }
 
 impl NetworkResource for NetworkConnectorCrd {
@@ -33,5 +33,7 @@ pub struct NetworkDependencyPipeline<G> {
 
 pub struct NetworkDependencyPipelineTemplate<G> {
     pub graph: G,
+    /// Outputs of the annotation functions, keyed by the function name
+    pub intermediates: BTreeMap<String, GraphEdges<LazyFrame>>,
     pub static_edges: Option<GraphEdges<LazyFrame>>,
 }
@@ -2,6 +2,7 @@
 pub mod polars;
 
 use std::{
+    collections::BTreeMap,
     fmt,
     ops::{Add, Div, Mul, Neg, Not, Sub},
 };
@@ -15,8 +16,8 @@ use serde::{Deserialize, Serialize};
 use crate::{
     function::FunctionMetadata,
     graph::{GraphDataType, GraphMetadataExt, GraphMetadataPinnedExt, GraphScope},
-    ops::{And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
-    problem::ProblemSpec,
+    ops::{Abs, And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
+    problem::{ProblemNonFinitePolicy, ProblemSpec},
     vm::{Feature, Number},
 };
 
@@ -69,6 +70,51 @@ impl DataFrame {
     }
 }
 
+/// The name and type of a column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub dtype: DataType,
+}
+
+impl ColumnSchema {
+    /// Check whether the column can be bound to a numeric attribute, e.g. color or size.
+    pub fn is_numeric(&self) -> bool {
+        self.dtype.is_numeric()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlowSummary {
+    /// Number of the edges with nonzero flow
+    pub num_moved_edges: usize,
+    /// Total amount of the moved supplies
+    pub total_flow: i64,
+}
+
+/// The min cost flow result of a solved graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowResult {
+    /// The edges moving any supplies
+    #[serde(default)]
+    pub edges: Vec<EdgeFlow>,
+    /// Total cost of the moved supplies and the supplies kept in the nodes
+    pub total_cost: i64,
+    /// Total amount of the supplies within the node capacities after moving
+    pub served_supply: i64,
+    /// Total amount of the supplies exceeding the node capacities after moving
+    pub unserved_supply: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeFlow {
+    pub src: String,
+    pub sink: String,
+    pub flow: i64,
+}
+
 #[derive(Clone, Default)]
 pub enum LazyFrame {
     #[default]
@@ -137,13 +183,48 @@ impl LazyFrame {
         }
     }
 
+    /// Append the rows of `other`, replacing the rows with the same `keys`.
+    pub fn merge(self, other: Self, keys: &[&str]) -> Result<Self> {
+        match (self, other) {
+            (Self::Empty, Self::Empty) => Ok(Self::Empty),
+            (Self::Empty, value) | (value, Self::Empty) => Ok(value),
+            #[cfg(feature = "df-polars")]
+            (Self::Polars(a), Self::Polars(b)) => self::polars::merge(a, b, keys).map(Self::Polars),
+        }
+    }
+
+    pub async fn count_rows(&self) -> Result<usize> {
+        match self {
+            Self::Empty => Ok(0),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => self::polars::count_rows(df.clone()),
+        }
+    }
+
+    /// Summarize the edge flows, e.g. to watch the optimization converging.
+    pub async fn summarize_flow<M>(&self, metadata: &M) -> Result<FlowSummary>
+    where
+        M: GraphMetadataExt,
+    {
+        match self {
+            Self::Empty => Ok(FlowSummary::default()),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => self::polars::summarize_flow(df.clone(), metadata.flow()),
+        }
+    }
+
     /// Create a fully-connected edges
     pub fn fabric<M>(&self, problem: &ProblemSpec<M>) -> Result<Self>
     where
         M: GraphMetadataPinnedExt,
     {
         let ProblemSpec {
+            column_remap: _,
+            constraints: _,
+            keep_original_columns: _,
             metadata,
+            non_finite: _,
+            retain_intermediates: _,
             verbose: _,
         } = problem;
 
@@ -186,6 +267,38 @@ impl LazyFrame {
         }
     }
 
+    pub fn get_column_names(&self) -> Result<Vec<String>> {
+        match self {
+            Self::Empty => bail!("cannot get column names from empty lazyframe"),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => df
+                .clone()
+                .collect_schema()
+                .map(|schema| schema.iter_names().map(|name| name.to_string()).collect())
+                .map_err(|error| anyhow!("failed to get column names: {error}")),
+        }
+    }
+
+    pub fn get_schema(&self) -> Result<Vec<ColumnSchema>> {
+        match self {
+            Self::Empty => Ok(Vec::default()),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => df
+                .clone()
+                .collect_schema()
+                .map(|schema| {
+                    schema
+                        .iter()
+                        .map(|(name, dtype)| ColumnSchema {
+                            name: name.to_string(),
+                            dtype: dtype.clone(),
+                        })
+                        .collect()
+                })
+                .map_err(|error| anyhow!("failed to get schema: {error}")),
+        }
+    }
+
     fn alias(&mut self, key: &str, value: &str) -> Result<()> {
         match self {
             Self::Empty => bail!("cannot make an alias to empty lazyframe: {key:?}"),
@@ -208,13 +321,62 @@ impl LazyFrame {
         self.alias(metadata.function(), name)
     }
 
-    pub fn alias_nodes<M>(&mut self, metadata: &M, scope: &GraphScope) -> Result<()>
+    /// Mark the nodes with the connector name.
+    ///
+    /// If `keep_original` is set, the user's own column of the same name
+    /// (if any) is preserved as `{connector}.original`.
+    pub fn alias_nodes<M>(
+        &mut self,
+        metadata: &M,
+        scope: &GraphScope,
+        keep_original: bool,
+    ) -> Result<()>
     where
         M: GraphMetadataExt,
     {
         let GraphScope { namespace: _, name } = scope;
+        let key = metadata.connector();
+
+        if keep_original && self.get_column_names()?.iter().any(|column| column == key) {
+            self.insert_column(&format!("{key}.original"), self.get_column(key)?)?;
+        }
+        self.alias(key, name)
+    }
 
-        self.alias(metadata.connector(), name)
+    /// Rename the columns onto a canonical schema.
+    ///
+    /// Fails if any renamed column collides with another one.
+    pub fn remap_columns(&mut self, remap: &BTreeMap<String, String>) -> Result<()> {
+        if remap.is_empty() || matches!(self, Self::Empty) {
+            return Ok(());
+        }
+
+        let columns = self.get_column_names()?;
+
+        let mut targets = BTreeMap::default();
+        for column in &columns {
+            let target = remap.get(column).unwrap_or(column);
+            if let Some(source) = targets.insert(target, column) {
+                bail!("column remap collides on {target:?}: {source:?} and {column:?}")
+            }
+        }
+
+        match self {
+            Self::Empty => Ok(()),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => {
+                *df = df.clone().select(
+                    columns
+                        .iter()
+                        .map(|column| {
+                            let target = remap.get(column).unwrap_or(column);
+                            dsl::col(column).alias(target)
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                Ok(())
+            }
+        }
     }
 
     pub fn apply_filter(&mut self, filter: LazySlice) -> Result<()> {
@@ -261,6 +423,45 @@ impl LazyFrame {
         }
     }
 
+    /// Handle the NaN or infinite values of the given column by the policy.
+    ///
+    /// Only the float columns are replaced, as the others cannot be non-finite.
+    /// The [`ProblemNonFinitePolicy::Fail`] policy is left to [`Self::ensure_finite`],
+    /// so that all the written columns are checked at once.
+    pub fn guard_non_finite(
+        &self,
+        name: &str,
+        column: LazySlice,
+        policy: ProblemNonFinitePolicy,
+    ) -> Result<LazySlice> {
+        let value = match policy {
+            ProblemNonFinitePolicy::Ignore | ProblemNonFinitePolicy::Fail => return Ok(column),
+            ProblemNonFinitePolicy::Replace { value } => value,
+        };
+
+        match (self, column) {
+            (Self::Empty, _) => bail!("cannot guard column of empty lazyframe: {name:?}"),
+            #[cfg(feature = "df-polars")]
+            (Self::Polars(df), LazySlice::Polars(column)) => {
+                self::polars::replace_non_finite(df, name, column, value).map(LazySlice::Polars)
+            }
+        }
+    }
+
+    /// Fail if any of the given float columns has NaN or infinite values.
+    ///
+    /// All the columns are checked within a single pass over the frame.
+    pub fn ensure_finite<'a, I>(&self, names: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        match self {
+            Self::Empty => bail!("cannot check columns of empty lazyframe"),
+            #[cfg(feature = "df-polars")]
+            Self::Polars(df) => self::polars::ensure_finite(df, names),
+        }
+    }
+
     #[cfg(feature = "df-polars")]
     pub fn try_into_polars(self) -> Result<::pl::lazy::frame::LazyFrame> {
         match self {
@@ -350,6 +551,55 @@ pub enum LazySlice {
     Polars(dsl::Expr),
 }
 
+impl LazySlice {
+    /// Compose the filter masks in priority order, so that each row is
+    /// taken by the first matching mask, recording its index.
+    pub fn first_match<I>(slices: I) -> Result<FirstMatch>
+    where
+        I: IntoIterator<Item = Self>,
+        I::IntoIter: DoubleEndedIterator + ExactSizeIterator,
+    {
+        // NOTE: compose from the last one, so that the first match is the outermost
+        let index = slices
+            .into_iter()
+            .enumerate()
+            .rev()
+            .try_fold(None, |acc, (index, slice)| -> Result<Option<Self>> {
+                let index = u32::try_from(index)
+                    .map_err(|_| anyhow!("too many filters to compose: {index}"))?;
+                match slice {
+                    #[cfg(feature = "df-polars")]
+                    Self::Polars(slice) => {
+                        let otherwise = match acc {
+                            Some(Self::Polars(acc)) => acc,
+                            None => dsl::lit(::pl::prelude::Null {}).cast(DataType::UInt32),
+                        };
+                        Ok(Some(Self::Polars(
+                            dsl::when(slice.fill_null(dsl::lit(false)))
+                                .then(dsl::lit(index))
+                                .otherwise(otherwise),
+                        )))
+                    }
+                }
+            })?
+            .ok_or_else(|| anyhow!("cannot compose empty filters"))?;
+
+        let mask = match index.clone() {
+            #[cfg(feature = "df-polars")]
+            Self::Polars(index) => Self::Polars(index.is_not_null()),
+        };
+        Ok(FirstMatch { index, mask })
+    }
+}
+
+/// The filter masks composed in priority order.
+pub struct FirstMatch {
+    /// The index of the first matching filter per row, or null if none matches
+    pub index: LazySlice,
+    /// Whether any filter matches the row
+    pub mask: LazySlice,
+}
+
 macro_rules! impl_expr_unary {
     ( impl $ty:ident ( $fn:ident ) for LazySlice {
         polars: $fn_polars:ident,
@@ -367,6 +617,21 @@ macro_rules! impl_expr_unary {
     };
 }
 
+impl Abs for LazySlice {
+    type Output = Self;
+
+    fn abs(self) -> Self::Output {
+        match self {
+            #[cfg(feature = "df-polars")]
+            Self::Polars(src) => Self::Polars(
+                dsl::when(src.clone().lt(dsl::lit(0)))
+                    .then(src.clone().neg())
+                    .otherwise(src),
+            ),
+        }
+    }
+}
+
 impl_expr_unary!(impl Neg(neg) for LazySlice {
     polars: neg,
 });
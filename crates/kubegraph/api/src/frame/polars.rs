@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, bail, Result};
+use num_traits::NumCast;
 use pl::{
     datatypes::DataType,
     frame::DataFrame,
@@ -6,7 +9,7 @@ use pl::{
         dsl,
         frame::{IntoLazy, LazyFrame},
     },
-    prelude::{Column, Literal, UnionArgs},
+    prelude::{Column, Literal, UnionArgs, UniqueKeepStrategy},
     series::Series,
 };
 
@@ -76,6 +79,262 @@ pub(super) fn concat(a: LazyFrame, b: LazyFrame) -> Result<LazyFrame> {
     dsl::concat([a, b], args).map_err(Into::into)
 }
 
+pub(super) fn merge(a: LazyFrame, b: LazyFrame, keys: &[&str]) -> Result<LazyFrame> {
+    let args = UnionArgs {
+        rechunk: true,
+        to_supertypes: true,
+        ..Default::default()
+    };
+    dsl::concat_lf_diagonal([a, b], args)
+        .map(|df| df.unique_stable_generic(Some(keys.to_vec()), UniqueKeepStrategy::Last))
+        .map_err(|error| anyhow!("failed to merge polars dataframes: {error}"))
+}
+
+pub(super) fn count_rows(df: LazyFrame) -> Result<usize> {
+    let key = "len";
+    df.select([dsl::len().alias(key)])
+        .collect()
+        .map_err(|error| anyhow!("failed to count rows: {error}"))?
+        .column(key)
+        .map_err(|error| anyhow!("failed to get row count column; it should be a BUG: {error}"))
+        .and_then(|column| {
+            column
+                .get(0)
+                .map_err(|error| anyhow!("failed to get row count: {error}"))
+        })
+        .and_then(|value| {
+            value.try_extract().map_err(|error| {
+                anyhow!("failed to convert row count to usize; it should be a BUG: {error}")
+            })
+        })
+}
+
+pub(super) fn summarize_flow(df: LazyFrame, key_flow: &str) -> Result<super::FlowSummary> {
+    let key_moved = "moved";
+    let key_total = "total";
+    let summary = df
+        .select([
+            // NOTE: the flows are non-negative
+            dsl::col(key_flow)
+                .sum()
+                .cast(DataType::Int64)
+                .alias(key_total),
+            dsl::col(key_flow)
+                .neq(dsl::lit(0))
+                .sum()
+                .cast(DataType::UInt64)
+                .alias(key_moved),
+        ])
+        .collect()
+        .map_err(|error| anyhow!("failed to summarize flows: {error}"))?;
+
+    Ok(super::FlowSummary {
+        num_moved_edges: get_first(&summary, key_moved)?,
+        total_flow: get_first(&summary, key_total)?,
+    })
+}
+
+pub(crate) fn collect_flow_result<M>(
+    edges: Option<LazyFrame>,
+    nodes: LazyFrame,
+    metadata: &M,
+) -> Result<super::FlowResult>
+where
+    M: GraphMetadataExt,
+{
+    let key_capacity = metadata.capacity();
+    let key_flow = metadata.flow();
+    let key_name = metadata.name();
+    let key_sink = metadata.sink();
+    let key_src = metadata.src();
+    let key_supply = metadata.supply();
+    let key_unit_cost = metadata.unit_cost();
+
+    // Step 1. Collect the node supplies
+    let nodes = nodes
+        .select([
+            dsl::col(key_name),
+            dsl::col(key_capacity),
+            dsl::col(key_supply),
+            dsl::col(key_unit_cost),
+        ])
+        .collect()
+        .map_err(|error| anyhow!("failed to collect nodes: {error}"))?;
+    let name = get_column(&nodes, "node", "name", key_name, Some(&DataType::String))?;
+    let capacity = get_column(
+        &nodes,
+        "node",
+        "capacity",
+        key_capacity,
+        Some(&DataType::Int64),
+    )?;
+    let supply = get_column(&nodes, "node", "supply", key_supply, Some(&DataType::Int64))?;
+    let cost = get_column(
+        &nodes,
+        "node",
+        "cost",
+        key_unit_cost,
+        Some(&DataType::Int64),
+    )?;
+
+    let mut balances: BTreeMap<_, _> = name
+        .str()?
+        .into_iter()
+        .zip(capacity.i64()?)
+        .zip(supply.i64()?)
+        .zip(cost.i64()?)
+        .filter_map(|(((name, capacity), supply), cost)| {
+            Some((
+                name?,
+                (
+                    supply.unwrap_or_default(),
+                    capacity.unwrap_or_default(),
+                    cost.unwrap_or_default(),
+                ),
+            ))
+        })
+        .collect();
+
+    // Step 2. Move the supplies along the edges
+    let mut result = super::FlowResult::default();
+    if let Some(edges) = edges {
+        let edges = edges
+            .select([
+                dsl::col(key_src),
+                dsl::col(key_sink),
+                dsl::col(key_flow),
+                dsl::col(key_unit_cost),
+            ])
+            .collect()
+            .map_err(|error| anyhow!("failed to collect edges: {error}"))?;
+        let src = get_column(&edges, "edge", "src", key_src, Some(&DataType::String))?;
+        let sink = get_column(&edges, "edge", "sink", key_sink, Some(&DataType::String))?;
+        let flow = get_column(&edges, "edge", "flow", key_flow, Some(&DataType::Int64))?;
+        let cost = get_column(
+            &edges,
+            "edge",
+            "cost",
+            key_unit_cost,
+            Some(&DataType::Int64),
+        )?;
+
+        for (((src, sink), flow), cost) in src
+            .str()?
+            .into_iter()
+            .zip(sink.str()?)
+            .zip(flow.i64()?)
+            .zip(cost.i64()?)
+        {
+            let (src, sink) = match (src, sink) {
+                (Some(src), Some(sink)) => (src, sink),
+                _ => continue,
+            };
+            let flow = flow.unwrap_or_default();
+
+            result.total_cost += flow * cost.unwrap_or_default();
+            if let Some((supply, _, _)) = balances.get_mut(src) {
+                *supply -= flow;
+            }
+            if let Some((supply, _, _)) = balances.get_mut(sink) {
+                *supply += flow;
+            }
+            // NOTE: the idle edges of the fabric are not reported
+            if flow != 0 {
+                result.edges.push(super::EdgeFlow {
+                    src: src.into(),
+                    sink: sink.into(),
+                    flow,
+                });
+            }
+        }
+    }
+
+    // Step 3. Keep the remaining supplies within the node capacities
+    for (supply, capacity, cost) in balances.into_values() {
+        let served = supply.min(capacity).max(0);
+        result.total_cost += served * cost;
+        result.served_supply += served;
+        result.unserved_supply += supply - served;
+    }
+    Ok(result)
+}
+
+pub(super) fn replace_non_finite(
+    df: &LazyFrame,
+    key: &str,
+    column: dsl::Expr,
+    value: Number,
+) -> Result<dsl::Expr> {
+    let is_float = df
+        .clone()
+        .with_column(column.clone().alias(key))
+        .collect_schema()
+        .map_err(|error| anyhow!("failed to get schema: {error}"))?
+        .get(key)
+        .is_some_and(|dtype| dtype.is_float());
+    if !is_float {
+        return Ok(column);
+    }
+
+    Ok(dsl::when(column.clone().is_finite())
+        .then(column)
+        .otherwise(value.lit()))
+}
+
+pub(super) fn ensure_finite<'a, I>(df: &LazyFrame, keys: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let schema = df
+        .clone()
+        .collect_schema()
+        .map_err(|error| anyhow!("failed to get schema: {error}"))?;
+    let keys: Vec<_> = keys
+        .into_iter()
+        .filter(|&key| schema.get(key).is_some_and(|dtype| dtype.is_float()))
+        .collect();
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let summary = df
+        .clone()
+        .select(
+            keys.iter()
+                .map(|&key| {
+                    dsl::col(key)
+                        .is_finite()
+                        .not()
+                        .sum()
+                        .cast(DataType::UInt64)
+                        .alias(key)
+                })
+                .collect::<Vec<_>>(),
+        )
+        .collect()
+        .map_err(|error| anyhow!("failed to check non-finite values: {error}"))?;
+
+    for key in keys {
+        let count: u64 = get_first(&summary, key)?;
+        if count > 0 {
+            bail!("found {count} NaN or infinite value(s) in column {key:?}")
+        }
+    }
+    Ok(())
+}
+
+fn get_first<T>(df: &DataFrame, key: &str) -> Result<T>
+where
+    T: NumCast,
+{
+    df.column(key)
+        .map_err(|error| anyhow!("failed to get {key} column; it should be a BUG: {error}"))?
+        .get(0)
+        .map_err(|error| anyhow!("failed to get {key} value: {error}"))?
+        .try_extract()
+        .map_err(|error| anyhow!("failed to convert {key} value; it should be a BUG: {error}"))
+}
+
 pub fn get_column(
     df: &DataFrame,
     kind: &str,
@@ -70,6 +70,9 @@ pub enum NetworkFunctionKind {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct NetworkFunctionTemplate<Script = String> {
+    /// Shared locals, compiled before both the filter and the script.
+    #[serde(default)]
+    pub prelude: Option<Script>,
     #[serde(default)]
     pub filter: Option<Script>,
     pub script: Script,
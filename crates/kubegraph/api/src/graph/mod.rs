@@ -1,9 +1,9 @@
 #[cfg(feature = "df-polars")]
 pub mod polars;
 
-use std::{collections::BTreeMap, fmt, mem::swap, sync::Arc};
+use std::{collections::BTreeMap, fmt, mem::swap, sync::Arc, time::Duration};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use futures::try_join;
 use kube::ResourceExt;
@@ -13,7 +13,7 @@ use tracing::{instrument, Level};
 
 use crate::{
     connector::NetworkConnectorCrd,
-    frame::{DataFrame, LazyFrame},
+    frame::{ColumnSchema, DataFrame, FlowResult, LazyFrame},
     function::FunctionMetadata,
     vm::{Feature, Number},
 };
@@ -91,6 +91,55 @@ where
         };
         self.get(&scope).await
     }
+
+    #[instrument(level = Level::INFO, skip(self, graph))]
+    async fn insert_with_policy(
+        &self,
+        graph: Graph<GraphData<LazyFrame>>,
+        policy: GraphInsertPolicy,
+    ) -> Result<()> {
+        let graph = match policy {
+            GraphInsertPolicy::Replace => graph,
+            GraphInsertPolicy::Merge => match self.get(&graph.scope).await? {
+                Some(last) => last.merge(graph)?,
+                None => graph,
+            },
+        };
+        self.insert(graph).await
+    }
+
+    /// List the scopes of all stored graphs.
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn list_scopes(&self) -> Result<Vec<GraphScope>> {
+        let filter = GraphFilter::all(String::default());
+        let graphs = self.list(&filter).await?;
+        Ok(graphs.into_iter().map(|graph| graph.scope).collect())
+    }
+
+    /// Summarize the graph without collecting its data.
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn summary(&self, scope: &GraphScope) -> Result<Option<GraphSummary>> {
+        let graph = match self.get(scope).await? {
+            Some(graph) => graph,
+            None => return Ok(None),
+        };
+        let GraphData { edges, nodes } = &graph.data;
+
+        fn get_column_names(df: &LazyFrame) -> Result<Vec<String>> {
+            match df {
+                LazyFrame::Empty => Ok(Vec::default()),
+                df => df.get_column_names(),
+            }
+        }
+
+        let (num_edges, num_nodes) = try_join!(edges.count_rows(), nodes.count_rows())?;
+        Ok(Some(GraphSummary {
+            edge_columns: get_column_names(edges)?,
+            node_columns: get_column_names(nodes)?,
+            num_edges,
+            num_nodes,
+        }))
+    }
 }
 
 #[async_trait]
@@ -105,13 +154,50 @@ where
 
     async fn insert(&self, graph: Graph<GraphData<LazyFrame>>) -> Result<()>;
 
+    /// Insert the graph, which expires after the `ttl`.
+    ///
+    /// The expired graphs are hidden, and are dropped by [`NetworkGraphDB::evict_expired`].
+    /// A persistent graph DB should persist the deadlines along with the graphs.
+    async fn insert_with_ttl(
+        &self,
+        graph: Graph<GraphData<LazyFrame>>,
+        ttl: Duration,
+    ) -> Result<()> {
+        let _ = (graph, ttl);
+        bail!("graph TTL is not supported by this graph DB")
+    }
+
     async fn list(&self, filter: &GraphFilter) -> Result<Vec<Graph<GraphData<LazyFrame>>>>;
 
     async fn remove(&self, scope: GraphScope) -> Result<()>;
 
+    /// Drop the expired graphs, returning their scopes.
+    async fn evict_expired(&self) -> Result<Vec<GraphScope>> {
+        Ok(Vec::default())
+    }
+
     async fn close(&self) -> Result<()>;
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphSummary {
+    pub edge_columns: Vec<String>,
+    pub node_columns: Vec<String>,
+    pub num_edges: usize,
+    pub num_nodes: usize,
+}
+
+/// How to store a graph into the scope which already has one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum GraphInsertPolicy {
+    /// Replace the whole graph of the scope.
+    #[default]
+    Replace,
+    /// Merge the nodes by name and the edges by their endpoints.
+    Merge,
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(transparent)]
 pub struct GraphEdges<T>(pub(crate) T);
@@ -301,7 +387,47 @@ where
     }
 }
 
+impl<M> Graph<GraphData<LazyFrame>, M>
+where
+    M: PartialEq + GraphMetadataExt,
+{
+    /// Merge the newer `other` graph of the same scope.
+    pub fn merge(self, other: Self) -> Result<Self> {
+        let Self {
+            connector: connector_a,
+            data: data_a,
+            metadata: metadata_a,
+            scope,
+        } = self;
+        let Self {
+            connector: connector_b,
+            data: data_b,
+            metadata: metadata_b,
+            scope: scope_b,
+        } = other;
+
+        if scope != scope_b {
+            bail!("cannot merge graphs of different scopes: {scope} and {scope_b}");
+        }
+        if metadata_a != metadata_b {
+            bail!("cannot merge graphs with different metadata: {scope}");
+        }
+
+        Ok(Self {
+            connector: connector_b.or(connector_a),
+            data: data_a.merge(data_b, &metadata_b)?,
+            metadata: metadata_b,
+            scope,
+        })
+    }
+}
+
 impl<M> Graph<GraphData<LazyFrame>, M> {
+    /// Return the columns of the edges and nodes.
+    pub fn schema(&self) -> Result<GraphData<Vec<ColumnSchema>>> {
+        self.data.schema()
+    }
+
     pub async fn collect(self) -> Result<Graph<GraphData<DataFrame>, M>> {
         let Self {
             connector,
@@ -377,6 +503,56 @@ impl GraphData<LazyFrame> {
             nodes: nodes_a.concat(nodes_b)?,
         })
     }
+
+    /// Derive the min cost flow result from the solved graph.
+    pub async fn collect_flow_result<M>(&self, metadata: &M) -> Result<FlowResult>
+    where
+        M: GraphMetadataExt,
+    {
+        match (&self.edges, &self.nodes) {
+            (_, LazyFrame::Empty) => Ok(FlowResult::default()),
+            #[cfg(feature = "df-polars")]
+            (LazyFrame::Empty, LazyFrame::Polars(nodes)) => {
+                crate::frame::polars::collect_flow_result(None, nodes.clone(), metadata)
+            }
+            #[cfg(feature = "df-polars")]
+            (LazyFrame::Polars(edges), LazyFrame::Polars(nodes)) => {
+                crate::frame::polars::collect_flow_result(
+                    Some(edges.clone()),
+                    nodes.clone(),
+                    metadata,
+                )
+            }
+        }
+    }
+
+    /// Return the columns of the edges and nodes.
+    pub fn schema(&self) -> Result<GraphData<Vec<ColumnSchema>>> {
+        Ok(GraphData {
+            edges: self.edges.get_schema()?,
+            nodes: self.nodes.get_schema()?,
+        })
+    }
+
+    /// Merge the `other` graph, preferring its nodes and edges on conflicts.
+    pub fn merge<M>(self, other: Self, metadata: &M) -> Result<Self>
+    where
+        M: GraphMetadataExt,
+    {
+        let Self {
+            edges: edges_a,
+            nodes: nodes_a,
+        } = self;
+        let Self {
+            edges: edges_b,
+            nodes: nodes_b,
+        } = other;
+
+        Ok(Self {
+            edges: edges_a.merge(edges_b, &[metadata.src(), metadata.sink()])?,
+            nodes: nodes_a.merge(nodes_b, &[metadata.name()])?,
+        })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -46,6 +46,12 @@ pub trait Or<Rhs = Self> {
     fn or(self, rhs: Rhs) -> Self::Output;
 }
 
+pub trait Abs {
+    type Output;
+
+    fn abs(self) -> Self::Output;
+}
+
 pub trait Max {
     type Output;
 
@@ -1,10 +1,15 @@
+use std::collections::BTreeMap;
+
 use kube::{CustomResource, CustomResourceExt};
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
-    graph::{GraphFilter, GraphMetadataPinned, GraphScope},
+    frame::DataFrame,
+    function::NetworkFunctionCrd,
+    graph::{Graph, GraphData, GraphFilter, GraphMetadataPinned, GraphScope},
     resource::NetworkResource,
+    vm::Number,
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -25,6 +30,17 @@ pub struct VirtualProblem<M = GraphMetadataPinned> {
     pub spec: ProblemSpec<M>,
 }
 
+/// A self-contained snapshot of a problem, which can be replayed on another VM.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemBundle {
+    #[serde(default)]
+    pub functions: Vec<NetworkFunctionCrd>,
+    #[serde(default)]
+    pub graphs: Vec<Graph<GraphData<DataFrame>>>,
+    pub problem: VirtualProblem,
+}
+
 #[derive(
     Clone,
     Debug,
@@ -64,9 +80,26 @@ pub struct VirtualProblem<M = GraphMetadataPinned> {
     bound = "M: Default + Serialize + DeserializeOwned"
 )]
 pub struct ProblemSpec<M = GraphMetadataPinned> {
+    /// Rename the graph columns onto a canonical schema before merging,
+    /// e.g. `cost` to `unit_cost`
+    #[serde(default)]
+    pub column_remap: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub constraints: ProblemConstraints,
+
+    #[serde(default)]
+    pub keep_original_columns: bool,
+
     #[serde(default)]
     pub metadata: M,
 
+    #[serde(default)]
+    pub non_finite: ProblemNonFinitePolicy,
+
+    #[serde(default)]
+    pub retain_intermediates: bool,
+
     #[serde(default = "ProblemSpec::<M>::default_verbose")]
     pub verbose: bool,
 }
@@ -77,12 +110,68 @@ where
 {
     fn default() -> Self {
         Self {
+            column_remap: BTreeMap::default(),
+            constraints: ProblemConstraints::default(),
+            keep_original_columns: false,
             metadata: M::default(),
+            non_finite: ProblemNonFinitePolicy::default(),
+            retain_intermediates: false,
             verbose: Self::default_verbose(),
         }
     }
 }
 
+/// Constraints to be validated by the solver.
+///
+/// The column names are taken from the problem metadata.
+/// The edge capacities need no constraint, as the solver never exceeds them.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemConstraints {
+    /// Ensure that all supplies are routed within the node capacities
+    #[serde(default)]
+    pub supply: bool,
+}
+
+/// How to handle the NaN or infinite values computed by the functions.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProblemNonFinitePolicy {
+    /// Pass the values through to the solver as they are
+    #[default]
+    Ignore,
+    /// Fail the step, naming the offending column
+    Fail,
+    /// Replace the values with the given sentinel
+    Replace { value: Number },
+}
+
 impl NetworkResource for NetworkProblemCrd {
     type Filter = ();
 
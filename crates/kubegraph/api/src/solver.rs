@@ -7,6 +7,11 @@ use crate::{graph::GraphMetadataPinned, problem::ProblemSpec};
 pub trait NetworkSolver<G> {
     type Output;
 
+    /// Return `false` if the solver is paused, so that the VM skips solving.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
     async fn solve(
         &self,
         graph: G,
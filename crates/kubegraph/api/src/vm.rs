@@ -12,7 +12,10 @@ use ark_core::signal::FunctionSignal;
 use async_trait::async_trait;
 use clap::Parser;
 use duration_string::DurationString;
-use futures::{stream::FuturesUnordered, TryStreamExt};
+use futures::{
+    stream::{FuturesOrdered, FuturesUnordered},
+    TryFutureExt, TryStreamExt,
+};
 use num_traits::FromPrimitive;
 use ordered_float::OrderedFloat;
 use schemars::JsonSchema;
@@ -26,13 +29,14 @@ use crate::{
         NetworkDependencyPipeline, NetworkDependencyPipelineTemplate, NetworkDependencySolver,
         NetworkDependencySolverSpec,
     },
-    frame::LazyFrame,
+    frame::{FlowResult, FlowSummary, LazyFrame},
+    function::NetworkFunctionCrd,
     graph::{
         Graph, GraphData, GraphFilter, GraphMetadata, GraphScope, NetworkGraphDB,
         NetworkGraphDBExt, ScopedNetworkGraphDBContainer,
     },
-    ops::{And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
-    problem::{NetworkProblemCrd, ProblemSpec, VirtualProblem},
+    ops::{Abs, And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
+    problem::{NetworkProblemCrd, ProblemBundle, ProblemSpec, VirtualProblem},
     resource::{NetworkResourceClient, NetworkResourceCollectionDB, NetworkResourceDB},
     runner::{NetworkRunner, NetworkRunnerContext},
     solver::NetworkSolver,
@@ -97,6 +101,7 @@ where
             match self.try_loop_forever().await {
                 Ok(()) => {
                     info!("Completed VM");
+                    self.observe_termination(NetworkTerminationReason::Converged);
                     signal.terminate();
                     break;
                 }
@@ -106,6 +111,7 @@ where
                     let interval = match fallback_interval {
                         NetworkFallbackPolicy::Interval { interval } => interval,
                         NetworkFallbackPolicy::Never => {
+                            self.observe_termination(NetworkTerminationReason::Failed);
                             signal.terminate_on_panic();
                             break;
                         }
@@ -128,6 +134,23 @@ where
             let instant = Instant::now();
 
             state = self.step(state).await?;
+            if let Some(metrics) = self.metrics() {
+                metrics.observe_heartbeat();
+            }
+
+            // NOTE: evict between the steps, so that no graph is dropped mid-solve
+            // NOTE: a failed eviction is retried on the next step
+            match self.graph_db().evict_expired().await {
+                Ok(scopes) => {
+                    for scope in scopes {
+                        info!("Evicted expired graph: {scope}");
+                        if let Some(metrics) = self.metrics() {
+                            metrics.observe_graph_eviction(&scope);
+                        }
+                    }
+                }
+                Err(error) => warn!("failed to evict expired graphs: {error}"),
+            }
 
             let interval = match state {
                 self::sealed::NetworkVirtualMachineState::Pending => {
@@ -186,23 +209,46 @@ where
         // Apply it
         problems
             .into_iter()
-            .map(|problem| self.step_with_custom_problem(state, problem))
+            .map(|problem| {
+                let collect_flow = false;
+                self.try_step_with_custom_problem(state, problem, collect_flow)
+                    .map_ok(|result| result.state)
+            })
             .collect::<FuturesUnordered<_>>()
             .try_collect()
             .await
     }
 
+    /// Solve the given problem once, collecting the solved flow.
     #[instrument(level = Level::INFO, skip(self, state))]
     async fn step_with_custom_problem(
         &self,
         state: self::sealed::NetworkVirtualMachineState,
         problem: VirtualProblem,
-    ) -> Result<self::sealed::NetworkVirtualMachineState> {
+    ) -> Result<StepResult> {
+        let collect_flow = true;
+        self.try_step_with_custom_problem(state, problem, collect_flow)
+            .await
+    }
+
+    /// Solve the given problem once.
+    ///
+    /// The flow result walks over all the solved edges, so it is only
+    /// collected on request (e.g. replays), not on the regular steps.
+    #[instrument(level = Level::INFO, skip(self, state))]
+    async fn try_step_with_custom_problem(
+        &self,
+        state: self::sealed::NetworkVirtualMachineState,
+        problem: VirtualProblem,
+        collect_flow: bool,
+    ) -> Result<StepResult> {
+        let instant = Instant::now();
+
         // Step 1. Check whether the problem is locked
         let scope = &problem.scope;
         if self.trader().is_enabled() && self.trader().is_locked(&problem).await? {
             info!("The problem is locked by the market: {scope}");
-            return Ok(self::sealed::NetworkVirtualMachineState::Trading);
+            return Ok(self::sealed::NetworkVirtualMachineState::Trading.into());
         }
 
         // Step 2. Pull & Convert graphs
@@ -218,6 +264,7 @@ where
                             metadata,
                             scope,
                         },
+                    intermediates: _,
                     static_edges,
                 },
         } = match self.pull_graph(&problem).await? {
@@ -226,15 +273,64 @@ where
                     self.visualizer()
                         .replace_graph(pipeline.template.graph)
                         .await?;
-                    return Ok(self::sealed::NetworkVirtualMachineState::Ready);
+                    return Ok(self::sealed::NetworkVirtualMachineState::Ready.into());
                 }
                 _ => pipeline,
             },
-            None => return Ok(self::sealed::NetworkVirtualMachineState::Empty),
+            None => return Ok(self::sealed::NetworkVirtualMachineState::Empty.into()),
         };
 
         // Step 3. Solve edge flows
-        let data = self.solver().solve(data, &problem.spec).await?;
+        if !self.solver().is_enabled() {
+            info!("The solver is paused: {scope}");
+            return Ok(self::sealed::NetworkVirtualMachineState::Ready.into());
+        }
+        let data = match self.solver().solve(data, &problem.spec).await {
+            Ok(data) => data,
+            Err(error) => {
+                if let Some(metrics) = self.metrics() {
+                    metrics.observe_solver_failure();
+                }
+                return Err(error);
+            }
+        };
+        if let Some(metrics) = self.metrics() {
+            // NOTE: only the steps that have solved something are observed
+            metrics.observe_step(instant.elapsed());
+
+            // NOTE: the metrics should not fail the step
+            match (data.nodes.count_rows().await, data.edges.count_rows().await) {
+                (Ok(nodes), Ok(edges)) => metrics.observe_graph(&problem.scope, nodes, edges),
+                (Err(error), _) | (_, Err(error)) => {
+                    warn!("failed to count the solved graph of {scope}: {error}")
+                }
+            }
+        }
+        let flow = if collect_flow {
+            match data.collect_flow_result(&problem.spec.metadata).await {
+                Ok(flow) => Some(flow),
+                Err(error) => {
+                    warn!("failed to collect the flow result of {scope}: {error}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if problem.spec.verbose {
+            // NOTE: the diagnostics should not fail the step
+            match data.edges.summarize_flow(&problem.spec.metadata).await {
+                Ok(FlowSummary {
+                    num_moved_edges,
+                    total_flow,
+                }) => {
+                    info!(
+                        "Solved {scope}: moved {total_flow} supplies over {num_moved_edges} edges"
+                    )
+                }
+                Err(error) => warn!("failed to summarize the flow of {scope}: {error}"),
+            }
+        }
 
         // Step 4. Register to the market if no feasible functions are found
         if matches!(&data.edges, LazyFrame::Empty) {
@@ -249,9 +345,15 @@ where
                 };
                 self.trader().register(ctx).await?;
                 info!("Registered the problem to the market: {scope}");
-                return Ok(self::sealed::NetworkVirtualMachineState::Trading);
+                return Ok(StepResult {
+                    state: self::sealed::NetworkVirtualMachineState::Trading,
+                    flow,
+                });
             } else {
-                return Ok(self::sealed::NetworkVirtualMachineState::Completed);
+                return Ok(StepResult {
+                    state: self::sealed::NetworkVirtualMachineState::Completed,
+                    flow,
+                });
             }
         }
 
@@ -278,7 +380,67 @@ where
             scope,
         };
         self.visualizer().replace_graph(graph).await?;
-        Ok(self::sealed::NetworkVirtualMachineState::Completed)
+        Ok(StepResult {
+            state: self::sealed::NetworkVirtualMachineState::Completed,
+            flow,
+        })
+    }
+
+    /// Export the problem with its graphs and functions, e.g. for reproducible bug reports.
+    ///
+    /// Only the functions in the problem's namespace are bundled.
+    #[instrument(level = Level::INFO, skip(self, problem))]
+    async fn export_problem(&self, problem: VirtualProblem) -> Result<ProblemBundle> {
+        let graphs = self
+            .pull_graphs(&problem)
+            .await?
+            .into_iter()
+            .map(|graph| graph.collect())
+            .collect::<FuturesOrdered<_>>()
+            .try_collect()
+            .await?;
+        let functions = self
+            .resource_db()
+            .list(())
+            .await
+            .ok_or_else(|| anyhow!("failed to list functions: {}", &problem.scope))?
+            .into_iter()
+            .filter(|cr: &NetworkFunctionCrd| {
+                GraphScope::from_resource(cr).namespace == problem.scope.namespace
+            })
+            .collect();
+
+        Ok(ProblemBundle {
+            functions,
+            graphs,
+            problem,
+        })
+    }
+
+    /// Register the graphs and functions of the exported problem.
+    #[instrument(level = Level::INFO, skip(self, bundle))]
+    async fn import_problem(&self, bundle: ProblemBundle) -> Result<VirtualProblem> {
+        let ProblemBundle {
+            functions,
+            graphs,
+            problem,
+        } = bundle;
+
+        for graph in graphs {
+            self.graph_db().insert(graph.lazy()).await?;
+        }
+        for function in functions {
+            self.resource_db().insert(function).await;
+        }
+        Ok(problem)
+    }
+
+    /// Import the exported problem and solve it once, returning the solved flow.
+    #[instrument(level = Level::INFO, skip(self, bundle))]
+    async fn replay_problem(&self, bundle: ProblemBundle) -> Result<StepResult> {
+        let problem = self.import_problem(bundle).await?;
+        let state = self::sealed::NetworkVirtualMachineState::Ready;
+        self.step_with_custom_problem(state, problem).await
     }
 
     #[instrument(level = Level::INFO, skip(self))]
@@ -306,24 +468,22 @@ where
         problem: &VirtualProblem,
     ) -> Result<Option<NetworkDependencyPipeline<Graph<GraphData<LazyFrame>>>>> {
         let VirtualProblem {
-            filter,
+            filter: _,
             scope,
-            spec: ProblemSpec {
-                metadata,
-                verbose: _,
-            },
+            spec:
+                ProblemSpec {
+                    column_remap: _,
+                    constraints: _,
+                    keep_original_columns: _,
+                    metadata,
+                    non_finite: _,
+                    retain_intermediates: _,
+                    verbose: _,
+                },
         } = problem;
 
         // Step 1. Collect all graphs
-        let graphs = match self
-            .graph_db()
-            .get_global_namespaced(&scope.namespace)
-            .await?
-        {
-            // If there is a global graph, use this
-            Some(graph) => vec![graph],
-            None => self.graph_db().list(filter).await?,
-        };
+        let graphs = self.pull_graphs(problem).await?;
         if graphs.is_empty() {
             return Ok(None);
         }
@@ -354,6 +514,7 @@ where
         };
         let NetworkDependencyPipelineTemplate {
             graph: data,
+            intermediates,
             static_edges,
         } = self
             .dependency_solver()
@@ -373,11 +534,30 @@ where
                         name: GraphScope::NAME_GLOBAL.into(),
                     },
                 },
+                intermediates,
                 static_edges,
             },
         }))
     }
 
+    #[instrument(level = Level::INFO, skip(self, problem))]
+    async fn pull_graphs(
+        &self,
+        problem: &VirtualProblem,
+    ) -> Result<Vec<Graph<GraphData<LazyFrame>>>> {
+        let VirtualProblem { filter, scope, .. } = problem;
+
+        match self
+            .graph_db()
+            .get_global_namespaced(&scope.namespace)
+            .await?
+        {
+            // If there is a global graph, use this
+            Some(graph) => Ok(vec![graph]),
+            None => self.graph_db().list(filter).await,
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close(&self) -> Result<()> {
         self.graph_db().close().await?;
@@ -410,6 +590,20 @@ mod sealed {
     }
 }
 
+/// The outcome of [`NetworkVirtualMachineExt::step_with_custom_problem`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepResult {
+    pub state: self::sealed::NetworkVirtualMachineState,
+    /// The solved flow, if requested and the solver has run in this step
+    pub flow: Option<FlowResult>,
+}
+
+impl From<self::sealed::NetworkVirtualMachineState> for StepResult {
+    fn from(state: self::sealed::NetworkVirtualMachineState) -> Self {
+        Self { state, flow: None }
+    }
+}
+
 #[async_trait]
 pub trait NetworkVirtualMachine
 where
@@ -451,6 +645,18 @@ where
         NetworkVirtualMachineRestartPolicy::default()
     }
 
+    fn metrics(&self) -> Option<&dyn NetworkVirtualMachineMetrics> {
+        None
+    }
+
+    /// Report why the VM has stopped.
+    ///
+    /// The default implementation only logs the reason; the implementations
+    /// may keep it instead, e.g. to expose it on their health checks.
+    fn observe_termination(&self, reason: NetworkTerminationReason) {
+        info!("Terminating VM: {reason}");
+    }
+
     async fn close_workers(&self) -> Result<()>;
 }
 
@@ -503,12 +709,62 @@ where
         <T as NetworkVirtualMachine>::restart_policy(&**self)
     }
 
+    fn metrics(&self) -> Option<&dyn NetworkVirtualMachineMetrics> {
+        <T as NetworkVirtualMachine>::metrics(&**self)
+    }
+
+    fn observe_termination(&self, reason: NetworkTerminationReason) {
+        <T as NetworkVirtualMachine>::observe_termination(&**self, reason)
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close_workers(&self) -> Result<()> {
         <T as NetworkVirtualMachine>::close_workers(&**self).await
     }
 }
 
+/// Observes the progress of the VM's optimization loop.
+pub trait NetworkVirtualMachineMetrics
+where
+    Self: Send + Sync,
+{
+    fn observe_graph(&self, scope: &GraphScope, nodes: usize, edges: usize);
+
+    fn observe_graph_eviction(&self, scope: &GraphScope);
+
+    fn observe_solver_failure(&self);
+
+    /// Observe a step that has solved a problem, excluding the idle ones.
+    fn observe_step(&self, duration: Duration);
+
+    /// Observe an iteration of the loop, whether or not it has solved anything.
+    fn observe_heartbeat(&self);
+}
+
+/// Why the VM has stopped.
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkTerminationReason {
+    /// All problems are completed and no restart is scheduled
+    Converged,
+    /// The VM has failed and no fallback is allowed
+    Failed,
+    /// The VM has been shut down by the operator
+    Shutdown,
+}
+
+impl fmt::Display for NetworkTerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Converged => "converged".fmt(f),
+            Self::Failed => "failed".fmt(f),
+            Self::Shutdown => "shutdown".fmt(f),
+        }
+    }
+}
+
 #[derive(
     Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
 )]
@@ -605,6 +861,15 @@ impl fmt::Display for NetworkVirtualMachineRestartPolicy {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Script {
     pub code: Vec<Instruction>,
+    /// The source spans of each instruction, if compiled with the debug info.
+    pub debug_info: Option<Vec<Option<SourceSpan>>>,
+}
+
+/// A byte range of the source code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -852,6 +1117,25 @@ macro_rules! impl_expr_function_builtin {
 impl_expr_function_builtin!(impl Max(max) for self as Number -> Number);
 impl_expr_function_builtin!(impl Min(min) for self as Number -> Number);
 
+impl Abs for Vec<Value> {
+    type Output = Result<Stmt>;
+
+    fn abs(self) -> Self::Output {
+        match self.as_slice() {
+            [value] => match value.to_number()? {
+                Some(value) => Ok(Stmt::DefineLocalValue {
+                    value: Some(value.abs()),
+                }),
+                None => Ok(Stmt::FunctionExpr {
+                    op: FunctionExpr::BuiltIn(BuiltInFunctionExpr::Abs),
+                    args: self,
+                }),
+            },
+            args => bail!("cannot call Abs with {} arguments: expected 1", args.len()),
+        }
+    }
+}
+
 impl Value {
     // fn is_feature(&self) -> bool {
     //     matches!(self, Self::Feature(_))
@@ -932,7 +1216,7 @@ impl Or for Feature {
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
 )]
 #[repr(transparent)]
 #[serde(transparent)]
@@ -960,6 +1244,14 @@ impl Number {
     }
 }
 
+impl Abs for Number {
+    type Output = Self;
+
+    fn abs(self) -> Self::Output {
+        Self(OrderedFloat(self.into_inner().abs()))
+    }
+}
+
 impl Neg for Number {
     type Output = Self;
 
@@ -1088,6 +1380,9 @@ pub enum FunctionExpr {
     Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
 )]
 pub enum BuiltInFunctionExpr {
+    Abs,
+    /// L1 distance over the paired arguments, i.e. `dist(a0, b0, a1, b1, ...)`.
+    Dist,
     Max,
     Min,
 }
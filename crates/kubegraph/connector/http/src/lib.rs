@@ -41,7 +41,7 @@ impl ::kubegraph_api::connector::NetworkConnector for NetworkConnector {
         let items = connectors.into_iter().filter_map(|object| {
             let cr = Arc::new(object.clone());
             let scope = GraphScope::from_resource(&object);
-            let NetworkConnectorSpec { kind } = object.spec;
+            let NetworkConnectorSpec { kind, .. } = object.spec;
 
             match kind {
                 NetworkConnectorKind::Http(spec) => {
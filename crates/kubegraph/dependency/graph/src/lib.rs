@@ -79,7 +79,12 @@ where
         claim: &GraphPipelineClaim<<N as Node>::Feature>,
     ) -> Option<Vec<GraphPipeline<N>>> {
         let GraphPipelineClaim {
-            option: GraphPipelineClaimOptions { fastest, max_depth },
+            option:
+                GraphPipelineClaimOptions {
+                    cheapest,
+                    fastest,
+                    max_depth,
+                },
             src: claim_src,
             sink: claim_sink,
         } = claim;
@@ -88,6 +93,9 @@ where
             return Some(vec![]);
         }
 
+        // NOTE: the cheapest pipeline may be longer than the shortest one
+        let return_first = *fastest && !*cheapest;
+
         // Prepare initial nodes to trigger the building
         let mut pipelines = Vec::default();
         let mut states = VecDeque::default();
@@ -103,7 +111,7 @@ where
             if provided.contains_all(&claim_sink) {
                 let pipeline = GraphPipeline { nodes: vec![sink] };
 
-                if *fastest {
+                if return_first {
                     return Some(vec![pipeline]);
                 } else {
                     pipelines.push(pipeline);
@@ -142,7 +150,7 @@ where
                             .collect(),
                     };
 
-                    if *fastest {
+                    if return_first {
                         return Some(vec![pipeline]);
                     } else {
                         pipelines.push(pipeline);
@@ -173,12 +181,138 @@ where
             }
         }
 
+        if *cheapest {
+            pipelines.sort_by_key(|pipeline| (pipeline.cost(), pipeline.nodes.len()));
+            if *fastest {
+                pipelines.truncate(1);
+            }
+        }
+
         if pipelines.is_empty() {
             None
         } else {
             Some(pipelines)
         }
     }
+
+    /// Explain why the given claim can or cannot be satisfied.
+    pub fn explain_claim<'a>(
+        &'a self,
+        claim: &GraphPipelineClaim<'a, <N as Node>::Feature>,
+    ) -> ClaimDiagnosis<'a, <N as Node>::Feature> {
+        let GraphPipelineClaim {
+            option: _,
+            src: claim_src,
+            sink: claim_sink,
+        } = claim;
+
+        // Test whether the sink features are provided by any node
+        let missing: Vec<_> = claim_sink
+            .iter()
+            .filter(|&feature| {
+                !claim_src.contains(feature)
+                    && !self
+                        .nodes
+                        .iter()
+                        .any(|node| node.provided().contains(feature))
+            })
+            .collect();
+        if !missing.is_empty() {
+            return ClaimDiagnosis::MissingProviders { features: missing };
+        }
+
+        // Collect all features reachable from the source
+        let mut reachable: BTreeSet<_> = claim_src.iter().collect();
+        loop {
+            let num_reachable = reachable.len();
+            for node in &self.nodes {
+                if reachable.contains_all(node.requirements()) {
+                    reachable.extend(node.provided());
+                }
+            }
+            if num_reachable == reachable.len() {
+                break;
+            }
+        }
+
+        // Find the requirements of the sink providers that are never reachable
+        let unreachable_sink: Vec<_> = claim_sink
+            .iter()
+            .filter(|feature| !reachable.contains(feature))
+            .collect();
+        if !unreachable_sink.is_empty() {
+            let features = self
+                .nodes
+                .iter()
+                .filter(|node| {
+                    unreachable_sink
+                        .iter()
+                        .any(|feature| node.provided().contains(feature))
+                })
+                .flat_map(|node| node.requirements())
+                .filter(|feature| !reachable.contains(feature))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            return ClaimDiagnosis::UnreachableRequirements { features };
+        }
+
+        if self.build_pipeline(claim).is_some() {
+            ClaimDiagnosis::Satisfied
+        } else {
+            ClaimDiagnosis::Constrained
+        }
+    }
+}
+
+/// The reason why a [`GraphPipelineClaim`] can or cannot be satisfied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClaimDiagnosis<'a, T> {
+    /// At least one pipeline satisfies the claim.
+    Satisfied,
+    /// No node provides these sink features.
+    MissingProviders { features: Vec<&'a T> },
+    /// The sink features are provided only by nodes requiring these features,
+    /// which are not reachable from the source.
+    UnreachableRequirements { features: Vec<&'a T> },
+    /// All features are reachable, but the claim options (e.g. `max_depth`)
+    /// or the final nodes prune every pipeline.
+    Constrained,
+}
+
+impl<'a, T> fmt::Display for ClaimDiagnosis<'a, T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn join<T>(f: &mut fmt::Formatter<'_>, features: &[&T]) -> fmt::Result
+        where
+            T: fmt::Display,
+        {
+            for (index, feature) in features.iter().enumerate() {
+                if index > 0 {
+                    ", ".fmt(f)?;
+                }
+                feature.fmt(f)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Self::Satisfied => "satisfied".fmt(f),
+            Self::MissingProviders { features } => {
+                "no providers for [".fmt(f)?;
+                join(f, features)?;
+                "]".fmt(f)
+            }
+            Self::UnreachableRequirements { features } => {
+                "unreachable requirements [".fmt(f)?;
+                join(f, features)?;
+                "]".fmt(f)
+            }
+            Self::Constrained => "constrained by the claim options".fmt(f),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -188,8 +322,16 @@ pub struct GraphPipelineClaim<'a, T> {
     pub sink: &'a [T],
 }
 
+/// Options to build the pipelines.
+///
+/// * `fastest` returns the first (shortest) pipeline only.
+/// * `cheapest` sorts the pipelines by their total [`Node::cost`],
+///   breaking ties by their length.
+///
+/// If both are set, only the cheapest pipeline is returned.
 #[derive(Copy, Clone, Debug)]
 pub struct GraphPipelineClaimOptions {
+    pub cheapest: bool,
     pub fastest: bool,
     pub max_depth: Option<usize>,
 }
@@ -197,6 +339,7 @@ pub struct GraphPipelineClaimOptions {
 impl Default for GraphPipelineClaimOptions {
     fn default() -> Self {
         Self {
+            cheapest: false,
             fastest: true,
             max_depth: None,
         }
@@ -208,6 +351,18 @@ pub struct GraphPipeline<'a, N> {
     pub nodes: Vec<&'a N>,
 }
 
+impl<'a, N> GraphPipeline<'a, N>
+where
+    N: Node,
+{
+    /// Return the total cost of the nodes.
+    pub fn cost(&self) -> u64 {
+        self.nodes
+            .iter()
+            .fold(0, |cost, node| cost.saturating_add(node.cost()))
+    }
+}
+
 impl<'a, N> fmt::Display for GraphPipeline<'a, N>
 where
     N: fmt::Display,
@@ -237,6 +392,11 @@ struct GraphVisitState<'a, T> {
 pub trait Node {
     type Feature: Ord;
 
+    /// Return the cost of running this node, used to find the cheapest pipeline.
+    fn cost(&self) -> u64 {
+        0
+    }
+
     fn is_final(&self) -> bool {
         false
     }
@@ -7,6 +7,11 @@ pub trait NodeIndex {
     type Key: Ord;
 
     fn key(&self) -> Self::Key;
+
+    /// The order of the nodes sharing the same key, e.g. the duplicated heads.
+    fn order(&self) -> Self::Key {
+        self.key()
+    }
 }
 
 impl<T> NodeIndex for &T
@@ -18,6 +23,10 @@ where
     fn key(&self) -> Self::Key {
         <T as NodeIndex>::key(*self)
     }
+
+    fn order(&self) -> Self::Key {
+        <T as NodeIndex>::order(*self)
+    }
 }
 
 impl NodeIndex for &str {
@@ -36,6 +45,13 @@ impl NodeIndex for String {
     }
 }
 
+/// Merge the pipelines sharing the same tails.
+///
+/// The output does not depend on the order of the input pipelines:
+///
+/// * The merged pipelines are ordered by the [`NodeIndex::key`] of their nodes.
+/// * If any pipeline passes through a shared node, that node comes first
+///   in the neighbors, followed by the heads ordered by [`NodeIndex::order`].
 pub trait GraphPipelineMerge<T> {
     fn merge_pipelines(self) -> Vec<Vec<GraphPipelineMergedNode<T>>>;
 }
@@ -74,6 +90,7 @@ struct ReversedNode<T>
 where
     T: NodeIndex,
 {
+    has_passing: bool,
     neighbors: Vec<T>,
     prevs: BTreeMap<<T as NodeIndex>::Key, Self>,
 }
@@ -84,6 +101,7 @@ where
 {
     fn default() -> Self {
         Self {
+            has_passing: false,
             neighbors: Vec::default(),
             prevs: BTreeMap::default(),
         }
@@ -94,8 +112,9 @@ impl<T> ReversedNode<T>
 where
     T: NodeIndex,
 {
-    fn new(node: T) -> Self {
+    fn new(node: T, is_first: bool) -> Self {
         Self {
+            has_passing: !is_first,
             neighbors: vec![node],
             prevs: BTreeMap::default(),
         }
@@ -103,11 +122,15 @@ where
 
     fn resolve(&mut self, node: T, is_first: bool) -> &mut Self {
         match self.prevs.entry(node.key()) {
-            Entry::Vacant(entry) => entry.insert(Self::new(node)),
+            Entry::Vacant(entry) => entry.insert(Self::new(node, is_first)),
             Entry::Occupied(entry) => {
                 let entry = entry.into_mut();
                 if is_first {
                     entry.neighbors.push(node);
+                } else if !entry.has_passing {
+                    // NOTE: keep the passing node first, regardless of the input order
+                    entry.has_passing = true;
+                    entry.neighbors.insert(0, node);
                 }
                 entry
             }
@@ -116,6 +139,7 @@ where
 
     fn into_disaggregated(self) -> Vec<Vec<GraphPipelineMergedNode<T>>> {
         let Self {
+            has_passing: _,
             neighbors: _,
             prevs,
         } = self;
@@ -129,9 +153,18 @@ where
         let mut stack = Vec::default();
         while let Some(ReversedNodeCursor {
             depth,
-            node: ReversedNode { neighbors, prevs },
+            node:
+                ReversedNode {
+                    has_passing,
+                    mut neighbors,
+                    prevs,
+                },
         }) = work_stack.pop()
         {
+            // NOTE: keep the passing node first, regardless of the input order
+            let passing = usize::from(has_passing);
+            neighbors[passing..].sort_by_cached_key(|node| node.order());
+
             stack.truncate(depth);
             stack.push(GraphPipelineMergedNode::Item(neighbors));
 
@@ -296,4 +329,73 @@ mod tests {
         ];
         assert_eq!(merged_pipelines, expected_pipelines);
     }
+
+    /// A node tagged with its input pipeline, which is only visible by its order.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct TaggedNode(&'static str, usize);
+
+    impl NodeIndex for TaggedNode {
+        type Key = String;
+
+        fn key(&self) -> Self::Key {
+            self.0.into()
+        }
+
+        fn order(&self) -> Self::Key {
+            format!("{}@{}", self.0, self.1)
+        }
+    }
+
+    #[test]
+    fn order_independent() {
+        // NOTE: tag the heads only, as the passing nodes are merged into one
+        let pipelines: Vec<_> = [
+            vec!["a", "b", "c", "d"],
+            vec!["x", "y", "z", "c", "d"],
+            vec!["y", "z", "c", "d"],
+            vec!["y", "z", "c", "d", "e"],
+            vec!["x", "y", "z", "c", "d"],
+            vec!["y", "z", "c", "d"],
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(tag, nodes)| {
+            nodes
+                .into_iter()
+                .enumerate()
+                .map(|(index, node)| TaggedNode(node, if index == 0 { tag } else { 0 }))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+        let expected_pipelines = pipelines.clone().merge_pipelines();
+
+        // The duplicated heads are sorted after the passing node
+        let heads = expected_pipelines
+            .iter()
+            .flatten()
+            .filter_map(|node| match node {
+                GraphPipelineMergedNode::Item(neighbors) if neighbors.len() > 1 => {
+                    Some(neighbors.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            heads,
+            [
+                vec![TaggedNode("x", 1), TaggedNode("x", 4)],
+                vec![TaggedNode("y", 0), TaggedNode("y", 2), TaggedNode("y", 5)],
+            ],
+        );
+
+        // Test all rotations and their reversed ones
+        for offset in 0..pipelines.len() {
+            let mut pipelines = pipelines.clone();
+            pipelines.rotate_left(offset);
+            assert_eq!(pipelines.clone().merge_pipelines(), expected_pipelines);
+
+            pipelines.reverse();
+            assert_eq!(pipelines.merge_pipelines(), expected_pipelines);
+        }
+    }
 }
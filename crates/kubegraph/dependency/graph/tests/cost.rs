@@ -0,0 +1,118 @@
+use std::fmt;
+
+use kubegraph_dependency_graph::{
+    Graph, GraphPipeline, GraphPipelineClaim, GraphPipelineClaimOptions, Node,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Package<'a> {
+    name: &'a str,
+    cost: u64,
+    provides: &'a [&'a str],
+    requirements: &'a [&'a str],
+}
+
+impl<'a> Node for Package<'a> {
+    type Feature = &'a str;
+
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    fn provided(&self) -> &[Self::Feature] {
+        self.provides
+    }
+
+    fn requirements(&self) -> &[Self::Feature] {
+        self.requirements
+    }
+}
+
+impl<'a> fmt::Display for Package<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            name,
+            cost: _,
+            provides: _,
+            requirements: _,
+        } = self;
+        write!(f, "{name}")?;
+        Ok(())
+    }
+}
+
+#[test]
+fn solve_cheapest() {
+    let mut graph = Graph::default();
+
+    // An expensive shortcut
+    let node_a = Package {
+        name: "A",
+        cost: 10,
+        provides: &["c"],
+        requirements: &["a"],
+    };
+    // A cheap detour
+    let node_b = Package {
+        name: "B",
+        cost: 1,
+        provides: &["b"],
+        requirements: &["a"],
+    };
+    let node_c = Package {
+        name: "C",
+        cost: 2,
+        provides: &["c"],
+        requirements: &["b"],
+    };
+
+    graph.add_node(node_a);
+    graph.add_node(node_b);
+    graph.add_node(node_c);
+
+    let claim = |cheapest, fastest| GraphPipelineClaim {
+        option: GraphPipelineClaimOptions {
+            cheapest,
+            fastest,
+            max_depth: None,
+        },
+        src: &["a"],
+        sink: &["c"],
+    };
+
+    // The shortest pipeline
+    let pipelines = graph.build_pipeline(&claim(false, true)).unwrap();
+    assert_eq!(
+        pipelines,
+        vec![GraphPipeline {
+            nodes: vec![&node_a],
+        }],
+    );
+
+    // The cheapest pipeline
+    let pipelines = graph.build_pipeline(&claim(true, true)).unwrap();
+    assert_eq!(
+        pipelines,
+        vec![GraphPipeline {
+            nodes: vec![&node_b, &node_c],
+        }],
+    );
+    assert_eq!(pipelines[0].cost(), 3);
+
+    // All pipelines, sorted by their costs
+    let pipelines = graph.build_pipeline(&claim(true, false)).unwrap();
+    assert_eq!(
+        pipelines,
+        vec![
+            GraphPipeline {
+                nodes: vec![&node_b, &node_c],
+            },
+            GraphPipeline {
+                nodes: vec![&node_a],
+            },
+            GraphPipeline {
+                nodes: vec![&node_b, &node_a],
+            },
+        ],
+    );
+}
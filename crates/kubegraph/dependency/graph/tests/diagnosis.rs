@@ -0,0 +1,96 @@
+use std::fmt;
+
+use kubegraph_dependency_graph::{
+    ClaimDiagnosis, Graph, GraphPipelineClaim, GraphPipelineClaimOptions, Node,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Package<'a> {
+    name: &'a str,
+    provides: &'a [&'a str],
+    requirements: &'a [&'a str],
+}
+
+impl<'a> Node for Package<'a> {
+    type Feature = &'a str;
+
+    fn provided(&self) -> &[Self::Feature] {
+        self.provides
+    }
+
+    fn requirements(&self) -> &[Self::Feature] {
+        self.requirements
+    }
+}
+
+impl<'a> fmt::Display for Package<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            name,
+            provides: _,
+            requirements: _,
+        } = self;
+        write!(f, "{name}")?;
+        Ok(())
+    }
+}
+
+#[test]
+fn explain() {
+    let mut graph = Graph::default();
+
+    graph.add_node(Package {
+        name: "A",
+        provides: &["b"],
+        requirements: &["a"],
+    });
+    graph.add_node(Package {
+        name: "B",
+        provides: &["c"],
+        requirements: &["b"],
+    });
+    graph.add_node(Package {
+        name: "C",
+        provides: &["e"],
+        requirements: &["c"],
+    });
+    graph.add_node(Package {
+        name: "D",
+        provides: &["d"],
+        requirements: &["x"],
+    });
+
+    let claim = |max_depth, sink: &'static [&'static str]| GraphPipelineClaim {
+        option: GraphPipelineClaimOptions {
+            max_depth,
+            ..Default::default()
+        },
+        src: &["a"],
+        sink,
+    };
+
+    assert_eq!(
+        graph.explain_claim(&claim(None, &["c"])),
+        ClaimDiagnosis::Satisfied,
+    );
+    assert_eq!(
+        graph.explain_claim(&claim(None, &["c", "z"])),
+        ClaimDiagnosis::MissingProviders {
+            features: vec![&"z"],
+        },
+    );
+    assert_eq!(
+        graph.explain_claim(&claim(None, &["d"])),
+        ClaimDiagnosis::UnreachableRequirements {
+            features: vec![&"x"],
+        },
+    );
+    assert_eq!(
+        graph.explain_claim(&claim(None, &["e"])),
+        ClaimDiagnosis::Satisfied,
+    );
+    assert_eq!(
+        graph.explain_claim(&claim(Some(2), &["e"])),
+        ClaimDiagnosis::Constrained,
+    );
+}
@@ -6,9 +6,10 @@ use std::{
 
 use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
+use itertools::Itertools;
 use kubegraph_api::{
     dependency::{NetworkDependencyPipelineTemplate, NetworkDependencySolverSpec},
-    frame::LazyFrame,
+    frame::{ColumnSchema, LazyFrame},
     function::{
         FunctionMetadata, NetworkFunctionCrd, NetworkFunctionKind, NetworkFunctionTemplate,
     },
@@ -25,10 +26,30 @@ use kubegraph_vm_lazy::{
     LazyVirtualMachine,
 };
 use regex::Regex;
-use tracing::{info, instrument, Level};
+use tracing::{info, instrument, warn, Level};
 
-#[derive(Clone, Default)]
-pub struct NetworkDependencyGraph {}
+#[derive(Clone)]
+pub struct NetworkDependencyGraph {
+    max_nodes: usize,
+}
+
+impl Default for NetworkDependencyGraph {
+    fn default() -> Self {
+        Self {
+            max_nodes: Self::DEFAULT_MAX_NODES,
+        }
+    }
+}
+
+impl NetworkDependencyGraph {
+    /// The default bound of the processed nodes while building a pipeline graph.
+    pub const DEFAULT_MAX_NODES: usize = 65_536;
+
+    /// Fail fast when building a pipeline graph processes more than `max_nodes` nodes.
+    pub fn with_max_nodes(max_nodes: usize) -> Self {
+        Self { max_nodes }
+    }
+}
 
 #[async_trait]
 impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyGraph {
@@ -46,20 +67,43 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
             .collect::<Result<Graph<_>>>()?;
 
         // Step 2. Disaggregate the graphs
+        let mut remapped_edges = ColumnRemapValidator::new(&problem.spec.column_remap);
+        let mut remapped_nodes = ColumnRemapValidator::new(&problem.spec.column_remap);
         let mut static_edges = Vec::with_capacity(spec.graphs.len());
         let mut static_nodes = Vec::with_capacity(spec.graphs.len());
         for ::kubegraph_api::graph::Graph {
             connector: _,
-            data: GraphData { edges, mut nodes },
+            data: GraphData {
+                mut edges,
+                mut nodes,
+            },
             metadata,
             scope,
         } in spec.graphs
         {
+            // NOTE: a connector without any nodes has nothing to feed the pipelines
+            if nodes.count_rows().await? == 0 {
+                if problem.spec.verbose {
+                    warn!("Skipping empty input graph: {scope}");
+                }
+                continue;
+            }
+
+            // Reconcile the connector schemas
+            edges.remap_columns(&problem.spec.column_remap)?;
+            nodes.remap_columns(&problem.spec.column_remap)?;
+            remapped_edges.validate(&scope, &edges)?;
+            remapped_nodes.validate(&scope, &nodes)?;
+
             // Mark the connector
-            nodes.alias_nodes(&problem.spec.metadata, &scope)?;
+            nodes.alias_nodes(
+                &problem.spec.metadata,
+                &scope,
+                problem.spec.keep_original_columns,
+            )?;
 
             static_edges.push(edges);
-            static_nodes.push((metadata, nodes));
+            static_nodes.push((scope, metadata, nodes));
         }
 
         // Step 3. Collect all static edges
@@ -77,9 +121,10 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
             .map(
                 |GraphPipeline {
                      inner: ::kubegraph_dependency_graph::GraphPipeline { nodes: functions },
+                     scope,
                      nodes,
                  }| {
-                    let mut nodes = Some(nodes);
+                    let mut nodes = Some((scope, nodes));
                     let nodes = ::std::iter::from_fn(move || Some(nodes.take()));
                     functions
                         .into_iter()
@@ -93,11 +138,25 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
         // Step 6. Build the dependency pipeline graph
         let mut finalized_edges = Vec::default();
         let mut finalized_nodes = Vec::default();
+        let mut intermediates = BTreeMap::<_, GraphEdges<_>>::default();
         let mut stack = BTreeMap::<_, Vec<_>>::default();
-        for (index, pipeline) in merged_pipelines.into_iter().enumerate().rev() {
+        let mut num_processed = 0usize;
+        'pipelines: for (index, pipeline) in merged_pipelines.into_iter().enumerate().rev() {
             let mut nodes = stack.remove(&index).unwrap_or_default();
 
             for merged_node in pipeline {
+                // NOTE: a buggy or adversarial function set should fail fast
+                num_processed = num_processed.saturating_add(match &merged_node {
+                    GraphPipelineMergedNode::Item(neighbors) => neighbors.len(),
+                    GraphPipelineMergedNode::Next(_) => nodes.len(),
+                });
+                if num_processed > self.max_nodes {
+                    bail!(
+                        "too many pipeline nodes: exceeded the limit of {max_nodes}",
+                        max_nodes = self.max_nodes,
+                    );
+                }
+
                 match merged_node {
                     GraphPipelineMergedNode::Item(neighbors) => {
                         let mut callable = None;
@@ -110,15 +169,18 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
                             if callable.is_none() {
                                 callable = Some(function);
                             }
-                            if let Some(static_nodes) = maybe_nodes {
+                            if let Some((_, static_nodes)) = maybe_nodes {
                                 nodes.push(static_nodes);
                             }
                         }
+                        let callable = callable.ok_or_else(|| anyhow!("empty function"))?;
                         if nodes.is_empty() {
-                            bail!("empty input graphs");
+                            if problem.spec.verbose {
+                                warn!("Skipping pipeline with empty input graphs: {callable}");
+                            }
+                            continue 'pipelines;
                         }
 
-                        let callable = callable.ok_or_else(|| anyhow!("empty function"))?;
                         let metadata = callable.metadata();
                         let inputs: GraphEdges<_> = {
                             let mut fetched = Vec::default();
@@ -136,10 +198,20 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
                             inputs.into_inner(),
                             callable.infer_type(),
                         )?;
+                        if !callable.is_final && problem.spec.retain_intermediates {
+                            intermediates
+                                .entry(callable.name())
+                                .or_default()
+                                .extend(Some(output.clone()));
+                        }
                         nodes.push(output.into_inner());
                     }
-                    GraphPipelineMergedNode::Next(index) => {
-                        stack.entry(index).or_default().append(&mut nodes)
+                    GraphPipelineMergedNode::Next(next) => {
+                        // NOTE: the pipelines are visited in reverse order
+                        if next >= index {
+                            bail!("invalid pipeline reference: {index} -> {next}");
+                        }
+                        stack.entry(next).or_default().append(&mut nodes)
                     }
                 }
             }
@@ -169,6 +241,7 @@ impl ::kubegraph_api::dependency::NetworkDependencySolver for NetworkDependencyG
 
         Ok(NetworkDependencyPipelineTemplate {
             graph,
+            intermediates,
             static_edges: Some(static_edges),
         })
     }
@@ -178,7 +251,7 @@ trait GraphPipelineBuilder {
     fn build_pipelines<M>(
         &self,
         problem: &VirtualProblem,
-        nodes: Vec<(M, LazyFrame)>,
+        nodes: Vec<(GraphScope, M, LazyFrame)>,
     ) -> (Vec<GraphPipeline<'_>>, Vec<LazyFrame>)
     where
         M: GraphMetadataExt;
@@ -188,15 +261,18 @@ impl GraphPipelineBuilder for Graph<Function> {
     fn build_pipelines<M>(
         &self,
         problem: &VirtualProblem,
-        nodes: Vec<(M, LazyFrame)>,
+        mut nodes: Vec<(GraphScope, M, LazyFrame)>,
     ) -> (Vec<GraphPipeline<'_>>, Vec<LazyFrame>)
     where
         M: GraphMetadataExt,
     {
+        // NOTE: the merged pipelines follow the order of the input graphs
+        nodes.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
         let mut dropped_nodes = Vec::default();
         let mut pipelines = Vec::default();
 
-        for (metadata, nodes) in nodes {
+        for (scope, metadata, nodes) in nodes {
             let src = metadata.all_node_inputs_raw();
             let sink: Vec<_> = problem
                 .spec
@@ -219,8 +295,22 @@ impl GraphPipelineBuilder for Graph<Function> {
                 .build_pipeline(&claim)
                 .and_then(|mut pipelines| pipelines.pop())
             {
-                Some(inner) => pipelines.push(GraphPipeline { inner, nodes }),
-                None => dropped_nodes.push(nodes),
+                Some(inner) => pipelines.push(GraphPipeline {
+                    inner,
+                    scope,
+                    nodes,
+                }),
+                None => {
+                    if problem.spec.verbose {
+                        let diagnosis = self.explain_claim(&claim);
+                        info!(
+                            "Dropped graph {scope}: {diagnosis}\n- src: [{src}]\n- sink: [{sink}]",
+                            src = src.iter().join(", "),
+                            sink = sink.iter().join(", "),
+                        );
+                    }
+                    dropped_nodes.push(nodes)
+                }
             }
         }
 
@@ -230,6 +320,7 @@ impl GraphPipelineBuilder for Graph<Function> {
 
 struct GraphPipeline<'a> {
     inner: ::kubegraph_dependency_graph::GraphPipeline<'a, Function>,
+    scope: GraphScope,
     nodes: LazyFrame,
 }
 
@@ -247,7 +338,8 @@ impl<'a> fmt::Display for GraphPipeline<'a> {
 
 struct GraphPipelineNode<'a> {
     function: &'a Function,
-    nodes: Option<LazyFrame>,
+    /// The input graph, only given to the head of the pipeline
+    nodes: Option<(GraphScope, LazyFrame)>,
 }
 
 impl<'a> fmt::Debug for GraphPipelineNode<'a> {
@@ -272,6 +364,53 @@ impl<'a> NodeIndex for GraphPipelineNode<'a> {
     fn key(&self) -> String {
         self.function.name()
     }
+
+    fn order(&self) -> String {
+        let name = self.function.name();
+        match &self.nodes {
+            Some((scope, _)) => format!("{name}@{scope}"),
+            None => name,
+        }
+    }
+}
+
+/// Ensure that each remapped column has the same type across the graphs.
+struct ColumnRemapValidator<'a> {
+    dtypes: BTreeMap<&'a str, (GraphScope, ColumnSchema)>,
+    targets: BTreeSet<&'a str>,
+}
+
+impl<'a> ColumnRemapValidator<'a> {
+    fn new(remap: &'a BTreeMap<String, String>) -> Self {
+        Self {
+            dtypes: BTreeMap::default(),
+            targets: remap.values().map(|target| target.as_str()).collect(),
+        }
+    }
+
+    fn validate(&mut self, scope: &GraphScope, frame: &LazyFrame) -> Result<()> {
+        if self.targets.is_empty() {
+            return Ok(());
+        }
+
+        for column in frame.get_schema()? {
+            let Some(&target) = self.targets.get(column.name.as_str()) else {
+                continue;
+            };
+            match self.dtypes.get(target) {
+                Some((first_scope, first)) if first.dtype != column.dtype => bail!(
+                    "column remap collides on {target:?} with different types: {first_dtype} ({first_scope}) and {dtype} ({scope})",
+                    first_dtype = first.dtype,
+                    dtype = column.dtype,
+                ),
+                Some(_) => continue,
+                None => {
+                    self.dtypes.insert(target, (scope.clone(), column));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -288,23 +427,45 @@ impl Function {
     where
         M: GraphMetadataExt,
     {
+        // NOTE: the prelude is compiled first, then the filter and the script
+        let prelude = LazyVirtualMachine::with_lazy_prelude(cr.spec.template.prelude.as_deref())?;
         let filter = cr
             .spec
             .template
             .filter
             .as_ref()
-            .map(|filter| LazyVirtualMachine::with_lazy_filter(filter))
+            .map(|filter| prelude.fork_lazy_filter(filter))
             .transpose()?;
-        let script = LazyVirtualMachine::with_lazy_script(&cr.spec.template.script)?;
+        let script = prelude.fork_lazy_script(&cr.spec.template.script)?;
+
+        // NOTE: the prelude locals are shared by the filter and the script,
+        // so they are neither provided nor written by the function itself
+        let prelude = prelude.dump_script().code;
+        let code = script.dump_script().code.split_off(prelude.len());
+
+        // NOTE: the function output column itself is filled by the runtime,
+        // so the script should write at least one edge column on its own
+        let is_final = !matches!(&cr.spec.kind, NetworkFunctionKind::Annotation(_));
+        if is_final && !code.iter().any(is_written) {
+            bail!(
+                "final function does not write any output column: {name}",
+                name = GraphScope::parse_name(&cr),
+            )
+        }
 
         let mut provided = BTreeSet::default();
         let mut requirements = BTreeSet::default();
-        for Instruction { name, stmt } in script.dump_script().code.into_iter().chain(
-            filter
-                .as_ref()
-                .map(|vm| vm.dump_script().code)
-                .unwrap_or_default(),
-        ) {
+        let prelude_len = prelude.len();
+        for Instruction { name, stmt } in code
+            .into_iter()
+            .chain(
+                filter
+                    .as_ref()
+                    .map(|vm| vm.dump_script().code.split_off(prelude_len))
+                    .unwrap_or_default(),
+            )
+            .chain(prelude.into_iter().filter(|ins| !is_written(ins)))
+        {
             let name = match name {
                 Some(ref name) => {
                     let re = Regex::new(r"^s(rc|ink)\.").unwrap();
@@ -322,7 +483,6 @@ impl Function {
             buf.insert(name);
         }
 
-        let is_final = !matches!(&cr.spec.kind, NetworkFunctionKind::Annotation(_));
         if is_final {
             provided.insert(problem.spec.metadata.function().into());
         }
@@ -332,7 +492,11 @@ impl Function {
             is_final,
             provided: provided.into_iter().collect(),
             requirements: requirements.into_iter().collect(),
-            template: NetworkFunctionTemplate { filter, script },
+            template: NetworkFunctionTemplate {
+                prelude: None,
+                filter,
+                script,
+            },
         })
     }
 
@@ -359,6 +523,16 @@ impl Function {
     }
 }
 
+/// Return whether the instruction writes a named column, rather than
+/// a bare placeholder of the requirements.
+const fn is_written(Instruction { name, stmt }: &Instruction) -> bool {
+    name.is_some()
+        && !matches!(
+            stmt,
+            Stmt::DefineLocalFeature { value: None } | Stmt::DefineLocalValue { value: None }
+        )
+}
+
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.name().fmt(f)?;
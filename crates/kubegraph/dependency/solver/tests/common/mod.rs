@@ -0,0 +1,87 @@
+// NOTE: not every test uses all of the fixtures
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kubegraph_api::{
+    connector::{NetworkConnectorCrd, NetworkConnectorKind, NetworkConnectorSpec},
+    frame::LazyFrame,
+    function::{
+        fake::NetworkFunctionFakeSpec, NetworkFunctionCrd, NetworkFunctionKind,
+        NetworkFunctionSpec, NetworkFunctionTemplate,
+    },
+    graph::{Graph, GraphData, GraphFilter, GraphMetadata, GraphScope},
+    problem::{ProblemSpec, VirtualProblem},
+};
+
+/// Build a graph of the given nodes, owned by a dummy connector.
+pub fn build_graph(name: &str, nodes: ::pl::frame::DataFrame) -> Graph<GraphData<LazyFrame>> {
+    let connector = NetworkConnectorCrd {
+        metadata: ObjectMeta {
+            namespace: Some("default".into()),
+            name: Some(name.into()),
+            ..Default::default()
+        },
+        spec: NetworkConnectorSpec {
+            kind: NetworkConnectorKind::Unknown {},
+            ttl: None,
+        },
+    };
+    let scope = GraphScope::from_resource(&connector);
+
+    Graph {
+        connector: Some(connector.into()),
+        data: GraphData {
+            edges: LazyFrame::default(),
+            nodes: nodes.into(),
+        },
+        metadata: GraphMetadata::default(),
+        scope,
+    }
+}
+
+/// Build a "move" function of the given filter and script.
+pub fn build_function(filter: &str, script: &str) -> BTreeMap<GraphScope, NetworkFunctionCrd> {
+    let function = NetworkFunctionCrd {
+        metadata: ObjectMeta {
+            namespace: Some("default".into()),
+            name: Some("move".into()),
+            ..Default::default()
+        },
+        spec: NetworkFunctionSpec {
+            kind: NetworkFunctionKind::Fake(NetworkFunctionFakeSpec {}),
+            template: NetworkFunctionTemplate {
+                prelude: None,
+                filter: Some(filter.into()),
+                script: script.into(),
+            },
+        },
+    };
+    Some((GraphScope::from_resource(&function), function))
+        .into_iter()
+        .collect()
+}
+
+/// Build a "move" function, which moves the supply into the smaller one.
+pub fn build_move_function() -> BTreeMap<GraphScope, NetworkFunctionCrd> {
+    build_function(
+        "src != sink and src.supply > 0 and src.supply > sink.supply",
+        r"
+            capacity = 50;
+            unit_cost = 1;
+        ",
+    )
+}
+
+/// Build a problem over all graphs of the default namespace.
+pub fn build_problem(spec: ProblemSpec) -> VirtualProblem {
+    VirtualProblem {
+        filter: GraphFilter::all("default".into()),
+        scope: GraphScope {
+            namespace: "default".into(),
+            name: "optimize-warehouses".into(),
+        },
+        spec,
+    }
+}
@@ -0,0 +1,83 @@
+#![cfg(all(feature = "df-polars", feature = "function-fake"))]
+
+extern crate polars as pl;
+
+mod common;
+
+use kubegraph_api::{
+    dependency::{
+        NetworkDependencyPipelineTemplate, NetworkDependencySolver, NetworkDependencySolverSpec,
+    },
+    frame::DataFrame,
+    graph::GraphData,
+    problem::ProblemSpec,
+};
+use kubegraph_dependency_solver::NetworkDependencyGraph;
+use pl::df;
+
+use self::common::{build_graph, build_move_function, build_problem};
+
+#[::tokio::test]
+async fn skip_empty_connector() {
+    // Step 1. Define an empty connector, with a column of its own
+    let empty = build_graph(
+        "empty",
+        df!(
+            "name"      => Vec::<&str>::new(),
+            "capacity"  => Vec::<i64>::new(),
+            "region"    => Vec::<&str>::new(),
+            "supply"    => Vec::<i64>::new(),
+            "unit_cost" => Vec::<i64>::new(),
+        )
+        .expect("failed to create empty nodes dataframe"),
+    );
+
+    // Step 2. Define a populated connector
+    let warehouse = build_graph(
+        "warehouse",
+        df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "unit_cost" => [   5i64,    1i64],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+
+    // Step 3. Define functions
+    let functions = build_move_function();
+
+    // Step 4. Define a problem
+    let problem = build_problem(ProblemSpec {
+        verbose: true,
+        ..Default::default()
+    });
+
+    // Step 5. Build the pipelines
+    let spec = NetworkDependencySolverSpec {
+        functions,
+        graphs: vec![empty, warehouse],
+    };
+    let NetworkDependencyPipelineTemplate {
+        graph: GraphData { edges, nodes },
+        intermediates: _,
+        static_edges: _,
+    } = NetworkDependencyGraph::default()
+        .build_pipeline(&problem, spec)
+        .await
+        .expect("failed to build pipelines");
+
+    // Step 6. Ensure the populated connector is still used
+    let edges = match edges.collect().await.expect("failed to collect edges") {
+        DataFrame::Empty => panic!("expected non-empty edges"),
+        DataFrame::Polars(edges) => edges,
+    };
+    assert!(edges.height() > 0);
+
+    // Step 7. Ensure the empty connector is dropped before merging
+    let nodes = match nodes.collect().await.expect("failed to collect nodes") {
+        DataFrame::Empty => panic!("expected non-empty nodes"),
+        DataFrame::Polars(nodes) => nodes,
+    };
+    assert!(nodes.column("region").is_err());
+}
@@ -0,0 +1,37 @@
+#![cfg(all(feature = "df-polars", feature = "function-fake"))]
+
+extern crate polars as pl;
+
+mod common;
+
+use kubegraph_api::{
+    dependency::{NetworkDependencySolver, NetworkDependencySolverSpec},
+    problem::ProblemSpec,
+};
+use kubegraph_dependency_solver::NetworkDependencyGraph;
+
+use self::common::{build_function, build_problem};
+
+#[::tokio::test]
+async fn reject_final_function_without_output() {
+    // Step 1. Define a final function which writes nothing
+    let functions = build_function("src != sink and src.supply > 0", "");
+
+    // Step 2. Define a problem
+    let problem = build_problem(ProblemSpec::default());
+
+    // Step 3. Ensure the function is rejected on building the pipelines
+    let spec = NetworkDependencySolverSpec {
+        functions,
+        graphs: vec![],
+    };
+    let result = NetworkDependencyGraph::default()
+        .build_pipeline(&problem, spec)
+        .await;
+    match result {
+        Ok(_) => panic!("final function without output should be rejected"),
+        Err(error) => assert!(error
+            .to_string()
+            .contains("does not write any output column")),
+    }
+}
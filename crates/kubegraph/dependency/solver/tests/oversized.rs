@@ -0,0 +1,49 @@
+#![cfg(all(feature = "df-polars", feature = "function-fake"))]
+
+extern crate polars as pl;
+
+mod common;
+
+use kubegraph_api::{
+    dependency::{NetworkDependencySolver, NetworkDependencySolverSpec},
+    problem::ProblemSpec,
+};
+use kubegraph_dependency_solver::NetworkDependencyGraph;
+use pl::df;
+
+use self::common::{build_graph, build_move_function, build_problem};
+
+#[::tokio::test]
+async fn reject_oversized_pipeline() {
+    // Step 1. Define many connectors sharing the same pipeline
+    let graphs = (0..16)
+        .map(|index| {
+            build_graph(
+                &format!("warehouse-{index}"),
+                df!(
+                    "name"      => [    "a",     "b"],
+                    "capacity"  => [ 300i64,  300i64],
+                    "supply"    => [ 300i64,    0i64],
+                    "unit_cost" => [   5i64,    1i64],
+                )
+                .expect("failed to create nodes dataframe"),
+            )
+        })
+        .collect();
+
+    // Step 2. Define functions
+    let functions = build_move_function();
+
+    // Step 3. Define a problem
+    let problem = build_problem(ProblemSpec::default());
+
+    // Step 4. Ensure the pipeline is rejected rather than built
+    let spec = NetworkDependencySolverSpec { functions, graphs };
+    let result = NetworkDependencyGraph::with_max_nodes(8)
+        .build_pipeline(&problem, spec)
+        .await;
+    match result {
+        Ok(_) => panic!("oversized pipeline should be rejected"),
+        Err(error) => assert!(error.to_string().contains("too many pipeline nodes")),
+    }
+}
@@ -0,0 +1,134 @@
+#![cfg(all(feature = "df-polars", feature = "function-fake"))]
+
+extern crate polars as pl;
+
+mod common;
+
+use kubegraph_api::{
+    dependency::{
+        NetworkDependencyPipelineTemplate, NetworkDependencySolver, NetworkDependencySolverSpec,
+    },
+    frame::{DataFrame, LazyFrame},
+    graph::{Graph, GraphData},
+    problem::ProblemSpec,
+};
+use kubegraph_dependency_solver::NetworkDependencyGraph;
+use pl::df;
+
+use self::common::{build_graph, build_move_function, build_problem};
+
+async fn build_pipeline(
+    graphs: Vec<Graph<GraphData<LazyFrame>>>,
+) -> anyhow::Result<NetworkDependencyPipelineTemplate<GraphData<LazyFrame>>> {
+    let problem = build_problem(ProblemSpec {
+        column_remap: Some(("cost".into(), "unit_cost".into()))
+            .into_iter()
+            .collect(),
+        verbose: true,
+        ..Default::default()
+    });
+
+    let spec = NetworkDependencySolverSpec {
+        functions: build_move_function(),
+        graphs,
+    };
+    NetworkDependencyGraph::default()
+        .build_pipeline(&problem, spec)
+        .await
+}
+
+#[::tokio::test]
+async fn remap_heterogeneous_connectors() {
+    let warehouse = build_graph(
+        "warehouse",
+        df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "unit_cost" => [   5i64,    1i64],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+    let legacy = build_graph(
+        "legacy",
+        df!(
+            "name"      => [    "c",     "d"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "cost"      => [   3i64,    2i64],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+
+    let NetworkDependencyPipelineTemplate {
+        graph: GraphData { edges: _, nodes },
+        intermediates: _,
+        static_edges: _,
+    } = build_pipeline(vec![warehouse, legacy])
+        .await
+        .expect("failed to build pipelines");
+
+    // All connectors are unified onto the canonical schema
+    let nodes = match nodes.collect().await.expect("failed to collect nodes") {
+        DataFrame::Empty => panic!("expected non-empty nodes"),
+        DataFrame::Polars(nodes) => nodes,
+    };
+    let columns: Vec<_> = nodes
+        .get_column_names()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+    assert!(columns.contains(&"unit_cost".to_string()));
+    assert!(!columns.contains(&"cost".to_string()));
+}
+
+#[::tokio::test]
+async fn remap_collides_on_types() {
+    let warehouse = build_graph(
+        "warehouse",
+        df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "unit_cost" => [   5i64,    1i64],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+    let legacy = build_graph(
+        "legacy",
+        df!(
+            "name"      => [    "c",     "d"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "cost"      => [    3.5,     2.5],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+
+    let error = build_pipeline(vec![warehouse, legacy])
+        .await
+        .err()
+        .expect("should reject the different types");
+    assert!(error.to_string().contains("different types"));
+}
+
+#[::tokio::test]
+async fn remap_collides_on_names() {
+    let warehouse = build_graph(
+        "warehouse",
+        df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "cost"      => [   3i64,    2i64],
+            "unit_cost" => [   5i64,    1i64],
+        )
+        .expect("failed to create nodes dataframe"),
+    );
+
+    let error = build_pipeline(vec![warehouse])
+        .await
+        .err()
+        .expect("should reject the colliding columns");
+    assert!(error.to_string().contains("collides"));
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, SystemTime};
+
 use anyhow::{anyhow, Result};
 use ark_core::signal::FunctionSignal;
 use async_trait::async_trait;
@@ -9,8 +11,8 @@ use kubegraph_api::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use sled::{Config, Db};
-use tracing::{info, instrument, Level};
+use sled::{Config, Db, IVec, Tree};
+use tracing::{info, instrument, warn, Level};
 
 #[derive(
     Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Parser,
@@ -45,6 +47,8 @@ impl NetworkGraphDBArgs {
 #[derive(Clone)]
 pub struct NetworkGraphDB {
     db: Db,
+    /// The deadlines of the graphs inserted with a TTL, persisted along with the graphs.
+    deadlines: Tree,
 }
 
 #[async_trait]
@@ -57,12 +61,42 @@ impl NetworkComponent for NetworkGraphDB {
 
         let NetworkGraphDBArgs { db_path } = args;
 
-        Ok(Self {
-            db: Config::default()
-                .path(db_path)
-                .open()
-                .map_err(|error| anyhow!("failed to open local db: {error}"))?,
-        })
+        let db = Config::default()
+            .path(db_path)
+            .open()
+            .map_err(|error| anyhow!("failed to open local db: {error}"))?;
+        let deadlines = db
+            .open_tree(Self::TREE_DEADLINES)
+            .map_err(|error| anyhow!("failed to open graph deadlines on local db: {error}"))?;
+
+        Ok(Self { db, deadlines })
+    }
+}
+
+impl NetworkGraphDB {
+    const TREE_DEADLINES: &'static str = "deadlines";
+
+    fn is_expired(&self, key: &[u8], now: SystemTime) -> Result<bool> {
+        self.deadlines
+            .get(key)
+            .map_err(|error| anyhow!("failed to get a graph deadline from local db: {error}"))
+            .and_then(|maybe_deadline| match maybe_deadline {
+                Some(deadline) => ::serde_json::from_slice::<SystemTime>(&deadline)
+                    .map(|deadline| deadline <= now)
+                    .map_err(Into::into),
+                None => Ok(false),
+            })
+    }
+
+    async fn insert_graph(&self, graph: Graph<GraphData<LazyFrame>>) -> Result<IVec> {
+        let graph = graph.collect().await?;
+        let key = IVec::from(::serde_json::to_vec(&graph.scope)?);
+        let value = ::serde_json::to_vec(&graph)?;
+
+        self.db
+            .insert(key.clone(), value)
+            .map(|_| key)
+            .map_err(|error| anyhow!("failed to insert graph into local db: {error}"))
     }
 }
 
@@ -71,6 +105,9 @@ impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
     #[instrument(level = Level::INFO, skip(self))]
     async fn get(&self, scope: &GraphScope) -> Result<Option<Graph<GraphData<LazyFrame>>>> {
         let key = ::serde_json::to_vec(scope)?;
+        if self.is_expired(&key, SystemTime::now())? {
+            return Ok(None);
+        }
 
         self.db
             .get(&key)
@@ -88,22 +125,37 @@ impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
 
     #[instrument(level = Level::INFO, skip(self, graph))]
     async fn insert(&self, graph: Graph<GraphData<LazyFrame>>) -> Result<()> {
-        let graph = graph.collect().await?;
-        let key = ::serde_json::to_vec(&graph.scope)?;
-        let value = ::serde_json::to_vec(&graph)?;
+        let key = self.insert_graph(graph).await?;
 
-        self.db
-            .insert(key, value)
+        self.deadlines
+            .remove(key)
             .map(|_| ())
-            .map_err(|error| anyhow!("failed to insert graph into local db: {error}"))
+            .map_err(|error| anyhow!("failed to reset a graph deadline on local db: {error}"))
+    }
+
+    #[instrument(level = Level::INFO, skip(self, graph))]
+    async fn insert_with_ttl(
+        &self,
+        graph: Graph<GraphData<LazyFrame>>,
+        ttl: Duration,
+    ) -> Result<()> {
+        let deadline = ::serde_json::to_vec(&(SystemTime::now() + ttl))?;
+        let key = self.insert_graph(graph).await?;
+
+        self.deadlines
+            .insert(key, deadline)
+            .map(|_| ())
+            .map_err(|error| anyhow!("failed to insert a graph deadline into local db: {error}"))
     }
 
     #[instrument(level = Level::INFO, skip(self))]
     async fn list(&self, filter: &GraphFilter) -> Result<Vec<Graph<GraphData<LazyFrame>>>> {
+        let now = SystemTime::now();
         Ok(self
             .db
             .iter()
             .filter_map(|result| result.ok())
+            .filter(|(key, _)| !self.is_expired(key, now).unwrap_or_default())
             .filter_map(|(key, value)| {
                 let key = ::serde_json::from_slice(&key).ok()?;
                 let value = ::serde_json::from_slice::<Graph<GraphData<DataFrame>>>(&value).ok()?;
@@ -120,10 +172,40 @@ impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
 
         self.db
             .remove(&key)
+            .and_then(|_| self.deadlines.remove(&key))
             .map(|_| ())
             .map_err(|error| anyhow!("failed to delete a graph from local db: {error}"))
     }
 
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn evict_expired(&self) -> Result<Vec<GraphScope>> {
+        let now = SystemTime::now();
+        let expired: Vec<_> = self
+            .deadlines
+            .iter()
+            .filter_map(|result| result.ok())
+            .filter(|(_, deadline)| {
+                ::serde_json::from_slice::<SystemTime>(deadline)
+                    .is_ok_and(|deadline| deadline <= now)
+            })
+            .filter_map(|(key, _)| Some((::serde_json::from_slice::<GraphScope>(&key).ok()?, key)))
+            .collect();
+
+        // NOTE: keep the failed deadlines, so that they are retried on the next call
+        let mut evicted = Vec::with_capacity(expired.len());
+        for (scope, key) in expired {
+            match self
+                .db
+                .remove(&key)
+                .and_then(|_| self.deadlines.remove(&key))
+            {
+                Ok(_) => evicted.push(scope),
+                Err(error) => warn!("failed to evict expired graph {scope}: {error}"),
+            }
+        }
+        Ok(evicted)
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close(&self) -> Result<()> {
         info!("Closing local db...");
@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -11,41 +15,93 @@ use tracing::{info, instrument, Level};
 
 #[derive(Clone, Default)]
 pub struct NetworkGraphDB {
+    /// The deadlines of the graphs inserted with a TTL.
+    deadlines: Arc<RwLock<BTreeMap<GraphScope, Instant>>>,
     map: Arc<RwLock<BTreeMap<GraphScope, Graph<GraphData<LazyFrame>>>>>,
 }
 
+impl NetworkGraphDB {
+    async fn is_expired(&self, scope: &GraphScope, now: Instant) -> bool {
+        self.deadlines
+            .read()
+            .await
+            .get(scope)
+            .is_some_and(|deadline| *deadline <= now)
+    }
+}
+
 #[async_trait]
 impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
     #[instrument(level = Level::INFO, skip(self))]
     async fn get(&self, scope: &GraphScope) -> Result<Option<Graph<GraphData<LazyFrame>>>> {
+        if self.is_expired(scope, Instant::now()).await {
+            return Ok(None);
+        }
         Ok(self.map.read().await.get(scope).cloned())
     }
 
     #[instrument(level = Level::INFO, skip(self, graph))]
     async fn insert(&self, graph: Graph<GraphData<LazyFrame>>) -> Result<()> {
         let mut map = self.map.write().await;
+        self.deadlines.write().await.remove(&graph.scope);
+        map.insert(graph.scope.clone(), graph);
+        Ok(())
+    }
+
+    #[instrument(level = Level::INFO, skip(self, graph))]
+    async fn insert_with_ttl(
+        &self,
+        graph: Graph<GraphData<LazyFrame>>,
+        ttl: Duration,
+    ) -> Result<()> {
+        let mut map = self.map.write().await;
+        self.deadlines
+            .write()
+            .await
+            .insert(graph.scope.clone(), Instant::now() + ttl);
         map.insert(graph.scope.clone(), graph);
         Ok(())
     }
 
     #[instrument(level = Level::INFO, skip(self))]
     async fn list(&self, filter: &GraphFilter) -> Result<Vec<Graph<GraphData<LazyFrame>>>> {
-        Ok(self
-            .map
-            .read()
-            .await
+        let map = self.map.read().await;
+        let deadlines = self.deadlines.read().await;
+        let now = Instant::now();
+        Ok(map
             .iter()
             .filter(|&(key, _)| filter.contains(key))
+            .filter(|&(key, _)| !deadlines.get(key).is_some_and(|deadline| *deadline <= now))
             .map(|(_, value)| value.clone())
             .collect())
     }
 
     #[instrument(level = Level::INFO, skip(self))]
     async fn remove(&self, scope: GraphScope) -> Result<()> {
-        self.map.write().await.remove(&scope);
+        let mut map = self.map.write().await;
+        self.deadlines.write().await.remove(&scope);
+        map.remove(&scope);
         Ok(())
     }
 
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn evict_expired(&self) -> Result<Vec<GraphScope>> {
+        let mut map = self.map.write().await;
+        let mut deadlines = self.deadlines.write().await;
+        let now = Instant::now();
+
+        let expired: Vec<_> = deadlines
+            .iter()
+            .filter(|&(_, deadline)| *deadline <= now)
+            .map(|(scope, _)| scope.clone())
+            .collect();
+        for scope in &expired {
+            deadlines.remove(scope);
+            map.remove(scope);
+        }
+        Ok(expired)
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close(&self) -> Result<()> {
         info!("Closing in-memory db...");
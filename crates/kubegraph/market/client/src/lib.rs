@@ -1,11 +1,11 @@
-mod page;
+use std::path::PathBuf;
 
 use anyhow::{bail, Error, Result};
 use ark_core::signal::FunctionSignal;
 use ark_core_k8s::data::Url;
 use async_trait::async_trait;
 use clap::Parser;
-use futures::Stream;
+use futures::{future, Stream, TryStreamExt};
 use kubegraph_api::{
     component::NetworkComponent,
     market::{
@@ -34,12 +34,45 @@ impl NetworkComponent for MarketClient {
 
     async fn try_new(args: <Self as NetworkComponent>::Args, _: &FunctionSignal) -> Result<Self> {
         Ok(Self {
+            session: build_session(&args)?,
             args,
-            session: ::reqwest::ClientBuilder::new().build()?,
         })
     }
 }
 
+fn build_session(args: &MarketClientArgs) -> Result<::reqwest::Client> {
+    #[allow(unused_mut)]
+    let mut builder = ::reqwest::ClientBuilder::new();
+
+    #[cfg(any(feature = "openssl-tls", feature = "rustls-tls"))]
+    {
+        if let Some(path) = &args.ca_cert {
+            let pem = ::std::fs::read(path).map_err(|error| {
+                ::anyhow::anyhow!(
+                    "failed to read the CA bundle ({path}): {error}",
+                    path = path.display()
+                )
+            })?;
+            for cert in ::reqwest::Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if args.insecure_skip_tls_verify {
+            ::tracing::warn!("TLS verification of the market endpoint is disabled");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+    }
+
+    #[cfg(not(any(feature = "openssl-tls", feature = "rustls-tls")))]
+    {
+        if args.ca_cert.is_some() || args.insecure_skip_tls_verify {
+            bail!("TLS options are given, but the market client is built without TLS");
+        }
+    }
+
+    builder.build().map_err(Into::into)
+}
+
 impl MarketClient {
     #[instrument(level = Level::INFO, skip(self))]
     pub async fn get_product(
@@ -66,12 +99,26 @@ impl MarketClient {
         self.execute(request).await
     }
 
+    #[instrument(level = Level::INFO, skip(self, spec))]
+    pub async fn lookup_product(
+        &self,
+        spec: &ProductSpec,
+    ) -> Result<Option<<ProductSpec as BaseModel>::Id>> {
+        let request = Request {
+            method: Method::POST,
+            rel_url: "prod/lookup",
+            page: None,
+            payload: Some(spec),
+        };
+        self.execute(request).await
+    }
+
     pub fn list_product_ids(
         &self,
     ) -> impl '_ + Stream<Item = Result<<ProductSpec as BaseModel>::Id>> {
-        let loader = move |page| self.list_product_ids_paged(page);
+        let loader = move |start, limit| self.list_product_ids_paged(Page { start, limit });
         let id_picker = move |id: &<ProductSpec as BaseModel>::Id| *id;
-        self::page::create_stream(loader, id_picker)
+        ::ark_core::stream::paginate(loader, id_picker, Page::<usize>::default().limit)
     }
 
     #[instrument(level = Level::INFO, skip(self))]
@@ -115,13 +162,49 @@ impl MarketClient {
 }
 
 impl MarketClient {
+    const MAX_CONCURRENT_PRICE_REQUESTS: usize = 8;
+
+    /// List all products with their latest price, if any.
+    pub fn list_products_with_price(
+        &self,
+    ) -> impl '_ + Stream<Item = Result<(ProductSpec, Option<PriceItem>)>> {
+        self.list_product_ids()
+            .map_ok(move |prod_id| self.get_product_with_price(prod_id))
+            .try_buffered(Self::MAX_CONCURRENT_PRICE_REQUESTS)
+            // NOTE: skip the products removed while listing
+            .try_filter_map(|item| future::ready(Ok(item)))
+    }
+
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn get_product_with_price(
+        &self,
+        prod_id: <ProductSpec as BaseModel>::Id,
+    ) -> Result<Option<(ProductSpec, Option<PriceItem>)>> {
+        let spec = match self.get_product(prod_id).await? {
+            Some(spec) => spec,
+            None => return Ok(None),
+        };
+
+        let price = self
+            .list_price_histogram(prod_id)
+            .try_fold(None, |latest: Option<PriceItem>, item| {
+                future::ready(Ok(match latest {
+                    Some(latest) if latest.timestamp > item.timestamp => Some(latest),
+                    _ => Some(item),
+                }))
+            })
+            .await?;
+        Ok(Some((spec, price)))
+    }
+
     pub fn list_price_histogram(
         &self,
         prod_id: <ProductSpec as BaseModel>::Id,
     ) -> impl '_ + Stream<Item = Result<PriceItem>> {
-        let loader = move |page| self.list_price_histogram_paged(prod_id, page);
+        let loader =
+            move |start, limit| self.list_price_histogram_paged(prod_id, Page { start, limit });
         let id_picker = move |item: &PriceItem| item.id;
-        self::page::create_stream(loader, id_picker)
+        ::ark_core::stream::paginate(loader, id_picker, Page::<usize>::default().limit)
     }
 
     #[instrument(level = Level::INFO, skip(self))]
@@ -175,9 +258,9 @@ impl MarketClient {
         &self,
         prod_id: <ProductSpec as BaseModel>::Id,
     ) -> impl '_ + Stream<Item = Result<<PubSpec as BaseModel>::Id>> {
-        let loader = move |page| self.list_pub_ids_paged(prod_id, page);
+        let loader = move |start, limit| self.list_pub_ids_paged(prod_id, Page { start, limit });
         let id_picker = move |id: &<PubSpec as BaseModel>::Id| *id;
-        self::page::create_stream(loader, id_picker)
+        ::ark_core::stream::paginate(loader, id_picker, Page::<usize>::default().limit)
     }
 
     #[instrument(level = Level::INFO, skip(self))]
@@ -246,9 +329,9 @@ impl MarketClient {
         &self,
         prod_id: <ProductSpec as BaseModel>::Id,
     ) -> impl '_ + Stream<Item = Result<<SubSpec as BaseModel>::Id>> {
-        let loader = move |page| self.list_sub_ids_paged(prod_id, page);
+        let loader = move |start, limit| self.list_sub_ids_paged(prod_id, Page { start, limit });
         let id_picker = move |id: &<SubSpec as BaseModel>::Id| *id;
-        self::page::create_stream(loader, id_picker)
+        ::ark_core::stream::paginate(loader, id_picker, Page::<usize>::default().limit)
     }
 
     #[instrument(level = Level::INFO, skip(self))]
@@ -361,12 +444,24 @@ pub struct MarketClientArgs {
     )]
     #[serde(default = "MarketClientArgs::default_endpoint")]
     pub endpoint: Url,
+
+    /// PEM-encoded CA bundle to verify the endpoint, in addition to the system roots
+    #[arg(long, env = "KUBEGRAPH_MARKET_CLIENT_CA_CERT", value_name = "PATH")]
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Skip verifying the TLS certificate of the endpoint (development only)
+    #[arg(long, env = "KUBEGRAPH_MARKET_CLIENT_INSECURE_SKIP_TLS_VERIFY")]
+    #[serde(default)]
+    pub insecure_skip_tls_verify: bool,
 }
 
 impl Default for MarketClientArgs {
     fn default() -> Self {
         Self {
             endpoint: Self::default_endpoint(),
+            ca_cert: None,
+            insecure_skip_tls_verify: false,
         }
     }
 }
@@ -42,6 +42,7 @@ async fn try_loop_forever(db: &Database) -> Result<()> {
             .service(crate::routes::product::list)
             .service(crate::routes::product::list_price)
             .service(crate::routes::product::get)
+            .service(crate::routes::product::post_lookup)
             .service(crate::routes::product::post)
             .service(crate::routes::product::post_trade)
             .service(crate::routes::product::put)
@@ -111,6 +111,26 @@ impl Database {
             .map_err(Into::into)
     }
 
+    #[instrument(level = Level::INFO, skip(self, spec))]
+    pub async fn lookup_product(
+        &self,
+        spec: ProductSpec,
+    ) -> Result<Option<<ProductSpec as BaseModel>::Id>> {
+        let col_id = entity::product::Column::Id;
+        let col_spec = entity::product::Column::Spec;
+
+        let spec = entity::product::to_spec(spec)?;
+        let dsl = entity::product::Entity::find()
+            .select_only()
+            .column(col_id)
+            .filter(col_spec.eq(spec));
+
+        dsl.into_tuple()
+            .one(&self.connection)
+            .await
+            .map_err(Into::into)
+    }
+
     #[instrument(level = Level::INFO, skip(self, spec))]
     pub async fn find_product(&self, spec: ProductSpec) -> Result<<ProductSpec as BaseModel>::Id> {
         self.connection
@@ -41,6 +41,12 @@ pub async fn post(db: Data<Database>, spec: Json<ProductSpec>) -> impl Responder
     HttpResponse::Ok().json(Result::from(db.find_product(spec.0).await))
 }
 
+#[instrument(level = Level::INFO, skip(db, spec))]
+#[post("/prod/lookup")]
+pub async fn post_lookup(db: Data<Database>, spec: Json<ProductSpec>) -> impl Responder {
+    HttpResponse::Ok().json(Result::from(db.lookup_product(spec.0).await))
+}
+
 #[instrument(level = Level::INFO, skip(db))]
 #[post("/prod/{prod_id}/trade")]
 pub async fn post_trade(
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// 1-based line number of the offending character.
+    pub line: usize,
+    /// 1-based column number (in characters) of the offending character.
+    pub column: usize,
+    #[serde(default)]
+    pub expected: Vec<String>,
+}
+
+impl ParseError {
+    pub fn new<T, E>(input: &str, error: ::lalrpop_util::ParseError<usize, T, E>) -> Self
+    where
+        T: fmt::Display,
+        E: fmt::Display,
+    {
+        use lalrpop_util::ParseError as Inner;
+
+        let (location, kind, expected) = match error {
+            Inner::InvalidToken { location } => (location, ParseErrorKind::InvalidToken, vec![]),
+            Inner::UnrecognizedEof { location, expected } => {
+                (location, ParseErrorKind::UnexpectedEof, expected)
+            }
+            Inner::UnrecognizedToken {
+                token: (location, token, _),
+                expected,
+            } => (
+                location,
+                ParseErrorKind::UnexpectedToken {
+                    token: token.to_string(),
+                },
+                expected,
+            ),
+            Inner::ExtraToken {
+                token: (location, token, _),
+            } => (
+                location,
+                ParseErrorKind::ExtraToken {
+                    token: token.to_string(),
+                },
+                vec![],
+            ),
+            Inner::User { error } => (
+                input.len(),
+                ParseErrorKind::Custom {
+                    message: error.to_string(),
+                },
+                vec![],
+            ),
+        };
+
+        let (line, column) = locate(input, location);
+        Self {
+            kind,
+            line,
+            column,
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            kind,
+            line,
+            column,
+            expected,
+        } = self;
+
+        write!(f, "{kind} at line {line}, column {column}")?;
+        match expected.as_slice() {
+            [] => Ok(()),
+            [token] => write!(f, "; expected {token}"),
+            tokens => write!(f, "; expected one of {}", tokens.join(", ")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ParseErrorKind {
+    Custom { message: String },
+    ExtraToken { token: String },
+    InvalidToken,
+    UnexpectedEof,
+    UnexpectedToken { token: String },
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom { message } => message.fmt(f),
+            Self::ExtraToken { token } => write!(f, "extra token {token:?}"),
+            Self::InvalidToken => "invalid token".fmt(f),
+            Self::UnexpectedEof => "unexpected end of input".fmt(f),
+            Self::UnexpectedToken { token } => write!(f, "unexpected token {token:?}"),
+        }
+    }
+}
+
+fn locate(input: &str, location: usize) -> (usize, usize) {
+    let mut location = location.min(input.len());
+    while !input.is_char_boundary(location) {
+        location -= 1;
+    }
+
+    let head = &input[..location];
+    let line = head.matches('\n').count() + 1;
+    let column = match head.rfind('\n') {
+        Some(index) => head[index + 1..].chars().count() + 1,
+        None => head.chars().count() + 1,
+    };
+    (line, column)
+}
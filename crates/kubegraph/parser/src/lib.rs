@@ -1,3 +1,5 @@
+mod error;
+
 use kubegraph_api::vm::{BinaryExpr, FunctionExpr, Literal, Number, UnaryExpr};
 use lalrpop_util::lalrpop_mod;
 use schemars::JsonSchema;
@@ -5,7 +7,10 @@ use serde::{Deserialize, Serialize};
 
 lalrpop_mod!(grammar);
 
-pub use self::grammar::{FilterParser, ProvideParser, ScriptParser};
+pub use self::{
+    error::{ParseError, ParseErrorKind},
+    grammar::{FilterParser, ProvideParser, ScriptParser, SpannedScriptParser},
+};
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Script(pub Vec<Stmt>);
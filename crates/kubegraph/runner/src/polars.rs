@@ -40,7 +40,12 @@ where
                     scope: _,
                     spec:
                         ProblemSpec {
+                            column_remap: _,
+                            constraints: _,
+                            keep_original_columns: _,
                             metadata,
+                            non_finite: _,
+                            retain_intermediates: _,
                             verbose: _,
                         },
                 },
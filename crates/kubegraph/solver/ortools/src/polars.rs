@@ -1,3 +1,5 @@
+use std::fmt;
+
 use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use kubegraph_api::{
@@ -46,7 +48,15 @@ impl ::kubegraph_api::solver::NetworkSolver<GraphData<LazyFrame>> for super::Net
         graph: GraphData<LazyFrame>,
         problem: &ProblemSpec<GraphMetadataPinned>,
     ) -> Result<Self::Output> {
-        let ProblemSpec { metadata, verbose } = problem;
+        let ProblemSpec {
+            column_remap: _,
+            constraints,
+            keep_original_columns: _,
+            metadata,
+            non_finite: _,
+            retain_intermediates: _,
+            verbose,
+        } = problem;
         let key_capacity = metadata.capacity();
         let key_flow = metadata.flow();
         let key_name = metadata.name();
@@ -119,6 +129,18 @@ impl ::kubegraph_api::solver::NetworkSolver<GraphData<LazyFrame>> for super::Net
         let node_supply_sum = node_supply
             .sum()
             .map_err(|error| anyhow!("failed to collect node supplies: {error}"))?;
+        let node_capacity_sum: FlowQuantity = node_capacity
+            .sum()
+            .map_err(|error| anyhow!("failed to collect node capacities: {error}"))?;
+
+        // Validate the supplies before solving
+        if constraints.supply && node_supply_sum > node_capacity_sum {
+            let report = InfeasibilityReport {
+                total_capacity: node_capacity_sum,
+                total_supply: node_supply_sum,
+            };
+            bail!("{report}");
+        }
 
         // Step 4. Map name indices: src, sink
         let src_map = find_indices(key_name, &name, &src)?;
@@ -212,6 +234,13 @@ impl ::kubegraph_api::solver::NetworkSolver<GraphData<LazyFrame>> for super::Net
             .solve()
             .ok_or_else(|| anyhow!("failed to solve minimum cost flow"))?;
         if output.status() != MinCostFlowStatus::Optimal {
+            if constraints.supply {
+                let report = InfeasibilityReport {
+                    total_capacity: node_capacity_sum,
+                    total_supply: node_supply_sum,
+                };
+                bail!("{report}");
+            }
             bail!("solving the min cost flow is not optimal!");
         }
 
@@ -245,6 +274,25 @@ impl ::kubegraph_api::solver::NetworkSolver<GraphData<LazyFrame>> for super::Net
     }
 }
 
+struct InfeasibilityReport {
+    total_capacity: FlowQuantity,
+    total_supply: FlowQuantity,
+}
+
+impl fmt::Display for InfeasibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            total_capacity,
+            total_supply,
+        } = self;
+
+        write!(
+            f,
+            "infeasible problem: total supply {total_supply}, total capacity {total_capacity}"
+        )
+    }
+}
+
 trait CollectFlow {
     fn collect_flow(&self, name: &str, num_edges: ArcIndex) -> Series {
         Series::from_iter((0..num_edges).map(|index| self.get_flow(index))).with_name(name.into())
@@ -0,0 +1,56 @@
+extern crate polars as pl;
+
+use kubegraph_api::{
+    graph::GraphData,
+    problem::{ProblemConstraints, ProblemSpec},
+    solver::NetworkSolver as _,
+};
+use kubegraph_solver_ortools::NetworkSolver;
+use pl::{df, frame::DataFrame};
+
+fn build_graph(supply: i64) -> GraphData<DataFrame> {
+    let edges = df!(
+        "src"       => [  0],
+        "sink"      => [  1],
+        "capacity"  => [ 20],
+        "unit_cost" => [  1],
+    )
+    .expect("failed to create edges dataframe");
+
+    let nodes = df!(
+        "name"      => [     0,    1],
+        "capacity"  => [    10,   10],
+        "supply"    => [supply,    0],
+        "unit_cost" => [     5,    0],
+    )
+    .expect("failed to create nodes dataframe");
+
+    GraphData { edges, nodes }
+}
+
+fn build_problem() -> ProblemSpec {
+    ProblemSpec {
+        constraints: ProblemConstraints { supply: true },
+        verbose: true,
+        ..Default::default()
+    }
+}
+
+#[::tokio::test]
+async fn solver_constraints_feasible() {
+    let solver = NetworkSolver::default();
+    solver
+        .solve(build_graph(20), &build_problem())
+        .await
+        .expect("failed to optimize the graph");
+}
+
+#[::tokio::test]
+async fn solver_constraints_infeasible() {
+    let solver = NetworkSolver::default();
+    let error = match solver.solve(build_graph(30), &build_problem()).await {
+        Ok(_) => panic!("expected an infeasible problem"),
+        Err(error) => error.to_string(),
+    };
+    assert!(error.contains("total supply 30, total capacity 20"));
+}
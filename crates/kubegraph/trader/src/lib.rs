@@ -2,27 +2,35 @@ mod actix;
 mod db;
 mod session;
 
+use std::{collections::BTreeMap, sync::Arc};
+
 use anyhow::Result;
 use ark_core::signal::FunctionSignal;
 use async_trait::async_trait;
 use clap::Parser;
+use futures::TryStreamExt;
 use kubegraph_api::{
     component::NetworkComponent,
     frame::LazyFrame,
+    graph::GraphScope,
     market::{product::ProductSpec, sub::SubSpec, BaseModel},
     problem::VirtualProblem,
     trader::NetworkTraderContext,
 };
 use kubegraph_market_client::{MarketClient, MarketClientArgs};
+use kubegraph_market_solver_api::MarketSolver as _;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::spawn;
+use tokio::{spawn, sync::RwLock};
 use tracing::{debug, info, instrument, Level};
 
 #[derive(Clone)]
 pub struct NetworkTrader {
     client: MarketClient,
     db: crate::db::NetworkTraderDB,
+    disabled: bool,
+    dry_run: bool,
+    products: Arc<RwLock<BTreeMap<GraphScope, (ProductSpec, <ProductSpec as BaseModel>::Id)>>>,
 }
 
 #[async_trait]
@@ -34,7 +42,12 @@ impl NetworkComponent for NetworkTrader {
         args: <Self as NetworkComponent>::Args,
         signal: &FunctionSignal,
     ) -> Result<Self> {
-        let NetworkTraderArgs { client, db } = args;
+        let NetworkTraderArgs {
+            client,
+            db,
+            disabled,
+            dry_run,
+        } = args;
 
         let db = crate::db::NetworkTraderDB::try_new(db, signal).await?;
         spawn(crate::actix::loop_forever(db.clone()));
@@ -45,12 +58,19 @@ impl NetworkComponent for NetworkTrader {
                 MarketClient::try_new(client, signal).await?
             },
             db,
+            disabled,
+            dry_run,
+            products: Arc::default(),
         })
     }
 }
 
 #[async_trait]
 impl ::kubegraph_api::trader::NetworkTrader<LazyFrame> for NetworkTrader {
+    fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+
     #[instrument(level = Level::INFO, skip(self, problem))]
     async fn is_locked(&self, problem: &VirtualProblem) -> Result<bool> {
         self.db.is_locked(&problem.scope).await
@@ -58,6 +78,10 @@ impl ::kubegraph_api::trader::NetworkTrader<LazyFrame> for NetworkTrader {
 
     #[instrument(level = Level::INFO, skip(self, ctx))]
     async fn register(&self, ctx: NetworkTraderContext<LazyFrame>) -> Result<()> {
+        if self.dry_run {
+            return self.report_register(&ctx).await;
+        }
+
         let mut state = NetworkTraderState::default();
         match self.try_register(&mut state, ctx).await {
             Ok(()) => Ok(()),
@@ -70,6 +94,71 @@ impl ::kubegraph_api::trader::NetworkTrader<LazyFrame> for NetworkTrader {
 }
 
 impl NetworkTrader {
+    /// Report the trades to be registered, without touching the market.
+    ///
+    /// The transactions are estimated over the current price histogram of the
+    /// product, as the market solver would settle them.
+    #[instrument(level = Level::INFO, skip(self, ctx))]
+    async fn report_register(&self, ctx: &NetworkTraderContext<LazyFrame>) -> Result<()> {
+        let scope = &ctx.problem.scope;
+        let spec = ProductSpec {
+            problem: ctx.problem.spec.clone(),
+        };
+        let function = self.db.webhook_endpoint()?;
+
+        info!("[dry-run] Trading {scope}: product {spec:?}, subscriber webhook {function:?}");
+
+        // NOTE: the product is looked up without registering it
+        let prod_id = match self.lookup_product(scope, &spec).await? {
+            Some(prod_id) => prod_id,
+            None => {
+                info!("[dry-run] No transactions for {scope}: product is not registered");
+                return Ok(());
+            }
+        };
+        let histogram = self
+            .client
+            .list_price_histogram(prod_id)
+            .try_collect()
+            .await?;
+        let templates = ::kubegraph_market_solver_trivial::MarketSolver::default()
+            .solve(prod_id, &spec, histogram)
+            .await?;
+
+        if templates.is_empty() {
+            info!("[dry-run] No transactions for {scope}");
+        }
+        for template in templates {
+            info!("[dry-run] Transaction for {scope}: {template:?}");
+        }
+        Ok(())
+    }
+
+    /// Find the registered product of the given spec, if any.
+    ///
+    /// The found product is cached per scope until its problem spec changes.
+    #[instrument(level = Level::INFO, skip(self, scope, spec))]
+    async fn lookup_product(
+        &self,
+        scope: &GraphScope,
+        spec: &ProductSpec,
+    ) -> Result<Option<<ProductSpec as BaseModel>::Id>> {
+        if let Some((cached_spec, prod_id)) = self.products.read().await.get(scope) {
+            if cached_spec == spec {
+                return Ok(Some(*prod_id));
+            }
+        }
+
+        let prod_id = self.client.lookup_product(spec).await?;
+        if let Some(prod_id) = prod_id {
+            self.products
+                .write()
+                .await
+                .insert(scope.clone(), (spec.clone(), prod_id));
+        }
+        Ok(prod_id)
+    }
+
     #[instrument(level = Level::INFO, skip(self, state, ctx))]
     async fn try_register(
         &self,
@@ -148,4 +237,14 @@ pub struct NetworkTraderArgs {
 
     #[command(flatten)]
     pub db: <self::db::NetworkTraderDB as NetworkComponent>::Args,
+
+    /// Leave the problems to the solver only, without locking or registering them
+    #[arg(long, env = "KUBEGRAPH_MARKET_TRADER_DISABLED")]
+    #[serde(default)]
+    pub disabled: bool,
+
+    /// Report the trades to be registered without executing them
+    #[arg(long, env = "KUBEGRAPH_MARKET_TRADER_DRY_RUN")]
+    #[serde(default)]
+    pub dry_run: bool,
 }
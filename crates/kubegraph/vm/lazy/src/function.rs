@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use anyhow::{anyhow, Result};
 use kubegraph_api::{
     frame::LazyFrame,
@@ -8,6 +10,8 @@ use kubegraph_api::{
     problem::VirtualProblem,
 };
 
+use tracing::debug;
+
 use crate::LazyVirtualMachine;
 
 pub trait NetworkFunctionExt
@@ -103,14 +107,26 @@ impl NetworkFunction for NetworkFunctionTemplate<LazyVirtualMachine> {
         nodes: LazyFrame,
         infer_type: NetworkFunctionInferType,
     ) -> Result<GraphEdges<LazyFrame>> {
-        let Self { filter, script } = self;
+        let Self {
+            prelude: _,
+            filter,
+            script,
+        } = self;
+        let timestamp = Instant::now();
 
         let filter = filter
             .as_ref()
             .map(|filter| filter.call_filter(problem, nodes.clone(), infer_type))
             .transpose()?;
 
-        script.call(problem, metadata, nodes, filter, infer_type)
+        let edges = script.call(problem, metadata, nodes, filter, infer_type);
+
+        debug!(
+            target: ::ark_core::tracer::TARGET_METRICS,
+            histogram.kubegraph_vm_function_duration_seconds = timestamp.elapsed().as_secs_f64(),
+            function = %metadata.scope,
+        );
+        edges
     }
 }
 
@@ -130,17 +146,28 @@ where
     let FunctionMetadata {
         scope: GraphScope { namespace, name },
     } = function;
-    let NetworkFunctionTemplate { filter, script } = metadata;
+    let NetworkFunctionTemplate {
+        prelude,
+        filter,
+        script,
+    } = metadata;
+
+    // NOTE: the prelude is compiled first, then the filter and the script
+    let prelude = LazyVirtualMachine::with_lazy_prelude(prelude.as_ref().map(AsRef::as_ref))
+        .map_err(|error| {
+            anyhow!("failed to parse function prelude ({namespace}/{name}): {error}")
+        })?;
 
     Ok(NetworkFunctionTemplate {
+        prelude: None,
         filter: filter
             .as_ref()
-            .map(|input| LazyVirtualMachine::with_lazy_filter(input.as_ref()))
+            .map(|input| prelude.fork_lazy_filter(input.as_ref()))
             .transpose()
             .map_err(|error| {
                 anyhow!("failed to parse function filter ({namespace}/{name}): {error}")
             })?,
-        script: LazyVirtualMachine::with_lazy_script(script.as_ref()).map_err(|error| {
+        script: prelude.fork_lazy_script(script.as_ref()).map_err(|error| {
             anyhow!("failed to parse function script ({namespace}/{name}): {error}")
         })?,
     })
@@ -164,6 +191,7 @@ mod tests {
 
         // Step 2. Add a function
         let function_template = NetworkFunctionTemplate {
+            prelude: None,
             filter: None,
             script: r"
                 capacity = 50;
@@ -209,6 +237,7 @@ mod tests {
 
         // Step 2. Add a function
         let function_template = NetworkFunctionTemplate {
+            prelude: None,
             filter: Some("src != sink and src.supply >= 50 and sink.capacity >= 50"),
             script: r"
                 capacity = 50;
@@ -240,6 +269,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn expand_polars_dataframe_simple_with_dist() {
+        // Step 1. Add nodes
+        let nodes: LazyFrame = ::polars::df!(
+            "name"      => [  "a",   "b"],
+            "capacity"  => [300.0,   0.0],
+            "supply"    => [300.0, 300.0],
+            "unit_cost" => [    5,     1],
+            "lat"       => [  0.0,   3.0],
+            "lon"       => [  0.0,  -4.0],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        // Step 2. Add a function
+        let function_template = NetworkFunctionTemplate {
+            prelude: None,
+            filter: None,
+            script: r"
+                capacity = 50;
+                unit_cost = dist(src.lat, sink.lat, src.lon, sink.lon);
+            ",
+        };
+
+        // Step 3. Call a function
+        let edges = expand_polars_dataframe(nodes, "move", function_template);
+
+        // Step 4. Test outputs
+        assert_eq!(
+            edges,
+            ::polars::df!(
+                "src"            => [   "a",    "a",    "b",    "b"],
+                "src.capacity"   => [ 300.0,  300.0,    0.0,    0.0],
+                "src.supply"     => [ 300.0,  300.0,  300.0,  300.0],
+                "src.unit_cost"  => [     5,      5,      1,      1],
+                "src.lat"        => [   0.0,    0.0,    3.0,    3.0],
+                "src.lon"        => [   0.0,    0.0,   -4.0,   -4.0],
+                "sink"           => [   "a",    "b",    "a",    "b"],
+                "sink.capacity"  => [ 300.0,    0.0,  300.0,    0.0],
+                "sink.supply"    => [ 300.0,  300.0,  300.0,  300.0],
+                "sink.unit_cost" => [     5,      1,      5,      1],
+                "sink.lat"       => [   0.0,    3.0,    0.0,    3.0],
+                "sink.lon"       => [   0.0,   -4.0,    0.0,   -4.0],
+                "capacity"       => [  50.0,   50.0,   50.0,   50.0],
+                "unit_cost"      => [   0.0,    7.0,    7.0,    0.0],
+                "function"       => ["move", "move", "move", "move"],
+            )
+            .expect("failed to create ground-truth edges dataframe")
+            .into(),
+        );
+    }
+
     fn expand_polars_dataframe(
         nodes: LazyFrame,
         function_name: &str,
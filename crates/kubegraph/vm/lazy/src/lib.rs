@@ -1,17 +1,29 @@
 pub mod function;
+pub mod lint;
+
+pub use self::lint::lint;
 
 use std::fmt;
 
 use anyhow::Result;
-use kubegraph_api::vm::Instruction;
+use kubegraph_api::vm::{Instruction, SourceSpan};
 
 #[derive(Clone, Debug, Default)]
 pub struct LazyVirtualMachine {
+    debug_info: Option<DebugInfo>,
     local_variables: Vec<Instruction>,
     parsers: ParserGroup,
+    /// The number of the leading instructions compiled from the prelude
+    prelude_len: usize,
     use_placeholders: bool,
 }
 
+#[derive(Clone, Debug, Default)]
+struct DebugInfo {
+    current: Option<SourceSpan>,
+    spans: Vec<Option<SourceSpan>>,
+}
+
 impl LazyVirtualMachine {
     pub fn with_lazy_filter(input: &str) -> Result<Self> {
         let mut this = Self {
@@ -29,31 +41,95 @@ impl LazyVirtualMachine {
         this.execute_script(input).map(|()| this)
     }
 
+    /// Compile the shared prelude, whose locals are visible to the filter and
+    /// the script forked from it.
+    pub fn with_lazy_prelude(input: Option<&str>) -> Result<Self> {
+        let mut this = Self {
+            use_placeholders: true,
+            ..Default::default()
+        };
+        match input {
+            Some(input) => this.execute_script(input).map(|()| this),
+            None => Ok(this),
+        }
+    }
+
+    /// Compile the filter after the prelude, in the same instruction namespace.
+    pub fn fork_lazy_filter(&self, input: &str) -> Result<Self> {
+        let mut this = self.fork();
+        this.execute_filter(input).map(|()| this)
+    }
+
+    /// Compile the script after the prelude, in the same instruction namespace.
+    ///
+    /// The prelude locals stay local, i.e. they are not written to the edges.
+    pub fn fork_lazy_script(&self, input: &str) -> Result<Self> {
+        let mut this = self.fork();
+        this.execute_script(input).map(|()| this)
+    }
+
+    fn fork(&self) -> Self {
+        let mut this = self.clone();
+        this.prelude_len = this.local_variables.len();
+        this
+    }
+
+    /// Compile the script in strict mode, treating only the given graph
+    /// columns as predefined.
+    pub fn with_known_columns(input: &str, columns: &[&str]) -> Result<Self> {
+        let mut this = Self {
+            use_placeholders: false,
+            ..Default::default()
+        };
+        for column in columns {
+            this.execute_register_value(column.to_string(), None);
+        }
+        this.execute_script(input).map(|()| this)
+    }
+
+    /// Record the source span of each instruction compiled from now on.
+    ///
+    /// This is intended for validation tools, so it is disabled by default.
+    pub fn with_debug_info(mut self) -> Self {
+        self.debug_info.get_or_insert_with(|| DebugInfo {
+            current: None,
+            spans: vec![None; self.local_variables.len()],
+        });
+        self
+    }
+
     pub fn dump_script(&self) -> ::kubegraph_api::vm::Script {
         ::kubegraph_api::vm::Script {
             code: self.local_variables.clone(),
+            debug_info: self
+                .debug_info
+                .as_ref()
+                .map(|debug_info| debug_info.spans.clone()),
         }
     }
 }
 
 mod impl_call {
     use std::{
+        cell::OnceCell,
         collections::BTreeMap,
         ops::{Add, Div, Mul, Neg, Not, Sub},
+        time::Instant,
     };
 
     use anyhow::{bail, Error, Result};
     use kubegraph_api::{
-        frame::{IntoLazySlice, LazyFrame, LazySlice, LazySliceOrScalar},
+        frame::{FirstMatch, IntoLazySlice, LazyFrame, LazySlice, LazySliceOrScalar},
         function::FunctionMetadata,
         graph::{GraphEdges, GraphMetadataExt},
-        ops::{And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
-        problem::VirtualProblem,
+        ops::{Abs, And, Eq, Ge, Gt, Le, Lt, Max, Min, Ne, Or},
+        problem::{ProblemNonFinitePolicy, VirtualProblem},
         vm::{
             BinaryExpr, BuiltInFunctionExpr, Feature, FunctionExpr, Instruction, Number, Stmt,
             UnaryExpr, Value,
         },
     };
+    use tracing::debug;
 
     use super::function::NetworkFunctionInferType;
 
@@ -67,7 +143,7 @@ mod impl_call {
             infer_type: NetworkFunctionInferType,
         ) -> Result<GraphEdges<LazyFrame>> {
             Context::try_new(problem, nodes, infer_type)?
-                .call(&self.local_variables, filter)
+                .call(&self.local_variables, self.prelude_len, filter)
                 .and_then(|ctx| ctx.try_into_edges(&problem.spec.metadata, metadata))
         }
 
@@ -78,13 +154,32 @@ mod impl_call {
             infer_type: NetworkFunctionInferType,
         ) -> Result<LazySlice> {
             Context::try_new(problem, nodes, infer_type)?
-                .call(&self.local_variables, None)
+                .call(&self.local_variables, self.prelude_len, None)
                 .and_then(|ctx| ctx.try_into_filter())
         }
+
+        /// Call the filters in priority order and compose them into a single
+        /// mask, where each edge is assigned to the first matching filter.
+        pub fn call_filter_first_match<'a, I>(
+            filters: I,
+            problem: &VirtualProblem,
+            nodes: LazyFrame,
+            infer_type: NetworkFunctionInferType,
+        ) -> Result<FirstMatch>
+        where
+            I: IntoIterator<Item = &'a Self>,
+        {
+            let slices = filters
+                .into_iter()
+                .map(|filter| filter.call_filter(problem, nodes.clone(), infer_type))
+                .collect::<Result<Vec<_>>>()?;
+            LazySlice::first_match(slices)
+        }
     }
 
     struct Context {
         heap: Heap,
+        non_finite: ProblemNonFinitePolicy,
         stack: Stack,
     }
 
@@ -102,20 +197,33 @@ mod impl_call {
 
             Ok(Self {
                 heap: Heap::new(edges),
+                non_finite: problem.spec.non_finite,
                 stack: Stack::default(),
             })
         }
 
-        fn call<'a, Code>(mut self, code: Code, filter: Option<LazySlice>) -> Result<Self>
+        fn call<'a, Code>(
+            mut self,
+            code: Code,
+            prelude_len: usize,
+            filter: Option<LazySlice>,
+        ) -> Result<Self>
         where
             Code: IntoIterator<Item = &'a Instruction>,
         {
-            let Self { heap, stack } = &mut self;
+            let Self {
+                heap,
+                non_finite,
+                stack,
+            } = &mut self;
 
             if let Some(filter) = filter {
                 heap.edges.apply_filter(filter)?;
             }
 
+            let timestamp = Instant::now();
+            let mut instructions = 0u64;
+            let mut written: Vec<&str> = Vec::default();
             for (pc, ins) in code.into_iter().enumerate() {
                 let Instruction { name, stmt } = ins;
 
@@ -148,7 +256,15 @@ mod impl_call {
                     Some(name) => match value {
                         Variable::Feature(None) => heap.get_feature(name)?,
                         Variable::Number(None) => heap.get_number(name)?,
+                        // NOTE: the prelude locals are only kept in the stack
+                        value if pc < prelude_len => value,
                         value => {
+                            let value = heap.guard_non_finite(name, value, *non_finite)?;
+                            if matches!(value, Variable::LazySlice(_))
+                                && !written.contains(&name.as_str())
+                            {
+                                written.push(name);
+                            }
                             heap.insert(name.clone(), value.clone())?;
                             value
                         }
@@ -158,7 +274,19 @@ mod impl_call {
 
                 // store
                 stack.push(value);
+                instructions += 1;
+            }
+
+            // NOTE: the written columns are checked at once, not to scan the edges per column
+            if *non_finite == ProblemNonFinitePolicy::Fail && !written.is_empty() {
+                heap.edges.ensure_finite(written)?;
             }
+
+            debug!(
+                target: ::ark_core::tracer::TARGET_METRICS,
+                monotonic_counter.kubegraph_vm_instructions_total = instructions,
+                histogram.kubegraph_vm_call_duration_seconds = timestamp.elapsed().as_secs_f64(),
+            );
             Ok(self)
         }
 
@@ -180,6 +308,8 @@ mod impl_call {
     }
 
     struct Heap {
+        /// The input columns, resolved once on the first column lookup
+        columns: OnceCell<Vec<String>>,
         edges: LazyFrame,
         variables: BTreeMap<String, Variable>,
     }
@@ -187,6 +317,7 @@ mod impl_call {
     impl Heap {
         fn new(edges: LazyFrame) -> Self {
             Self {
+                columns: OnceCell::default(),
                 edges,
                 variables: BTreeMap::default(),
             }
@@ -207,11 +338,37 @@ mod impl_call {
         }
 
         fn get_unchecked(&self, key: &str) -> Result<Variable> {
-            self.variables
-                .get(key)
-                .cloned()
-                .map(Ok)
-                .unwrap_or_else(|| self.edges.get_column(key).map(Variable::LazySlice))
+            if let Some(value) = self.variables.get(key) {
+                return Ok(value.clone());
+            }
+
+            // NOTE: the new columns are kept in the variables, so the input schema is enough
+            let columns = match self.columns.get() {
+                Some(columns) => columns,
+                None => {
+                    let columns = self.edges.get_column_names()?;
+                    self.columns.get_or_init(|| columns)
+                }
+            };
+            if !columns.iter().any(|column| column == key) {
+                bail!("column {key:?} not found; available columns: {columns:?}")
+            }
+            self.edges.get_column(key).map(Variable::LazySlice)
+        }
+
+        fn guard_non_finite(
+            &self,
+            key: &str,
+            value: Variable,
+            policy: ProblemNonFinitePolicy,
+        ) -> Result<Variable> {
+            match value {
+                Variable::LazySlice(column) => self
+                    .edges
+                    .guard_non_finite(key, column, policy)
+                    .map(Variable::LazySlice),
+                value => Ok(value),
+            }
         }
 
         fn insert(&mut self, key: String, value: Variable) -> Result<()> {
@@ -360,12 +517,29 @@ mod impl_call {
 
         fn execute_expr_function_builtin(self, op: BuiltInFunctionExpr) -> Result<Variable> {
             match op {
+                BuiltInFunctionExpr::Abs => self.abs(),
+                BuiltInFunctionExpr::Dist => bail!("unexpected function: dist should be expanded"),
                 BuiltInFunctionExpr::Max => self.max(),
                 BuiltInFunctionExpr::Min => self.min(),
             }
         }
     }
 
+    impl Abs for VariableVec {
+        type Output = Result<Variable>;
+
+        fn abs(self) -> Self::Output {
+            let Self(args) = self;
+            match <[Variable; 1]>::try_from(args) {
+                Ok([Variable::LazySlice(arg)]) => Ok(Variable::LazySlice(arg.abs())),
+                Ok([Variable::Feature(_)]) => error_unexpected_type_feature(),
+                Ok([Variable::Number(Some(arg))]) => Ok(Variable::Number(Some(arg.abs()))),
+                Ok([Variable::Number(None)]) => error_undefined_number(),
+                Err(args) => bail!("cannot call Abs with {} arguments: expected 1", args.len()),
+            }
+        }
+    }
+
     macro_rules! impl_expr_unary {
         ( impl $name:ident ($fn:ident) for $src:ident as Feature ) => {
             impl $name for Variable {
@@ -586,24 +760,80 @@ mod impl_call {
 }
 
 mod impl_execute {
-    use anyhow::{anyhow, bail, Result};
+    use anyhow::{anyhow, bail, Error, Result};
     use kubegraph_api::vm::{
-        BinaryExpr, BuiltInFunctionExpr, FunctionExpr, Instruction, Literal, Number,
+        BinaryExpr, BuiltInFunctionExpr, FunctionExpr, Instruction, Literal, Number, SourceSpan,
         Stmt as LazyStmt, UnaryExpr, Value as RefValue,
     };
-    use kubegraph_parser::{Expr, Filter, Script, Stmt, Value};
+    use kubegraph_parser::{Expr, Filter, ParseError, Script, Stmt, Value};
 
     impl super::LazyVirtualMachine {
         pub fn execute_script(&mut self, input: &str) -> Result<()> {
+            if self.debug_info.is_some() {
+                return self.execute_script_with_debug_info(input);
+            }
+
             let Script(stmts) = self
                 .parsers
                 .script
                 .parse(input)
-                .map_err(|error| anyhow!("{error}"))?;
+                .map_err(|error| Error::from(ParseError::new(input, error)))?;
 
             stmts
                 .into_iter()
                 .try_for_each(|stmt| self.execute_stmt(stmt))
+                .map(|()| self.execute_fold_builtins())
+        }
+
+        fn execute_script_with_debug_info(&mut self, input: &str) -> Result<()> {
+            let stmts = self
+                .parsers
+                .script_spanned
+                .parse(input)
+                .map_err(|error| Error::from(ParseError::new(input, error)))?;
+
+            let result = stmts.into_iter().try_for_each(|(start, stmt, end)| {
+                self.execute_set_span(Some(SourceSpan { start, end }));
+                self.execute_stmt(stmt)
+            });
+            self.execute_set_span(None);
+            result.map(|()| self.execute_fold_builtins())
+        }
+
+        /// Compile the script statement by statement, collecting the errors
+        /// with the names of the failed locals instead of stopping at the first one.
+        pub(crate) fn execute_script_collect(
+            &mut self,
+            input: &str,
+        ) -> Vec<(Option<SourceSpan>, Option<String>, Error)> {
+            let stmts = match self.parsers.script_spanned.parse(input) {
+                Ok(stmts) => stmts,
+                Err(error) => return vec![(None, None, ParseError::new(input, error).into())],
+            };
+
+            let mut errors = Vec::new();
+            for (start, stmt, end) in stmts {
+                let span = SourceSpan { start, end };
+                let name = match &stmt {
+                    Stmt::Set { lhs, rhs: _ } => lhs.0.clone(),
+                };
+
+                self.execute_set_span(Some(span));
+                if let Err(error) = self.execute_stmt(stmt) {
+                    // NOTE: define the failed local as a placeholder, not to cascade the error
+                    self.execute_register_value(name.clone(), None);
+                    errors.push((Some(span), Some(name), error));
+                }
+            }
+            self.execute_set_span(None);
+            self.execute_fold_builtins();
+            errors
+        }
+
+        fn execute_set_span(&mut self, span: Option<SourceSpan>) {
+            if let Some(debug_info) = self.debug_info.as_mut() {
+                debug_info.current = span;
+            }
         }
 
         pub fn execute_filter(&mut self, input: &str) -> Result<()> {
@@ -611,9 +841,14 @@ mod impl_execute {
                 .parsers
                 .filter
                 .parse(input)
-                .map_err(|error| anyhow!("{error}"))?;
-
-            match filter {
+                .map_err(|error| Error::from(ParseError::new(input, error)))?;
+
+            // NOTE: a filter is a single expression
+            self.execute_set_span(Some(SourceSpan {
+                start: 0,
+                end: input.len(),
+            }));
+            let result = match filter {
                 Filter::Ensure {
                     value: Literal(name),
                 } => {
@@ -621,6 +856,89 @@ mod impl_execute {
                     Ok(())
                 }
                 Filter::Expr { value: expr } => self.execute_expr(expr).map(|_| ()),
+            };
+            self.execute_set_span(None);
+            result.map(|()| self.execute_fold_builtins())
+        }
+
+        /// Flatten the nested associative built-in calls,
+        /// e.g. `max(a, max(b, c))` into `max(a, b, c)`.
+        ///
+        /// The inner call is folded only if it is anonymous and used once.
+        fn execute_fold_builtins(&mut self) {
+            let code = &mut self.local_variables;
+
+            // Count the references of each instruction
+            let mut uses = vec![0usize; code.len()];
+            for Instruction { name: _, stmt } in code.iter() {
+                for_each_reference(stmt, |index| uses[index] += 1);
+            }
+
+            // Flatten the calls in order, so that the inner calls are already flattened
+            let mut removed = vec![false; code.len()];
+            for index in 0..code.len() {
+                let op = match &code[index].stmt {
+                    LazyStmt::FunctionExpr {
+                        op:
+                            op @ FunctionExpr::BuiltIn(
+                                BuiltInFunctionExpr::Max | BuiltInFunctionExpr::Min,
+                            ),
+                        args: _,
+                    } => op.clone(),
+                    _ => continue,
+                };
+
+                let args = match &mut code[index].stmt {
+                    LazyStmt::FunctionExpr { args, .. } => ::std::mem::take(args),
+                    _ => unreachable!(),
+                };
+                let mut flattened = Vec::with_capacity(args.len());
+                for arg in args {
+                    match arg {
+                        RefValue::Variable(inner) if uses[inner] == 1 => match &code[inner] {
+                            Instruction {
+                                name: None,
+                                stmt:
+                                    LazyStmt::FunctionExpr {
+                                        op: inner_op,
+                                        args: inner_args,
+                                    },
+                            } if *inner_op == op => {
+                                flattened.extend(inner_args.iter().cloned());
+                                removed[inner] = true;
+                            }
+                            _ => flattened.push(arg),
+                        },
+                        arg => flattened.push(arg),
+                    }
+                }
+                if let LazyStmt::FunctionExpr { args, .. } = &mut code[index].stmt {
+                    *args = flattened;
+                }
+            }
+            if !removed.contains(&true) {
+                return;
+            }
+
+            // Remove the folded calls and remap the references
+            let mut indices = Vec::with_capacity(code.len());
+            let mut next = 0;
+            for &removed in &removed {
+                indices.push(next);
+                if !removed {
+                    next += 1;
+                }
+            }
+
+            let mut removed_iter = removed.iter();
+            code.retain(|_| !removed_iter.next().unwrap());
+            for Instruction { name: _, stmt } in code.iter_mut() {
+                remap_references(stmt, |index| indices[index]);
+            }
+
+            if let Some(debug_info) = self.debug_info.as_mut() {
+                let mut removed_iter = removed.iter();
+                debug_info.spans.retain(|_| !removed_iter.next().unwrap());
             }
         }
 
@@ -652,6 +970,9 @@ mod impl_execute {
         pub(crate) fn execute_register_instruction(&mut self, ins: Instruction) -> RefValue {
             let index = self.local_variables.len();
             self.local_variables.push(ins);
+            if let Some(debug_info) = self.debug_info.as_mut() {
+                debug_info.spans.push(debug_info.current);
+            }
             RefValue::Variable(index)
         }
 
@@ -688,11 +1009,15 @@ mod impl_execute {
                 Expr::Function { op, args } => self.execute_expr_function(op, args)?,
             };
 
+            Ok(self.execute_register_stmt(stmt))
+        }
+
+        fn execute_register_stmt(&mut self, stmt: LazyStmt) -> RefValue {
             match stmt.to_value() {
-                Some(value) => Ok(value),
+                Some(value) => value,
                 None => {
                     let ins = Instruction { name: None, stmt };
-                    Ok(self.execute_register_instruction(ins))
+                    self.execute_register_instruction(ins)
                 }
             }
         }
@@ -852,11 +1177,50 @@ mod impl_execute {
             args: Vec<RefValue>,
         ) -> Result<LazyStmt> {
             match op {
+                BuiltInFunctionExpr::Abs => self.execute_expr_function_builtin_abs(args),
+                BuiltInFunctionExpr::Dist => self.execute_expr_function_builtin_dist(args),
                 BuiltInFunctionExpr::Max => self.execute_expr_function_builtin_max(args),
                 BuiltInFunctionExpr::Min => self.execute_expr_function_builtin_min(args),
             }
         }
 
+        fn execute_expr_function_builtin_abs(&mut self, args: Vec<RefValue>) -> Result<LazyStmt> {
+            use kubegraph_api::ops::Abs;
+
+            args.abs()
+        }
+
+        /// Expand `dist(a0, b0, a1, b1, ...)` into `abs(a0 - b0) + abs(a1 - b1) + ...`.
+        fn execute_expr_function_builtin_dist(&mut self, args: Vec<RefValue>) -> Result<LazyStmt> {
+            use std::ops::{Add, Sub};
+
+            use kubegraph_api::ops::Abs;
+
+            if args.is_empty() || args.len() % 2 != 0 {
+                bail!(
+                    "cannot call Dist with {} arguments: expected an even number of arguments",
+                    args.len(),
+                )
+            }
+
+            let mut acc: Option<LazyStmt> = None;
+            let mut args = args.into_iter();
+            while let (Some(lhs), Some(rhs)) = (args.next(), args.next()) {
+                let diff = lhs.sub(rhs)?;
+                let term = vec![self.execute_register_stmt(diff)].abs()?;
+
+                acc = Some(match acc {
+                    Some(acc) => {
+                        let acc = self.execute_register_stmt(acc);
+                        let term = self.execute_register_stmt(term);
+                        acc.add(term)?
+                    }
+                    None => term,
+                });
+            }
+            acc.ok_or_else(|| anyhow!("cannot call Dist with empty arguments"))
+        }
+
         fn execute_expr_function_builtin_max(&mut self, args: Vec<RefValue>) -> Result<LazyStmt> {
             use kubegraph_api::ops::Max;
 
@@ -871,10 +1235,51 @@ mod impl_execute {
     }
 }
 
+fn for_each_reference(stmt: &::kubegraph_api::vm::Stmt, mut f: impl FnMut(usize)) {
+    use kubegraph_api::vm::{Stmt, Value};
+
+    let mut visit = |value: &Value| {
+        if let Value::Variable(index) = value {
+            f(*index)
+        }
+    };
+    match stmt {
+        Stmt::Identity { index } => visit(&Value::Variable(*index)),
+        Stmt::DefineLocalFeature { .. } | Stmt::DefineLocalValue { .. } => {}
+        Stmt::BinaryExpr { lhs, rhs, op: _ } => {
+            visit(lhs);
+            visit(rhs);
+        }
+        Stmt::UnaryExpr { src, op: _ } => visit(src),
+        Stmt::FunctionExpr { op: _, args } => args.iter().for_each(visit),
+    }
+}
+
+fn remap_references(stmt: &mut ::kubegraph_api::vm::Stmt, f: impl Fn(usize) -> usize) {
+    use kubegraph_api::vm::{Stmt, Value};
+
+    let visit = |value: &mut Value| {
+        if let Value::Variable(index) = value {
+            *index = f(*index)
+        }
+    };
+    match stmt {
+        Stmt::Identity { index } => *index = f(*index),
+        Stmt::DefineLocalFeature { .. } | Stmt::DefineLocalValue { .. } => {}
+        Stmt::BinaryExpr { lhs, rhs, op: _ } => {
+            visit(lhs);
+            visit(rhs);
+        }
+        Stmt::UnaryExpr { src, op: _ } => visit(src),
+        Stmt::FunctionExpr { op: _, args } => args.iter_mut().for_each(visit),
+    }
+}
+
 #[derive(Default)]
 struct ParserGroup {
     filter: ::kubegraph_parser::FilterParser,
     script: ::kubegraph_parser::ScriptParser,
+    script_spanned: ::kubegraph_parser::SpannedScriptParser,
 }
 
 impl Clone for ParserGroup {
@@ -929,6 +1334,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lazy_negative_literal() {
+        let mut vm = LazyVirtualMachine::default();
+
+        let input = "a = -5; b = a-1;";
+        vm.execute_script(input).expect("failed to compile");
+
+        let script = vm.dump_script();
+
+        assert_eq!(
+            script.code,
+            &[
+                Instruction {
+                    name: Some("a".into()),
+                    stmt: Stmt::DefineLocalValue {
+                        value: Some(Number::new(-5.)),
+                    },
+                },
+                Instruction {
+                    name: Some("b".into()),
+                    stmt: Stmt::DefineLocalValue {
+                        value: Some(Number::new(-6.)),
+                    },
+                },
+            ]
+        );
+    }
+
     #[test]
     fn lazy_simple_add_with_placeholder() {
         let mut vm = LazyVirtualMachine::default();
@@ -967,4 +1400,402 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn lazy_debug_info() {
+        let mut vm = LazyVirtualMachine::default().with_debug_info();
+        vm.execute_register_value("a".into(), None);
+
+        let input = "b = 3 + 4;\nc = a + b;";
+        vm.execute_script(input).expect("failed to compile");
+
+        let script = vm.dump_script();
+        let spans = script.debug_info.expect("should have debug info");
+        assert_eq!(spans.len(), script.code.len());
+
+        // The placeholder has no source
+        assert_eq!(spans[0], None);
+        assert_eq!(
+            spans[1].map(|span| &input[span.start..span.end]),
+            Some("b = 3 + 4")
+        );
+        for span in &spans[2..] {
+            assert_eq!(
+                span.map(|span| &input[span.start..span.end]),
+                Some("c = a + b")
+            );
+        }
+
+        // Production compilation stays lean
+        let mut vm = LazyVirtualMachine::default();
+        vm.execute_script(input).expect("failed to compile");
+        assert_eq!(vm.dump_script().debug_info, None);
+    }
+
+    #[test]
+    fn lazy_fold_builtins() {
+        use kubegraph_api::{
+            frame::LazyFrame,
+            graph::{GraphFilter, GraphScope},
+            problem::{ProblemSpec, VirtualProblem},
+            vm::{BuiltInFunctionExpr, FunctionExpr},
+        };
+
+        use crate::function::NetworkFunctionInferType;
+
+        let input = "max(x, max(y, 3)) > 4";
+        let vm = LazyVirtualMachine::with_lazy_filter(input).expect("failed to compile");
+
+        // The nested calls are folded into a single call
+        let calls: Vec<_> = vm
+            .dump_script()
+            .code
+            .into_iter()
+            .filter_map(|Instruction { name: _, stmt }| match stmt {
+                Stmt::FunctionExpr { op, args } => Some((op, args)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            [(
+                FunctionExpr::BuiltIn(BuiltInFunctionExpr::Max),
+                vec![
+                    Value::Variable(0),
+                    Value::Variable(1),
+                    Value::Number(Number::new(3.)),
+                ],
+            )],
+        );
+
+        // The folded call works on the mixed slices
+        let nodes: LazyFrame = ::polars::df!(
+            "name"  => [ "a",  "b",  "c",  "d"],
+            "x"     => [ 1.0,  5.0,  2.0,  0.0],
+            "y"     => [ 2.0,  1.0,  6.0,  0.0],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+
+        let mask = vm
+            .call_filter(&problem, nodes.clone(), NetworkFunctionInferType::Node)
+            .expect("failed to call the filter");
+
+        let mut nodes = nodes;
+        nodes.apply_filter(mask).unwrap();
+
+        let nodes = nodes
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect filtered nodes");
+        assert_eq!(nodes.height(), 2);
+    }
+
+    #[test]
+    fn lazy_shared_prelude() {
+        use kubegraph_api::{
+            frame::LazyFrame,
+            graph::{GraphFilter, GraphScope},
+            problem::{ProblemSpec, VirtualProblem},
+        };
+
+        use crate::function::NetworkFunctionInferType;
+
+        let prelude = LazyVirtualMachine::with_lazy_prelude(Some("threshold = x * 2;"))
+            .expect("failed to compile the prelude");
+        let filter = prelude
+            .fork_lazy_filter("y > threshold")
+            .expect("failed to compile the filter");
+        let script = prelude
+            .fork_lazy_script("z = threshold + 1;")
+            .expect("failed to compile the script");
+
+        // Both the filter and the script start with the prelude
+        let prelude = prelude.dump_script().code;
+        for vm in [&filter, &script] {
+            let code = vm.dump_script().code;
+            assert_eq!(&code[..prelude.len()], prelude.as_slice());
+
+            // The prelude locals are not redefined as placeholders
+            let redefined = code[prelude.len()..]
+                .iter()
+                .filter(|ins| ins.name.as_deref() == Some("threshold"))
+                .count();
+            assert_eq!(redefined, 0);
+        }
+
+        // The filter can reference the prelude locals
+        let nodes: LazyFrame = ::polars::df!(
+            "name"  => [ "a",  "b",  "c"],
+            "x"     => [ 1.0,  2.0,  3.0],
+            "y"     => [ 1.0,  5.0,  7.0],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+
+        let mask = filter
+            .call_filter(&problem, nodes.clone(), NetworkFunctionInferType::Node)
+            .expect("failed to call the filter");
+
+        let mut nodes = nodes;
+        nodes.apply_filter(mask).unwrap();
+
+        let nodes = nodes
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect filtered nodes");
+        assert_eq!(nodes.height(), 2);
+    }
+
+    #[test]
+    fn lazy_shared_prelude_output_columns() {
+        use kubegraph_api::{
+            frame::LazyFrame,
+            function::FunctionMetadata,
+            graph::{GraphFilter, GraphScope},
+            problem::{ProblemSpec, VirtualProblem},
+        };
+
+        use crate::function::NetworkFunctionInferType;
+
+        let nodes: LazyFrame = ::polars::df!(
+            "name"  => [ "a",  "b",  "c"],
+            "x"     => [ 1.0,  2.0,  3.0],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        let metadata = FunctionMetadata {
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "shift".into(),
+            },
+        };
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+
+        let prelude = LazyVirtualMachine::with_lazy_prelude(Some("threshold = x * 2;"))
+            .expect("failed to compile the prelude");
+        let script = prelude
+            .fork_lazy_script("z = threshold + 1;")
+            .expect("failed to compile the script");
+
+        let nodes = script
+            .call(
+                &problem,
+                &metadata,
+                nodes,
+                None,
+                NetworkFunctionInferType::Node,
+            )
+            .expect("failed to call the script")
+            .into_inner()
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect nodes");
+
+        // Only the script locals are written
+        let columns: Vec<_> = nodes
+            .get_column_names()
+            .into_iter()
+            .map(|column| column.as_str())
+            .collect();
+        assert!(columns.contains(&"z"));
+        assert!(!columns.contains(&"threshold"));
+
+        let z: Vec<_> = nodes
+            .column("z")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(z, [3.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn lazy_guard_non_finite() {
+        use kubegraph_api::{
+            frame::LazyFrame,
+            function::FunctionMetadata,
+            graph::{GraphFilter, GraphScope},
+            problem::{ProblemNonFinitePolicy, ProblemSpec, VirtualProblem},
+        };
+
+        use crate::function::NetworkFunctionInferType;
+
+        let nodes: LazyFrame = ::polars::df!(
+            "name"  => [ "a",  "b",  "c"],
+            "x"     => [ 1.0,  2.0,  0.0],
+            "y"     => [ 1.0,  0.0,  0.0],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        let metadata = FunctionMetadata {
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "divide".into(),
+            },
+        };
+        let vm = LazyVirtualMachine::with_lazy_script("z = x / y;").expect("failed to compile");
+
+        let call = |non_finite| {
+            let problem = VirtualProblem {
+                filter: GraphFilter::all("default".into()),
+                scope: GraphScope {
+                    namespace: "default".into(),
+                    name: "optimize-warehouses".into(),
+                },
+                spec: ProblemSpec {
+                    non_finite,
+                    ..Default::default()
+                },
+            };
+            vm.call(
+                &problem,
+                &metadata,
+                nodes.clone(),
+                None,
+                NetworkFunctionInferType::Node,
+            )
+        };
+
+        // The per-row division by zero is detected
+        let error = call(ProblemNonFinitePolicy::Fail)
+            .err()
+            .expect("should detect the division by zero");
+        assert!(error.to_string().contains("\"z\""));
+
+        // The non-finite values are replaced with the sentinel
+        let nodes = call(ProblemNonFinitePolicy::Replace {
+            value: Number::new(-1.),
+        })
+        .expect("failed to call the script")
+        .into_inner()
+        .try_into_polars()
+        .unwrap()
+        .collect()
+        .expect("failed to collect nodes");
+        let z: Vec<_> = nodes
+            .column("z")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(z, [1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn lazy_parse_error_location() {
+        let input = "a = 1 + 2;\nb = 3 +;";
+        let error = LazyVirtualMachine::with_lazy_script(input)
+            .expect_err("should fail to compile")
+            .downcast::<::kubegraph_parser::ParseError>()
+            .expect("should be a parse error");
+
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, 8);
+        assert!(!error.expected.is_empty());
+    }
+
+    #[test]
+    fn lazy_known_columns() {
+        let input = "b = a + 1;";
+        LazyVirtualMachine::with_known_columns(input, &["a"]).expect("failed to compile");
+        LazyVirtualMachine::with_known_columns(input, &["c"])
+            .expect_err("should reject an undefined column");
+    }
+
+    #[test]
+    fn lazy_filter_first_match() {
+        use kubegraph_api::{
+            frame::{FirstMatch, LazyFrame},
+            graph::{GraphFilter, GraphScope},
+            problem::{ProblemSpec, VirtualProblem},
+        };
+
+        use crate::function::NetworkFunctionInferType;
+
+        let nodes: LazyFrame = ::polars::df!(
+            "name"      => [  "a",   "b",   "c"],
+            "capacity"  => [  0.0, 300.0, 300.0],
+            "supply"    => [300.0,   0.0, 300.0],
+            "unit_cost" => [    5,     1,     1],
+        )
+        .expect("failed to create nodes dataframe")
+        .into();
+
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+
+        // Overlapping filters: (src in [a, c]) and (sink in [b, c])
+        let filters = [
+            LazyVirtualMachine::with_lazy_filter("src.supply >= 100").unwrap(),
+            LazyVirtualMachine::with_lazy_filter("sink.capacity >= 100").unwrap(),
+        ];
+        let FirstMatch { index, mask } = LazyVirtualMachine::call_filter_first_match(
+            &filters,
+            &problem,
+            nodes.clone(),
+            NetworkFunctionInferType::Edge,
+        )
+        .expect("failed to compose filters");
+
+        let mut edges = nodes.fabric(&problem.spec).unwrap();
+        edges.insert_column("rule", index).unwrap();
+        edges.apply_filter(mask).unwrap();
+
+        let edges = edges
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect filtered edges");
+        assert_eq!(edges.height(), 8);
+
+        // The overlapping edges are taken by the first filter
+        let rules: Vec<_> = edges
+            .column("rule")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(rules.iter().filter(|&&rule| rule == 0).count(), 6);
+        assert_eq!(rules.iter().filter(|&&rule| rule == 1).count(), 2);
+    }
 }
@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+
+use anyhow::Error;
+use kubegraph_api::vm::{Instruction, SourceSpan, Stmt};
+use kubegraph_parser::ParseError;
+use serde::{Deserialize, Serialize};
+
+use crate::LazyVirtualMachine;
+
+/// Compile the filter and the script of a function offline, collecting all
+/// the diagnostics instead of stopping at the first one.
+///
+/// If `known_columns` is empty, the unknown references are reported as the
+/// required columns; otherwise only the given graph columns are predefined,
+/// along with the edge endpoints and the `src.` and `sink.` edge variants.
+pub fn lint(script: &str, filter: Option<&str>, known_columns: &[&str]) -> LintReport {
+    let mut base = LazyVirtualMachine {
+        use_placeholders: known_columns.is_empty(),
+        ..Default::default()
+    }
+    .with_debug_info();
+    for column in known_columns {
+        base.execute_register_value(column.to_string(), None);
+        base.execute_register_value(format!("src.{column}"), None);
+        base.execute_register_value(format!("sink.{column}"), None);
+    }
+    if !known_columns.is_empty() {
+        for side in ["src", "sink"] {
+            base.execute_register_value(side.into(), None);
+        }
+    }
+    let known_len = base.local_variables.len();
+
+    let mut report = LintReport::default();
+    let mut provided = BTreeSet::default();
+    let mut required = BTreeSet::default();
+
+    // NOTE: the filter and the script are compiled on their own, as the runtime does
+    if let Some(input) = filter {
+        let mut vm = base.clone();
+        if let Err(error) = vm.execute_filter(input) {
+            let span = SourceSpan {
+                start: 0,
+                end: input.len(),
+            };
+            report
+                .diagnostics
+                .push(LintDiagnostic::new(LintSource::Filter, Some(span), error));
+        }
+        collect_columns(&vm, known_len, &mut provided, &mut required);
+    }
+
+    let mut vm = base;
+    let errors = vm.execute_script_collect(script);
+    collect_columns(&vm, known_len, &mut provided, &mut required);
+    for (span, name, error) in errors {
+        // NOTE: the failed locals are still written by the script
+        if let Some(name) = name {
+            let name = normalize_column(&name);
+            required.remove(&name);
+            provided.insert(name);
+        }
+        report
+            .diagnostics
+            .push(LintDiagnostic::new(LintSource::Script, span, error));
+    }
+
+    report.provided = provided.into_iter().collect();
+    report.required = required.into_iter().collect();
+    report
+}
+
+fn collect_columns(
+    vm: &LazyVirtualMachine,
+    known_len: usize,
+    provided: &mut BTreeSet<String>,
+    required: &mut BTreeSet<String>,
+) {
+    let code = &vm.local_variables;
+    let (known, compiled) = code.split_at(known_len.min(code.len()));
+
+    for Instruction { name, stmt } in compiled {
+        crate::for_each_reference(stmt, |index| {
+            if let Some(Instruction {
+                name: Some(name), ..
+            }) = known.get(index)
+            {
+                required.insert(normalize_column(name));
+            }
+        });
+
+        let name = match name {
+            Some(name) => normalize_column(name),
+            None => continue,
+        };
+        match stmt {
+            Stmt::DefineLocalFeature { value: None } | Stmt::DefineLocalValue { value: None } => {
+                required.insert(name)
+            }
+            _ => provided.insert(name),
+        };
+    }
+}
+
+/// Map the edge columns (e.g. `src.supply`) onto their node columns,
+/// as the dependency solver does.
+fn normalize_column(name: &str) -> String {
+    name.strip_prefix("src.")
+        .or_else(|| name.strip_prefix("sink."))
+        .unwrap_or(name)
+        .into()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintReport {
+    #[serde(default)]
+    pub diagnostics: Vec<LintDiagnostic>,
+    /// The columns written by the function
+    #[serde(default)]
+    pub provided: Vec<String>,
+    /// The columns read by the function
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+impl LintReport {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintDiagnostic {
+    pub kind: LintDiagnosticKind,
+    pub message: String,
+    pub source: LintSource,
+    /// The byte range of the offending statement, if known
+    #[serde(default)]
+    pub span: Option<SourceSpan>,
+}
+
+impl LintDiagnostic {
+    fn new(source: LintSource, span: Option<SourceSpan>, error: Error) -> Self {
+        match error.downcast::<ParseError>() {
+            Ok(error) => Self {
+                kind: LintDiagnosticKind::Parse,
+                message: error.to_string(),
+                source,
+                // NOTE: the parse error has its own location
+                span: None,
+            },
+            Err(error) => Self {
+                kind: LintDiagnosticKind::Compile,
+                message: error.to_string(),
+                source,
+                span,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LintDiagnosticKind {
+    Parse,
+    Compile,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LintSource {
+    Filter,
+    Script,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_collect_all_errors() {
+        let script = "a = 1 / 0; b = c + 1; d = a + b; e = capacity * 2;";
+        let report = lint(script, Some("supply > 0"), &["capacity", "supply"]);
+
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.kind, diagnostic.source, diagnostic.span))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    LintDiagnosticKind::Compile,
+                    LintSource::Script,
+                    Some(SourceSpan { start: 0, end: 9 }),
+                ),
+                (
+                    LintDiagnosticKind::Compile,
+                    LintSource::Script,
+                    Some(SourceSpan { start: 11, end: 20 }),
+                ),
+            ],
+        );
+        assert!(report.diagnostics[1].message.contains("\"c\""));
+        assert_eq!(report.provided, ["a", "b", "d", "e"]);
+        assert_eq!(report.required, ["capacity", "supply"]);
+    }
+
+    #[test]
+    fn lint_parse_error() {
+        let report = lint("a = ;", None, &[]);
+
+        assert!(!report.is_ok());
+        assert_eq!(report.diagnostics[0].kind, LintDiagnosticKind::Parse);
+        assert_eq!(report.diagnostics[0].span, None);
+        assert!(report.diagnostics[0].message.contains("line 1"));
+    }
+
+    #[test]
+    fn lint_edge_columns() {
+        let filter = "src != sink and src.supply > 0 and src.supply > sink.supply";
+        let script = "capacity = 50; unit_cost = sink.capacity * 2;";
+
+        // The edge variants of the known columns are predefined
+        let report = lint(script, Some(filter), &["capacity", "supply"]);
+        assert!(report.is_ok(), "{:?}", report.diagnostics);
+        assert_eq!(report.provided, ["capacity", "unit_cost"]);
+        assert_eq!(report.required, ["capacity", "sink", "src", "supply"]);
+
+        // The edge prefixes are stripped from the requirements
+        let report = lint(script, Some(filter), &[]);
+        assert!(report.is_ok(), "{:?}", report.diagnostics);
+        assert_eq!(report.required, ["capacity", "sink", "src", "supply"]);
+    }
+
+    #[test]
+    fn lint_lenient_requirements() {
+        let report = lint("capacity = 50; unit_cost = supply * 2;", None, &[]);
+
+        assert!(report.is_ok());
+        assert_eq!(report.provided, ["capacity", "unit_cost"]);
+        assert_eq!(report.required, ["supply"]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::net::SocketAddr;
+
+use actix_web::{get, web::Data, App, HttpResponse, HttpServer, Responder};
+use anyhow::{anyhow, Result};
+use futures::TryFutureExt;
+use kubegraph_api::vm::{NetworkFallbackPolicy, NetworkVirtualMachine as _};
+use tokio::time::sleep;
+use tracing::{error, info, instrument, warn, Level};
+
+use crate::NetworkVirtualMachine;
+
+#[instrument(level = Level::INFO, skip(vm))]
+#[get("/healthz")]
+async fn health(vm: Data<NetworkVirtualMachine>) -> impl Responder {
+    if vm.is_healthy().await {
+        HttpResponse::Ok().json("healthy")
+    } else if let Some(reason) = vm.termination_reason() {
+        HttpResponse::ServiceUnavailable().json(format!("terminated: {reason}"))
+    } else {
+        HttpResponse::ServiceUnavailable().json("unhealthy")
+    }
+}
+
+#[instrument(level = Level::INFO, skip(vm))]
+#[get("/metrics")]
+async fn export_metrics(vm: Data<NetworkVirtualMachine>) -> impl Responder {
+    match vm.metrics.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(error) => HttpResponse::InternalServerError().body(error.to_string()),
+    }
+}
+
+pub(crate) async fn loop_forever(addr: SocketAddr, vm: NetworkVirtualMachine) {
+    loop {
+        if let Err(error) = try_loop_forever(addr, &vm).await {
+            error!("failed to operate metrics server: {error}");
+
+            match vm.fallback_policy() {
+                NetworkFallbackPolicy::Interval { interval } => {
+                    warn!("restarting metrics server in {interval:?}...");
+                    sleep(interval).await;
+                    info!("Restarted metrics server");
+                }
+                NetworkFallbackPolicy::Never => break,
+            }
+        }
+    }
+}
+
+async fn try_loop_forever(addr: SocketAddr, vm: &NetworkVirtualMachine) -> Result<()> {
+    info!("Starting metrics server...");
+
+    let vm = Data::new(vm.clone());
+
+    // Create a http server
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(Data::clone(&vm))
+            .service(health)
+            .service(export_metrics)
+    })
+    .bind(addr)
+    .map_err(|error| anyhow!("failed to bind to {addr}: {error}"))?;
+
+    // Start http server
+    server.run().map_err(Into::into).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test;
+    use kube::api::ObjectMeta;
+    use kubegraph_api::{
+        connector::{NetworkConnectorCrd, NetworkConnectorKind, NetworkConnectorSpec},
+        graph::{Graph, GraphData, GraphFilter, GraphMetadata, GraphScope, NetworkGraphDB},
+        problem::{ProblemSpec, VirtualProblem},
+        vm::{NetworkTerminationReason, NetworkVirtualMachineExt},
+    };
+
+    use super::*;
+    use crate::args::NetworkVirtualMachineArgs;
+
+    async fn build_vm() -> NetworkVirtualMachine {
+        crate::tests::build_vm(NetworkVirtualMachineArgs {
+            metrics_addr: Some(([127, 0, 0, 1], 0).into()),
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn get_metrics(vm: &NetworkVirtualMachine) -> String {
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(vm.clone()))
+                .service(export_metrics),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        String::from_utf8(body.to_vec()).expect("failed to decode metrics")
+    }
+
+    #[::tokio::test]
+    async fn export_solved_steps_only() {
+        // Step 1. Define problems
+        let vm = build_vm().await;
+
+        // Step 2. Run an idle step, which should not be observed
+        let state = Default::default();
+        vm.step(state).await.expect("failed to step");
+        assert!(get_metrics(&vm)
+            .await
+            .contains("kubegraph_vm_steps_completed_total 0\n"));
+
+        // Step 3. Register the initial graph
+        let nodes = ::polars::df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "unit_cost" => [   5i64,    1i64],
+        )
+        .expect("failed to create nodes dataframe");
+        let edges = ::polars::df!(
+            "src"       => [    "a"],
+            "sink"      => [    "b"],
+            "capacity"  => [  50i64],
+            "unit_cost" => [   1i64],
+        )
+        .expect("failed to create edges dataframe");
+        let connector = NetworkConnectorCrd {
+            metadata: ObjectMeta {
+                namespace: Some("default".into()),
+                name: Some("warehouse".into()),
+                ..Default::default()
+            },
+            spec: NetworkConnectorSpec {
+                kind: NetworkConnectorKind::Unknown {},
+                ttl: None,
+            },
+        };
+        let scope = GraphScope::from_resource(&connector);
+        let graph = Graph {
+            connector: Some(connector.into()),
+            data: GraphData {
+                edges: edges.into(),
+                nodes: nodes.into(),
+            },
+            metadata: GraphMetadata::default(),
+            scope,
+        };
+        vm.graph_db.insert(graph).await.unwrap();
+
+        // Step 4. Solve the graph once
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+        let state = Default::default();
+        vm.step_with_custom_problem(state, problem)
+            .await
+            .expect("failed to optimize");
+
+        // Step 5. Verify the metrics
+        let metrics = get_metrics(&vm).await;
+        assert!(metrics.contains("kubegraph_vm_steps_completed_total 1\n"));
+        assert!(metrics
+            .contains("kubegraph_vm_graph_nodes{problem=\"default/optimize-warehouses\"} 2\n"));
+    }
+
+    #[::tokio::test]
+    async fn health_terminated_converged() {
+        let vm = build_vm().await;
+
+        // Only the first reason is kept, even if the workers are shut down later
+        vm.observe_termination(NetworkTerminationReason::Converged);
+        vm.close_workers().await.expect("failed to close workers");
+
+        let app =
+            test::init_service(App::new().app_data(Data::new(vm.clone())).service(health)).await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.status(),
+            ::actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let body = test::read_body(res).await;
+        assert_eq!(body, "\"terminated: converged\"");
+    }
+}
@@ -1,4 +1,8 @@
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+
+use anyhow::{bail, Result};
 use clap::Parser;
+use duration_string::DurationString;
 use kubegraph_api::{
     component::NetworkComponent,
     vm::{NetworkFallbackPolicy, NetworkVirtualMachine, NetworkVirtualMachineRestartPolicy},
@@ -43,6 +47,106 @@ pub struct NetworkArgs {
     pub vm: NetworkVirtualMachineArgs,
 }
 
+impl NetworkArgs {
+    pub fn builder() -> NetworkArgsBuilder {
+        NetworkArgsBuilder::default()
+    }
+
+    /// Validate the options across the components.
+    pub fn validate(&self) -> Result<()> {
+        let NetworkVirtualMachineArgs {
+            health_staleness,
+            metrics_addr,
+            ..
+        } = self.vm;
+
+        if let Some(health_staleness) = health_staleness {
+            if health_staleness.is_zero() {
+                bail!("health staleness should be positive");
+            }
+            if metrics_addr.is_none() {
+                bail!("health staleness requires the metrics address to serve /healthz");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build [`NetworkArgs`] in-process, validating the options across the components.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkArgsBuilder {
+    args: NetworkArgs,
+}
+
+impl NetworkArgsBuilder {
+    pub fn dependency_graph(
+        mut self,
+        dependency_graph: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::DependencySolver as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.dependency_graph = dependency_graph;
+        self
+    }
+
+    pub fn graph_db(
+        mut self,
+        graph_db: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::GraphDB as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.graph_db = graph_db;
+        self
+    }
+
+    pub fn resource_db(
+        mut self,
+        resource_db: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::ResourceDB as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.resource_db = resource_db;
+        self
+    }
+
+    pub fn runner(
+        mut self,
+        runner: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::Runner as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.runner = runner;
+        self
+    }
+
+    pub fn solver(
+        mut self,
+        solver: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::Solver as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.solver = solver;
+        self
+    }
+
+    pub fn trader(
+        mut self,
+        trader: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::Trader as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.trader = trader;
+        self
+    }
+
+    pub fn visualizer(
+        mut self,
+        visualizer: <<crate::NetworkVirtualMachine as NetworkVirtualMachine>::Visualizer as NetworkComponent>::Args,
+    ) -> Self {
+        self.args.visualizer = visualizer;
+        self
+    }
+
+    pub fn vm(mut self, vm: NetworkVirtualMachineArgs) -> Self {
+        self.args.vm = vm;
+        self
+    }
+
+    pub fn build(self) -> Result<NetworkArgs> {
+        let Self { args } = self;
+        args.validate()?;
+        Ok(args)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema, Parser)]
 #[clap(rename_all = "kebab-case")]
 #[serde(rename_all = "camelCase")]
@@ -56,6 +160,21 @@ pub struct NetworkVirtualMachineArgs {
     #[serde(default)]
     pub fallback_policy: NetworkFallbackPolicy,
 
+    /// Report unhealthy if the VM loop has not iterated within this window
+    #[arg(
+        long,
+        env = "KUBEGRAPH_VM_HEALTH_STALENESS",
+        value_name = "DURATION",
+        value_parser = parse_duration,
+    )]
+    #[serde(default)]
+    pub health_staleness: Option<Duration>,
+
+    /// Address serving `/metrics` and `/healthz`
+    #[arg(long, env = "KUBEGRAPH_VM_METRICS_ADDR", value_name = "ADDR")]
+    #[serde(default)]
+    pub metrics_addr: Option<SocketAddr>,
+
     #[arg(
         long,
         env = "KUBEGRAPH_VM_RESTART_POLICY",
@@ -65,3 +184,43 @@ pub struct NetworkVirtualMachineArgs {
     #[serde(default)]
     pub restart_policy: NetworkVirtualMachineRestartPolicy,
 }
+
+fn parse_duration(s: &str) -> Result<Duration, ::duration_string::Error> {
+    DurationString::from_str(s).map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_default() {
+        let args = NetworkArgs::builder()
+            .build()
+            .expect("failed to build args");
+        assert_eq!(args, NetworkArgs::default());
+    }
+
+    #[test]
+    fn build_health_staleness_without_metrics_addr() {
+        let vm = NetworkVirtualMachineArgs {
+            health_staleness: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        assert!(NetworkArgs::builder().vm(vm).build().is_err());
+    }
+
+    #[test]
+    fn build_health_staleness_with_metrics_addr() {
+        let vm = NetworkVirtualMachineArgs {
+            health_staleness: Some(Duration::from_secs(60)),
+            metrics_addr: Some(([127, 0, 0, 1], 9090).into()),
+            ..Default::default()
+        };
+        let args = NetworkArgs::builder()
+            .vm(vm)
+            .build()
+            .expect("failed to build args");
+        assert_eq!(args.vm, vm);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use ark_core::signal::FunctionSignal;
 use async_trait::async_trait;
@@ -134,6 +136,20 @@ impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
         }
     }
 
+    #[instrument(level = Level::INFO, skip(self, graph))]
+    async fn insert_with_ttl(
+        &self,
+        graph: Graph<GraphData<LazyFrame>>,
+        ttl: Duration,
+    ) -> Result<()> {
+        match self {
+            #[cfg(feature = "graph-local")]
+            Self::Local(runtime) => runtime.insert_with_ttl(graph, ttl).await,
+            #[cfg(feature = "graph-memory")]
+            Self::Memory(runtime) => runtime.insert_with_ttl(graph, ttl).await,
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn list(&self, filter: &GraphFilter) -> Result<Vec<Graph<GraphData<LazyFrame>>>> {
         match self {
@@ -154,6 +170,16 @@ impl ::kubegraph_api::graph::NetworkGraphDB for NetworkGraphDB {
         }
     }
 
+    #[instrument(level = Level::INFO, skip(self))]
+    async fn evict_expired(&self) -> Result<Vec<GraphScope>> {
+        match self {
+            #[cfg(feature = "graph-local")]
+            Self::Local(runtime) => runtime.evict_expired().await,
+            #[cfg(feature = "graph-memory")]
+            Self::Memory(runtime) => runtime.evict_expired().await,
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close(&self) -> Result<()> {
         match self {
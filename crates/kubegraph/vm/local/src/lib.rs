@@ -1,6 +1,8 @@
+mod actix;
 mod args;
 mod dependency;
 mod graph;
+mod metrics;
 mod reloader;
 mod resource;
 mod runner;
@@ -8,6 +10,8 @@ mod solver;
 mod trader;
 mod visualizer;
 
+pub use self::args::{NetworkArgs, NetworkArgsBuilder};
+
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -16,16 +20,21 @@ use async_trait::async_trait;
 use clap::Parser;
 use kubegraph_api::{
     component::NetworkComponent,
-    vm::{NetworkFallbackPolicy, NetworkVirtualMachineExt, NetworkVirtualMachineRestartPolicy},
+    vm::{
+        NetworkFallbackPolicy, NetworkTerminationReason, NetworkVirtualMachineExt,
+        NetworkVirtualMachineMetrics, NetworkVirtualMachineRestartPolicy,
+    },
 };
 use tokio::{sync::Mutex, task::JoinHandle};
-use tracing::{instrument, Level};
+use tracing::{info, instrument, Level};
 
 #[derive(Clone)]
 pub struct NetworkVirtualMachine {
     dependency_graph: self::dependency::NetworkDependencyGraph,
     args: self::args::NetworkVirtualMachineArgs,
     graph_db: self::graph::NetworkGraphDB,
+    metrics: Arc<self::metrics::NetworkVirtualMachineMetrics>,
+    metrics_server: Arc<Mutex<Option<JoinHandle<()>>>>,
     resource_db: self::resource::NetworkResourceDB,
     resource_worker: Arc<Mutex<Option<self::resource::NetworkResourceWorker>>>,
     runner: self::runner::NetworkRunner,
@@ -44,6 +53,9 @@ impl NetworkComponent for NetworkVirtualMachine {
         args: <Self as NetworkComponent>::Args,
         signal: &FunctionSignal,
     ) -> Result<Self> {
+        // Step 0. Validate the options, as parsed from the command line
+        args.validate()?;
+
         // Step 1. Initialize components
         let self::args::NetworkArgs {
             dependency_graph,
@@ -63,6 +75,8 @@ impl NetworkComponent for NetworkVirtualMachine {
             )
             .await?,
             graph_db: self::graph::NetworkGraphDB::try_new(graph_db, signal).await?,
+            metrics: Arc::default(),
+            metrics_server: Arc::new(Mutex::new(None)),
             resource_db: self::resource::NetworkResourceDB::try_new(resource_db, signal).await?,
             resource_worker: Arc::new(Mutex::new(None)),
             runner: self::runner::NetworkRunner::try_new(runner, signal).await?,
@@ -81,6 +95,12 @@ impl NetworkComponent for NetworkVirtualMachine {
             .lock()
             .await
             .replace(NetworkVirtualMachineRunner::spawn(signal, vm.clone()));
+        if let Some(addr) = vm.args.metrics_addr {
+            vm.metrics_server
+                .lock()
+                .await
+                .replace(::tokio::spawn(self::actix::loop_forever(addr, vm.clone())));
+        }
         Ok(vm)
     }
 }
@@ -133,18 +153,66 @@ impl ::kubegraph_api::vm::NetworkVirtualMachine for NetworkVirtualMachine {
         self.args.restart_policy
     }
 
+    fn metrics(&self) -> Option<&dyn NetworkVirtualMachineMetrics> {
+        if self.args.metrics_addr.is_some() {
+            Some(&*self.metrics)
+        } else {
+            None
+        }
+    }
+
+    fn observe_termination(&self, reason: NetworkTerminationReason) {
+        if self.metrics.observe_termination(reason) {
+            info!("Terminating VM: {reason}");
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self))]
     async fn close_workers(&self) -> Result<()> {
+        self.observe_termination(NetworkTerminationReason::Shutdown);
+
         if let Some(worker) = self.resource_worker.lock().await.take() {
             worker.abort();
         }
         if let Some(worker) = self.vm_runner.lock().await.take() {
             worker.abort();
         }
+        if let Some(worker) = self.metrics_server.lock().await.take() {
+            worker.abort();
+        }
         Ok(())
     }
 }
 
+impl NetworkVirtualMachine {
+    /// Check whether all workers are alive and the VM is not stalled.
+    pub(crate) async fn is_healthy(&self) -> bool {
+        let is_resource_worker_alive = self
+            .resource_worker
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|worker| worker.is_alive());
+        let is_vm_runner_alive = self
+            .vm_runner
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|worker| worker.is_alive());
+        let is_fresh = match self.args.health_staleness {
+            Some(staleness) => self.metrics.since_last_heartbeat() <= staleness,
+            None => true,
+        };
+
+        is_resource_worker_alive && is_vm_runner_alive && is_fresh
+    }
+
+    /// Return why the VM has stopped, if it has.
+    pub(crate) fn termination_reason(&self) -> Option<NetworkTerminationReason> {
+        self.metrics.termination_reason()
+    }
+}
+
 struct NetworkVirtualMachineRunner {
     inner: JoinHandle<()>,
 }
@@ -162,6 +230,10 @@ impl NetworkVirtualMachineRunner {
         }
     }
 
+    pub(crate) fn is_alive(&self) -> bool {
+        !self.inner.is_finished()
+    }
+
     pub(crate) fn abort(&self) {
         self.inner.abort()
     }
@@ -177,6 +249,47 @@ mod tests {
 
     use super::*;
 
+    /// Build a VM without the visualizer.
+    pub(crate) async fn build_vm(
+        vm: crate::args::NetworkVirtualMachineArgs,
+    ) -> NetworkVirtualMachine {
+        use crate::{
+            args::NetworkArgs,
+            visualizer::{NetworkVisualizerArgs, NetworkVisualizerType},
+        };
+
+        let args = NetworkArgs {
+            visualizer: NetworkVisualizerArgs {
+                visualizer: NetworkVisualizerType::Disabled,
+                ..Default::default()
+            },
+            vm,
+            ..Default::default()
+        };
+        let signal = FunctionSignal::default();
+        NetworkVirtualMachine::try_new(args, &signal)
+            .await
+            .expect("failed to init vm")
+    }
+
+    #[::tokio::test]
+    async fn reject_invalid_args() {
+        use std::time::Duration;
+
+        use crate::args::{NetworkArgs, NetworkVirtualMachineArgs};
+
+        // NOTE: skip the builder, as the args parsed from the command line do
+        let args = NetworkArgs {
+            vm: NetworkVirtualMachineArgs {
+                health_staleness: Some(Duration::from_secs(60)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let signal = FunctionSignal::default();
+        assert!(NetworkVirtualMachine::try_new(args, &signal).await.is_err());
+    }
+
     #[::tokio::test]
     async fn simulate_simple_with_edges() {
         use kubegraph_api::{
@@ -230,6 +343,7 @@ mod tests {
             },
             spec: NetworkConnectorSpec {
                 kind: NetworkConnectorKind::Unknown {},
+                ttl: None,
             },
         };
         let scope = GraphScope::from_resource(&connector);
@@ -313,6 +427,203 @@ mod tests {
         );
     }
 
+    #[::tokio::test]
+    async fn merge_with_edges() {
+        use kubegraph_api::graph::{
+            Graph, GraphData, GraphInsertPolicy, GraphScope, NetworkGraphDB, NetworkGraphDBExt,
+        };
+
+        use crate::{
+            args::NetworkArgs,
+            visualizer::{NetworkVisualizerArgs, NetworkVisualizerType},
+        };
+
+        // Step 1. Define problems
+        let args = NetworkArgs {
+            visualizer: NetworkVisualizerArgs {
+                visualizer: NetworkVisualizerType::Disabled,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let signal = FunctionSignal::default();
+        let vm = NetworkVirtualMachine::try_new(args, &signal)
+            .await
+            .expect("failed to init vm");
+
+        // Step 2. Register the initial graph
+        let connector = NetworkConnectorCrd {
+            metadata: ObjectMeta {
+                namespace: Some("default".into()),
+                name: Some("warehouse".into()),
+                ..Default::default()
+            },
+            spec: NetworkConnectorSpec {
+                kind: NetworkConnectorKind::Unknown {},
+                ttl: None,
+            },
+        };
+        let scope = GraphScope::from_resource(&connector);
+        let graph = Graph {
+            connector: Some(connector.into()),
+            data: GraphData {
+                edges: ::polars::df!(
+                    "src"       => [    "a"],
+                    "sink"      => [    "b"],
+                    "capacity"  => [  50i64],
+                    "unit_cost" => [   1i64],
+                )
+                .expect("failed to create edges dataframe")
+                .into(),
+                nodes: ::polars::df!(
+                    "name"      => [    "a",     "b"],
+                    "capacity"  => [ 300i64,  300i64],
+                    "supply"    => [ 300i64,    0i64],
+                    "unit_cost" => [   5i64,    1i64],
+                )
+                .expect("failed to create nodes dataframe")
+                .into(),
+            },
+            metadata: GraphMetadata::default(),
+            scope: scope.clone(),
+        };
+        vm.graph_db
+            .insert_with_policy(graph, GraphInsertPolicy::Merge)
+            .await
+            .unwrap();
+
+        // Step 3. Merge a partial graph
+        let graph = Graph {
+            connector: None,
+            data: GraphData {
+                edges: ::polars::df!(
+                    "src"       => [    "b"],
+                    "sink"      => [    "c"],
+                    "capacity"  => [  30i64],
+                    "unit_cost" => [   2i64],
+                )
+                .expect("failed to create edges dataframe")
+                .into(),
+                nodes: ::polars::df!(
+                    "name"      => [    "b",     "c"],
+                    "capacity"  => [ 200i64,  100i64],
+                    "supply"    => [  10i64,    0i64],
+                    "unit_cost" => [   1i64,    3i64],
+                )
+                .expect("failed to create nodes dataframe")
+                .into(),
+            },
+            metadata: GraphMetadata::default(),
+            scope: scope.clone(),
+        };
+        vm.graph_db
+            .insert_with_policy(graph, GraphInsertPolicy::Merge)
+            .await
+            .unwrap();
+
+        // Step 4. Collect the merged graph
+        let Graph {
+            connector,
+            data:
+                GraphData {
+                    edges: output_edges,
+                    nodes: output_nodes,
+                },
+            ..
+        } = vm.graph_db.get(&scope).await.unwrap().unwrap();
+        let output_edges = output_edges
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect output edges dataframe");
+        let output_nodes = output_nodes
+            .try_into_polars()
+            .unwrap()
+            .collect()
+            .expect("failed to collect output nodes dataframe");
+
+        // Step 5. Verify the merged graph
+        assert!(connector.is_some());
+        assert_eq!(
+            output_nodes,
+            ::polars::df!(
+                "name"      => [    "a",     "b",     "c"],
+                "capacity"  => [ 300i64,  200i64,  100i64],
+                "supply"    => [ 300i64,   10i64,    0i64],
+                "unit_cost" => [   5i64,    1i64,    3i64],
+            )
+            .expect("failed to create ground-truth nodes dataframe"),
+        );
+        assert_eq!(
+            output_edges,
+            ::polars::df!(
+                "src"       => [    "a",     "b"],
+                "sink"      => [    "b",     "c"],
+                "capacity"  => [  50i64,   30i64],
+                "unit_cost" => [   1i64,    2i64],
+            )
+            .expect("failed to create ground-truth edges dataframe"),
+        );
+
+        // Step 6. Verify the summary
+        assert_eq!(vm.graph_db.list_scopes().await.unwrap(), [scope.clone()]);
+        let summary = vm.graph_db.summary(&scope).await.unwrap().unwrap();
+        assert_eq!(summary.num_edges, 2);
+        assert_eq!(summary.num_nodes, 3);
+        assert_eq!(
+            summary.node_columns,
+            ["name", "capacity", "supply", "unit_cost"]
+        );
+    }
+
+    #[::tokio::test]
+    async fn evict_expired_graphs() {
+        use std::time::Duration;
+
+        use kubegraph_api::graph::{Graph, GraphData, GraphScope, NetworkGraphDB};
+
+        use crate::{
+            args::NetworkArgs,
+            visualizer::{NetworkVisualizerArgs, NetworkVisualizerType},
+        };
+
+        // Step 1. Define problems
+        let args = NetworkArgs {
+            visualizer: NetworkVisualizerArgs {
+                visualizer: NetworkVisualizerType::Disabled,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let signal = FunctionSignal::default();
+        let vm = NetworkVirtualMachine::try_new(args, &signal)
+            .await
+            .expect("failed to init vm");
+
+        // Step 2. Register a transient graph
+        let scope = GraphScope {
+            namespace: "default".into(),
+            name: "transient".into(),
+        };
+        let graph = Graph {
+            connector: None,
+            data: GraphData::default(),
+            metadata: GraphMetadata::default(),
+            scope: scope.clone(),
+        };
+        let ttl = Duration::from_millis(10);
+        vm.graph_db.insert_with_ttl(graph, ttl).await.unwrap();
+        assert!(vm.graph_db.get(&scope).await.unwrap().is_some());
+
+        // Step 3. Wait until the graph expires
+        ::tokio::time::sleep(ttl * 2).await;
+        assert!(vm.graph_db.get(&scope).await.unwrap().is_none());
+
+        // Step 4. Evict the expired graph
+        assert_eq!(vm.graph_db.evict_expired().await.unwrap(), [scope]);
+        assert!(vm.graph_db.evict_expired().await.unwrap().is_empty());
+    }
+
     #[::tokio::test]
     async fn simulate_simple_with_function() {
         use kube::api::ObjectMeta;
@@ -364,6 +675,7 @@ mod tests {
             },
             spec: NetworkConnectorSpec {
                 kind: NetworkConnectorKind::Unknown {},
+                ttl: None,
             },
         };
         let scope = GraphScope::from_resource(&connector);
@@ -388,6 +700,7 @@ mod tests {
             spec: NetworkFunctionSpec {
                 kind: NetworkFunctionKind::Fake(NetworkFunctionFakeSpec {}),
                 template: NetworkFunctionTemplate {
+                    prelude: None,
                     filter: Some(
                         "src != sink and src.supply > 0 and src.supply > sink.supply".into(),
                     ),
@@ -418,9 +731,16 @@ mod tests {
         let n_step = 10;
         for _ in 0..n_step {
             let state = Default::default();
-            vm.step_with_custom_problem(state, problem.clone())
+            let result = vm
+                .step_with_custom_problem(state, problem.clone())
                 .await
                 .expect("failed to optimize");
+
+            // The supplies are conserved within the node capacities
+            let flow = result.flow.expect("should have a flow result");
+            assert_eq!(flow.served_supply, 300);
+            assert_eq!(flow.unserved_supply, 0);
+            assert!(flow.edges.iter().all(|edge| edge.flow > 0));
         }
 
         // Step 7. Collect the output graph
@@ -454,4 +774,122 @@ mod tests {
         );
         assert_eq!(output_edges.collect().await.unwrap(), DataFrame::Empty);
     }
+
+    #[::tokio::test]
+    async fn replay_exported_problem() {
+        use kube::api::ObjectMeta;
+        use kubegraph_api::{
+            frame::LazyFrame,
+            function::{
+                fake::NetworkFunctionFakeSpec, NetworkFunctionCrd, NetworkFunctionKind,
+                NetworkFunctionSpec, NetworkFunctionTemplate,
+            },
+            graph::{Graph, GraphData, GraphFilter, GraphScope, NetworkGraphDB},
+            problem::{ProblemSpec, VirtualProblem},
+            resource::NetworkResourceDB,
+        };
+
+        fn build_function(namespace: &str) -> NetworkFunctionCrd {
+            NetworkFunctionCrd {
+                metadata: ObjectMeta {
+                    namespace: Some(namespace.into()),
+                    name: Some("move".into()),
+                    ..Default::default()
+                },
+                spec: NetworkFunctionSpec {
+                    kind: NetworkFunctionKind::Fake(NetworkFunctionFakeSpec {}),
+                    template: NetworkFunctionTemplate {
+                        prelude: None,
+                        filter: Some(
+                            "src != sink and src.supply > 0 and src.supply > sink.supply".into(),
+                        ),
+                        script: r"
+                        capacity = 50;
+                        unit_cost = 1;
+                    "
+                        .into(),
+                    },
+                },
+            }
+        }
+
+        // Step 1. Register the initial graph and the functions
+        let vm = build_vm(Default::default()).await;
+        let nodes = ::polars::df!(
+            "name"      => [    "a",     "b"],
+            "capacity"  => [ 300i64,  300i64],
+            "supply"    => [ 300i64,    0i64],
+            "unit_cost" => [   5i64,    1i64],
+            "warehouse" => [   true,    true],
+        )
+        .expect("failed to create nodes dataframe");
+        let connector = NetworkConnectorCrd {
+            metadata: ObjectMeta {
+                namespace: Some("default".into()),
+                name: Some("warehouse".into()),
+                ..Default::default()
+            },
+            spec: NetworkConnectorSpec {
+                kind: NetworkConnectorKind::Unknown {},
+                ttl: None,
+            },
+        };
+        let graph = Graph {
+            connector: Some(connector.clone().into()),
+            data: GraphData {
+                edges: LazyFrame::default(),
+                nodes: nodes.into(),
+            },
+            metadata: GraphMetadata::default(),
+            scope: GraphScope::from_resource(&connector),
+        };
+        vm.graph_db.insert(graph).await.unwrap();
+        vm.resource_db.insert(build_function("default")).await;
+        vm.resource_db.insert(build_function("other")).await;
+
+        // Step 2. Export the problem
+        let problem = VirtualProblem {
+            filter: GraphFilter::all("default".into()),
+            scope: GraphScope {
+                namespace: "default".into(),
+                name: "optimize-warehouses".into(),
+            },
+            spec: ProblemSpec::default(),
+        };
+        let bundle = vm
+            .export_problem(problem.clone())
+            .await
+            .expect("failed to export problem");
+
+        // The functions in the other namespaces are not bundled
+        assert_eq!(
+            bundle
+                .functions
+                .iter()
+                .map(GraphScope::from_resource)
+                .collect::<Vec<_>>(),
+            [GraphScope {
+                namespace: "default".into(),
+                name: "move".into(),
+            }],
+        );
+        assert_eq!(bundle.graphs.len(), 1);
+
+        // Step 3. Replay the problem on a fresh VM
+        let replayed = build_vm(Default::default())
+            .await
+            .replay_problem(bundle)
+            .await
+            .expect("failed to replay problem");
+
+        // Step 4. Verify the replayed flow matches the original one
+        let state = Default::default();
+        let original = vm
+            .step_with_custom_problem(state, problem)
+            .await
+            .expect("failed to optimize");
+        let flow = replayed.flow.expect("should have a flow result");
+        assert_eq!(flow.served_supply, 300);
+        assert_eq!(Some(flow), original.flow);
+    }
 }
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use kubegraph_api::{graph::GraphScope, vm::NetworkTerminationReason};
+
+#[derive(Debug)]
+pub(crate) struct NetworkVirtualMachineMetrics {
+    graph_evictions: AtomicU64,
+    graphs: Mutex<BTreeMap<String, GraphMetrics>>,
+    last_heartbeat: Mutex<Instant>,
+    last_step_duration_micros: AtomicU64,
+    solver_failures: AtomicU64,
+    steps_completed: AtomicU64,
+    termination: Mutex<Option<NetworkTerminationReason>>,
+}
+
+impl Default for NetworkVirtualMachineMetrics {
+    fn default() -> Self {
+        Self {
+            graph_evictions: AtomicU64::default(),
+            graphs: Mutex::default(),
+            last_heartbeat: Mutex::new(Instant::now()),
+            last_step_duration_micros: AtomicU64::default(),
+            solver_failures: AtomicU64::default(),
+            steps_completed: AtomicU64::default(),
+            termination: Mutex::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct GraphMetrics {
+    edges: usize,
+    nodes: usize,
+}
+
+impl ::kubegraph_api::vm::NetworkVirtualMachineMetrics for NetworkVirtualMachineMetrics {
+    fn observe_graph(&self, scope: &GraphScope, nodes: usize, edges: usize) {
+        if let Ok(mut graphs) = self.graphs.lock() {
+            graphs.insert(scope.to_string(), GraphMetrics { edges, nodes });
+        }
+    }
+
+    fn observe_graph_eviction(&self, scope: &GraphScope) {
+        if let Ok(mut graphs) = self.graphs.lock() {
+            graphs.remove(&scope.to_string());
+        }
+        self.graph_evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn observe_solver_failure(&self) {
+        self.solver_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn observe_step(&self, duration: Duration) {
+        self.steps_completed.fetch_add(1, Ordering::Relaxed);
+        self.last_step_duration_micros.store(
+            duration.as_micros().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    fn observe_heartbeat(&self) {
+        if let Ok(mut last_heartbeat) = self.last_heartbeat.lock() {
+            *last_heartbeat = Instant::now();
+        }
+    }
+}
+
+impl NetworkVirtualMachineMetrics {
+    /// Record the termination reason, returning `false` if one is already recorded.
+    pub(crate) fn observe_termination(&self, reason: NetworkTerminationReason) -> bool {
+        match self.termination.lock() {
+            Ok(mut termination) if termination.is_none() => {
+                *termination = Some(reason);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn termination_reason(&self) -> Option<NetworkTerminationReason> {
+        self.termination
+            .lock()
+            .ok()
+            .and_then(|termination| *termination)
+    }
+
+    /// Return the elapsed time since the last loop iteration, or since boot.
+    pub(crate) fn since_last_heartbeat(&self) -> Duration {
+        self.last_heartbeat
+            .lock()
+            .map(|last_heartbeat| last_heartbeat.elapsed())
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub(crate) fn render(&self) -> Result<String, fmt::Error> {
+        let mut buf = String::new();
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_steps_completed_total",
+            "counter",
+            "Number of VM steps that have solved a problem.",
+        )?;
+        writeln!(
+            buf,
+            "kubegraph_vm_steps_completed_total {}",
+            self.steps_completed.load(Ordering::Relaxed),
+        )?;
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_last_step_duration_seconds",
+            "gauge",
+            "Duration of the last solved VM step.",
+        )?;
+        writeln!(
+            buf,
+            "kubegraph_vm_last_step_duration_seconds {}",
+            Duration::from_micros(self.last_step_duration_micros.load(Ordering::Relaxed))
+                .as_secs_f64(),
+        )?;
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_solver_failures_total",
+            "counter",
+            "Number of failed solver runs.",
+        )?;
+        writeln!(
+            buf,
+            "kubegraph_vm_solver_failures_total {}",
+            self.solver_failures.load(Ordering::Relaxed),
+        )?;
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_graph_evictions_total",
+            "counter",
+            "Number of graphs evicted by their TTL.",
+        )?;
+        writeln!(
+            buf,
+            "kubegraph_vm_graph_evictions_total {}",
+            self.graph_evictions.load(Ordering::Relaxed),
+        )?;
+
+        let graphs = self
+            .graphs
+            .lock()
+            .map(|graphs| graphs.clone())
+            .unwrap_or_default();
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_graph_nodes",
+            "gauge",
+            "Number of nodes in the last solved graph.",
+        )?;
+        for (problem, GraphMetrics { edges: _, nodes }) in &graphs {
+            writeln!(
+                buf,
+                "kubegraph_vm_graph_nodes{{problem={problem:?}}} {nodes}"
+            )?;
+        }
+
+        write_header(
+            &mut buf,
+            "kubegraph_vm_graph_edges",
+            "gauge",
+            "Number of edges in the last solved graph.",
+        )?;
+        for (problem, GraphMetrics { edges, nodes: _ }) in &graphs {
+            writeln!(
+                buf,
+                "kubegraph_vm_graph_edges{{problem={problem:?}}} {edges}"
+            )?;
+        }
+        Ok(buf)
+    }
+}
+
+fn write_header(buf: &mut String, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(buf, "# HELP {name} {help}")?;
+    writeln!(buf, "# TYPE {name} {kind}")
+}
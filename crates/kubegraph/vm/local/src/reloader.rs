@@ -48,6 +48,10 @@ where
         }
     }
 
+    pub(crate) fn is_alive(&self) -> bool {
+        !self.inner.is_finished()
+    }
+
     pub(crate) fn abort(&self) {
         let name = <K as CustomResourceExt>::crd_name();
         info!("Stopping {name} reloader...");
@@ -223,6 +223,14 @@ impl NetworkResourceWorker {
         })
     }
 
+    /// NOTE: the connector workers are not checked, as they finish
+    /// immediately when no connectors are enabled.
+    pub(crate) fn is_alive(&self) -> bool {
+        self.connector_reloader.is_alive()
+            && self.function_reloader.is_alive()
+            && self.problem_reloader.is_alive()
+    }
+
     pub(crate) fn abort(&self) {
         self.connector_db.abort();
         self.connector_reloader.abort();
@@ -40,6 +40,11 @@ pub struct NetworkSolverArgs {
     #[serde(default)]
     pub solver: NetworkSolverType,
 
+    /// Skip solving (and applying) the problems, keeping the graphs as they are
+    #[arg(long, env = "KUBEGRAPH_SOLVER_PAUSED")]
+    #[serde(default)]
+    pub paused: bool,
+
     #[cfg(feature = "solver-ortools")]
     #[command(flatten)]
     #[serde(default)]
@@ -72,7 +77,13 @@ pub enum NetworkSolverType {
 }
 
 #[derive(Clone)]
-pub enum NetworkSolver {
+pub struct NetworkSolver {
+    inner: NetworkSolverImpl,
+    paused: bool,
+}
+
+#[derive(Clone)]
+enum NetworkSolverImpl {
     Disabled,
     #[cfg(feature = "solver-ortools")]
     Ortools(::kubegraph_solver_ortools::NetworkSolver),
@@ -89,20 +100,22 @@ impl NetworkComponent for NetworkSolver {
     ) -> Result<Self> {
         let NetworkSolverArgs {
             solver,
+            paused,
             #[cfg(feature = "solver-ortools")]
             ortools,
         } = args;
 
-        match solver {
+        let inner = match solver {
             NetworkSolverType::Disabled => {
                 let _ = signal;
-                Ok(Self::Disabled)
+                NetworkSolverImpl::Disabled
             }
             #[cfg(feature = "solver-ortools")]
-            NetworkSolverType::Ortools => Ok(Self::Ortools(
+            NetworkSolverType::Ortools => NetworkSolverImpl::Ortools(
                 ::kubegraph_solver_ortools::NetworkSolver::try_new(ortools, signal).await?,
-            )),
-        }
+            ),
+        };
+        Ok(Self { inner, paused })
     }
 }
 
@@ -110,19 +123,23 @@ impl NetworkComponent for NetworkSolver {
 impl ::kubegraph_api::solver::NetworkSolver<GraphData<LazyFrame>> for NetworkSolver {
     type Output = GraphData<LazyFrame>;
 
+    fn is_enabled(&self) -> bool {
+        !self.paused
+    }
+
     #[instrument(level = Level::INFO, skip(self, graph, problem))]
     async fn solve(
         &self,
         graph: GraphData<LazyFrame>,
         problem: &ProblemSpec<GraphMetadataPinned>,
     ) -> Result<Self::Output> {
-        match self {
-            Self::Disabled => {
+        match &self.inner {
+            NetworkSolverImpl::Disabled => {
                 let _ = problem;
                 Ok(graph)
             }
             #[cfg(feature = "solver-ortools")]
-            Self::Ortools(runtime) => runtime.solve(graph, problem).await,
+            NetworkSolverImpl::Ortools(runtime) => runtime.solve(graph, problem).await,
         }
     }
 }
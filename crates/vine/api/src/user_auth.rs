@@ -123,6 +123,8 @@ impl From<UserAuthError> for UserSessionResponse {
 #[derive(Clone, Debug, Error, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase", tag = "status", content = "data")]
 pub enum UserSessionError {
+    #[error("The accelerators of this node are already claimed: {accelerators:?}")]
+    AcceleratorsClaimed { accelerators: String },
     #[error("This user is already logged in to {node_name:?}")]
     AlreadyLoggedInByNode { node_name: String },
     #[error("This node is already logged in by {user_name:?}")]
@@ -10,6 +10,7 @@ where
     T: ServiceFactory<ServiceRequest, Error = Error, Config = (), InitError = ()>,
 {
     app.service(crate::routes::desktop::batch::post_exec_broadcast)
+        .service(crate::routes::desktop::batch::post_exec_for)
         .service(crate::routes::desktop::single::post_exec)
         .service(crate::routes::session::list)
         .service(crate::routes::user::get)
@@ -1,12 +1,12 @@
 use actix_web::{
     post,
-    web::{Data, Json},
+    web::{Data, Json, Path},
     HttpRequest, HttpResponse, Responder,
 };
 use ark_core::result::Result;
 use kube::Client;
 use tracing::{instrument, warn, Level};
-use vine_api::user_session::{UserSession, UserSessionCommandBatch};
+use vine_api::user_session::{UserSession, UserSessionCommand, UserSessionCommandBatch};
 use vine_rbac::auth::AuthUserSession;
 use vine_session::batch::{BatchCommandArgs, BatchCommandUsers};
 
@@ -44,3 +44,36 @@ pub async fn post_exec_broadcast(
     let result = args.exec(&kube).await;
     HttpResponse::from(Result::from(result))
 }
+
+#[instrument(level = Level::INFO, skip(request, kube))]
+#[post("/batch/user/{user_name}/desktop/exec")]
+pub async fn post_exec_for(
+    request: HttpRequest,
+    kube: Data<Client>,
+    user_name: Path<String>,
+    Json(command): Json<UserSessionCommand>,
+) -> impl Responder {
+    let kube = kube.as_ref().clone();
+    if let Err(error) = UserSession::from_request(&kube, &request)
+        .await
+        .and_then(|metadata| metadata.assert_admin())
+    {
+        warn!("{error}");
+        return HttpResponse::from(Result::<()>::Err(error.to_string()));
+    };
+
+    let user_name = user_name.into_inner();
+    let args = BatchCommandArgs {
+        command,
+        terminal: false,
+        users: BatchCommandUsers::List(vec![user_name.clone()]),
+        wait: false,
+    };
+
+    let result = match args.exec(&kube).await {
+        Ok(0) => Err(format!("no such user session: {user_name:?}")),
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    };
+    HttpResponse::from(Result::from(result))
+}
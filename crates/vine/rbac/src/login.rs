@@ -17,8 +17,9 @@ pub async fn execute(
         true,
         |session_manager, spec| async move {
             session_manager
-                .try_create(&spec.as_ref(), logout_on_failed)
+                .try_create(&spec.as_ref(), logout_on_failed, false)
                 .await
+                .map(|_| ())
         },
     )
     .await
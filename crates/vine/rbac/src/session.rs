@@ -176,7 +176,9 @@ where
 
             let persistence = is_persistent(&node);
             let spec = SessionContextSpecOwned {
+                accelerators: Vec::default(),
                 box_quota: Some(box_quota.clone()),
+                extra: ::vine_session::load_extra_context()?,
                 node,
                 persistence,
                 role: Some(role),
@@ -208,8 +210,16 @@ fn assert_allocable<T>(
 where
     T: ResourceExt,
 {
-    match ::vine_session::is_allocable(object.labels(), box_name, user_name) {
+    match ::vine_session::is_allocable(object.labels(), box_name, user_name, &[]) {
         AllocationState::AllocatedByMyself | AllocationState::NotAllocated => None,
+        AllocationState::AcceleratorsClaimed { accelerators } => {
+            warn!("[{now}] the accelerators are already claimed: {accelerators:?}");
+            Some(UserSessionResponse::Error(
+                UserSessionError::AcceleratorsClaimed {
+                    accelerators: accelerators.into(),
+                },
+            ))
+        }
         AllocationState::AllocatedByOtherNode { node_name } => {
             warn!("[{now}] the user is already allocated to {node_name:?}: {user_name:?}");
             Some(UserSessionResponse::Error(
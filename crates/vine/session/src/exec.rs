@@ -2,14 +2,18 @@ use std::{convert::identity, fmt};
 
 use anyhow::{anyhow, Error, Result};
 use ark_api::{NamespaceAny, SessionRef};
+use ark_core::env;
 use async_trait::async_trait;
-use futures::{stream::FuturesUnordered, StreamExt, TryStreamExt};
+use futures::{
+    stream::{self, FuturesUnordered},
+    StreamExt, TryStreamExt,
+};
 use k8s_openapi::api::core::v1::{Pod, PodCondition};
 use kube::{
     api::{AttachParams, AttachedProcess, ListParams},
     Api, Client, ResourceExt,
 };
-use tokio::{spawn, task::yield_now};
+use tokio::{io::AsyncWrite, spawn, task::yield_now};
 use tracing::{instrument, Level};
 use vine_api::user::UserCrd;
 
@@ -43,6 +47,25 @@ where
         };
         <Self as SessionExec>::exec(self, kube, ap, command).await
     }
+
+    /// Execute the command with stdin attached.
+    ///
+    /// The input can be written with [`Process::stdin`].
+    #[instrument(level = Level::INFO, skip(kube, command), err(Display))]
+    async fn exec_with_stdin<I>(&self, kube: Client, command: I) -> Result<Vec<Process>>
+    where
+        I: 'static + Send + Sync + Clone + fmt::Debug + IntoIterator,
+        <I as IntoIterator>::Item: Sync + Into<String>,
+    {
+        let ap = AttachParams {
+            stdin: true,
+            stdout: true,
+            stderr: true,
+            tty: false,
+            ..Default::default()
+        };
+        <Self as SessionExec>::exec(self, kube, ap, command).await
+    }
 }
 
 #[async_trait]
@@ -64,6 +87,10 @@ pub trait SessionExec {
     where
         I: 'static + Send + Sync + Clone + fmt::Debug + IntoIterator,
         <I as IntoIterator>::Item: Sync + Into<String>;
+
+    /// List the `(namespace, name)` of the pods that [`SessionExec::exec`]
+    /// would attach to, without executing anything.
+    async fn exec_targets(&self, kube: Client) -> Result<Vec<(String, String)>>;
 }
 
 #[async_trait]
@@ -103,6 +130,23 @@ impl<'a> SessionExec for SessionRef<'a> {
             .map_err(Into::into)
     }
 
+    #[instrument(level = Level::INFO, skip(kube), err(Display))]
+    async fn exec_targets(&self, kube: Client) -> Result<Vec<(String, String)>> {
+        let api = Api::<Pod>::namespaced(kube, &self.namespace);
+        let pods = list_targets(&api).await?;
+
+        Ok(pods
+            .into_iter()
+            .map(|pod| {
+                let name = pod.name_any();
+                let namespace = pod
+                    .namespace()
+                    .unwrap_or_else(|| self.namespace.to_string());
+                (namespace, name)
+            })
+            .collect())
+    }
+
     #[instrument(level = Level::INFO, skip(kube, ap, command), err(Display))]
     async fn exec<I>(&self, kube: Client, ap: AttachParams, command: I) -> Result<Vec<Process>>
     where
@@ -110,19 +154,65 @@ impl<'a> SessionExec for SessionRef<'a> {
         <I as IntoIterator>::Item: Sync + Into<String>,
     {
         let api = Api::<Pod>::namespaced(kube, &self.namespace);
-        let lp = ListParams {
-            label_selector: Some("app=desktop".into()),
-            ..Default::default()
-        };
-        let pods = api.list(&lp).await?.into_iter().filter(|pod| {
-            fn check_condition(conditions: &[PodCondition], type_: &str) -> bool {
-                conditions
-                    .iter()
-                    .find(|condition| condition.type_ == type_)
-                    .map(|condition| condition.status == "True")
-                    .unwrap_or_default()
-            }
+        let pods = list_targets(&api).await?;
+
+        let concurrency = env::infer::<_, usize>("VINE_SESSION_EXEC_CONCURRENCY")
+            .ok()
+            .filter(|&concurrency| concurrency > 0)
+            .unwrap_or(DEFAULT_EXEC_CONCURRENCY);
+
+        stream::iter(pods)
+            .map(|pod| {
+                let api = api.clone();
+                let ap = AttachParams {
+                    container: Some("desktop-environment".into()),
+                    ..ap
+                };
+                let command = command.clone();
+                spawn(async move {
+                    yield_now().await;
+
+                    let name = pod.name_any();
+                    let namespace = pod.namespace();
+                    match api.exec(&name, command, &ap).await {
+                        Ok(ap) => Ok(Process {
+                            ap,
+                            name,
+                            namespace,
+                        }),
+                        Err(error) => {
+                            let namespace = namespace.unwrap_or_else(|| name.clone());
+                            Err(anyhow!("failed to execute to {namespace}: {error}"))
+                        }
+                    }
+                })
+            })
+            .buffer_unordered(concurrency)
+            .map(|handle| handle.map_err(Error::from).and_then(identity))
+            .try_collect()
+            .await
+    }
+}
+
+/// List the running (and not terminating) desktop pods.
+async fn list_targets(api: &Api<Pod>) -> Result<Vec<Pod>> {
+    fn check_condition(conditions: &[PodCondition], type_: &str) -> bool {
+        conditions
+            .iter()
+            .find(|condition| condition.type_ == type_)
+            .map(|condition| condition.status == "True")
+            .unwrap_or_default()
+    }
+
+    let lp = ListParams {
+        label_selector: Some("app=desktop".into()),
+        ..Default::default()
+    };
+    let pods = api.list(&lp).await?;
 
+    Ok(pods
+        .into_iter()
+        .filter(|pod| {
             pod.status
                 .as_ref()
                 .and_then(|status| status.conditions.as_ref())
@@ -131,40 +221,13 @@ impl<'a> SessionExec for SessionRef<'a> {
                         && !check_condition(conditions, "DisruptionTarget") // not Terminating
                 })
                 .unwrap_or_default()
-        });
-
-        pods.map(|pod| {
-            let api = api.clone();
-            let ap = AttachParams {
-                container: Some("desktop-environment".into()),
-                ..ap
-            };
-            let command = command.clone();
-            spawn(async move {
-                yield_now().await;
-
-                let name = pod.name_any();
-                let namespace = pod.namespace();
-                match api.exec(&name, command, &ap).await {
-                    Ok(ap) => Ok(Process {
-                        ap,
-                        name,
-                        namespace,
-                    }),
-                    Err(error) => {
-                        let namespace = namespace.unwrap_or_else(|| name.clone());
-                        Err(anyhow!("failed to execute to {namespace}: {error}"))
-                    }
-                }
-            })
         })
-        .collect::<FuturesUnordered<_>>()
-        .map(|handle| handle.map_err(Error::from).and_then(identity))
-        .try_collect()
-        .await
-    }
+        .collect())
 }
 
+/// The maximum number of pods attached at once by [`SessionExec::exec`].
+const DEFAULT_EXEC_CONCURRENCY: usize = 16;
+
 pub struct Process {
     pub ap: AttachedProcess,
     pub name: String,
@@ -172,6 +235,13 @@ pub struct Process {
 }
 
 impl Process {
+    /// Return the writer of the stdin, if attached.
+    ///
+    /// The writer can be taken only once.
+    pub fn stdin(&mut self) -> Option<impl AsyncWrite + Unpin> {
+        self.ap.stdin()
+    }
+
     pub async fn join(self) -> Result<()> {
         self.ap.join().await.map_err(|error| {
             let namespace = self.namespace.unwrap_or(self.name);
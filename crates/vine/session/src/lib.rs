@@ -5,77 +5,226 @@ pub mod exec;
 #[cfg(feature = "shell")]
 pub mod shell;
 
-use std::{collections::BTreeMap, fmt, fs, path::PathBuf, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs, io,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
 
-use anyhow::{bail, Error, Result};
+use anyhow::{anyhow, bail, Result};
 use ark_api::{NamespaceAny, SessionRef};
 use ark_core::env;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use dash_provider::client::job::TaskActorJobClient;
 use dash_provider_api::SessionContextMetadata;
-use futures::TryFutureExt;
+use futures::{join, TryFutureExt};
 use k8s_openapi::{
     api::core::v1::{Namespace, Node, Pod},
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
     serde_json::Value,
 };
 use kiss_api::r#box::BoxCrd;
 use kube::{
-    api::{DeleteParams, ListParams, Patch, PatchParams},
+    api::{DeleteParams, DynamicObject, ListParams, Patch, PatchParams},
     Api, Client, Resource, ResourceExt,
 };
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::json;
-use tracing::{info, instrument, Level};
+use tokio::time::sleep;
+use tracing::{info, instrument, warn, Level};
 use vine_api::{user::UserCrd, user_box_quota::UserBoxQuotaSpec, user_role::UserRoleSpec};
 
 pub(crate) mod consts {
     pub const NAME: &str = "vine-session";
 }
 
+/// The session templates already validated by this process.
+///
+/// A session manager is built on every login and reconcile, so the templates
+/// are only rendered once per templates directory and globs.
+static VALIDATED_TEMPLATES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
 pub struct SessionManager {
     client: TaskActorJobClient,
+    field_manager: String,
+    max_lifetime: Option<Duration>,
+    reap_persistent: bool,
 }
 
 impl SessionManager {
     #[instrument(level = Level::INFO, skip(kube), err(Display))]
     pub async fn try_new(namespace: String, kube: Client) -> Result<Self> {
-        let templates_home = env::infer("VINE_SESSION_TEMPLATES_HOME").or_else(|_| {
+        let mut config = env::ConfigLoader::default();
+        let templates_home: PathBuf = config.infer_or_else("VINE_SESSION_TEMPLATES_HOME", || {
             // local directory
-            "../../../templates/vine/templates/session"
-                .parse::<PathBuf>()
-                .map_err(Error::from)
-        })?;
-        let templates_home = fs::canonicalize(templates_home)?;
+            "../../../templates/vine/templates/session".into()
+        });
+        let field_manager: String =
+            config.infer_or_else("VINE_SESSION_FIELD_MANAGER", || self::consts::NAME.into());
+        let max_lifetime = config
+            .infer_opt::<u64>("VINE_SESSION_MAX_LIFETIME_SECS")
+            .map(Duration::from_secs);
+        config.validate(
+            "VINE_SESSION_MAX_LIFETIME_SECS",
+            max_lifetime != Some(Duration::ZERO),
+            "should be positive",
+        );
+        let reap_persistent = config.infer_or_else("VINE_SESSION_REAP_PERSISTENT", || false);
+        let templates_globs: String =
+            config.infer_or_else("VINE_SESSION_TEMPLATES_GLOBS", || "*.yaml.j2".into());
+        let templates_wait =
+            Duration::from_secs(config.infer_or_else("VINE_SESSION_TEMPLATES_WAIT_SECS", || 0));
+        config.finish()?;
+
+        let templates_home = resolve_templates_home(templates_home, templates_wait).await?;
 
         match templates_home.to_str() {
             Some(templates_home) => {
                 let metadata = Default::default();
-                let templates_home = format!("{templates_home}/*.yaml.j2");
+                // NOTE: the earlier globs take precedence on the same file names
+                let globs = templates_globs
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|glob| !glob.is_empty())
+                    .map(|glob| format!("{templates_home}/{glob}"));
                 let use_prefix = false;
-                let client = TaskActorJobClient::from_dir(metadata, namespace, kube, &templates_home, use_prefix)?;
-                Ok(Self { client })
+                let client = TaskActorJobClient::from_globs(metadata, namespace, kube, globs, use_prefix)?;
+                let manager = Self {
+                    client,
+                    field_manager,
+                    max_lifetime,
+                    reap_persistent,
+                };
+
+                let key = format!("{templates_home}:{templates_globs}");
+                let mut validated = VALIDATED_TEMPLATES
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if !validated.contains(&key) {
+                    manager.validate_templates()?;
+                    validated.insert(key);
+                }
+                Ok(manager)
             },
             None => bail!("failed to parse the environment variable: VINE_SESSION_TEMPLATES_HOME = {templates_home:?}"),
         }
     }
+
+    /// Render the session templates against synthetic contexts.
+    ///
+    /// A broken template should fail on boot, not on a user's login.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub fn validate_templates(&self) -> Result<()> {
+        let accelerators = vec!["validate".to_string()];
+        let box_quota = UserBoxQuotaSpec::default();
+        let extra = BTreeMap::default();
+        let node = Node {
+            metadata: ObjectMeta {
+                name: Some("validate".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let role = UserRoleSpec::default();
+
+        let specs = [
+            // A session being deleted
+            SessionContextSpec {
+                accelerators: &[],
+                box_quota: None,
+                extra: &extra,
+                node: &node,
+                persistence: false,
+                role: None,
+                user_name: "validate",
+            },
+            // A session being created
+            SessionContextSpec {
+                accelerators: &accelerators,
+                box_quota: Some(&box_quota),
+                extra: &extra,
+                node: &node,
+                persistence: true,
+                role: Some(&role),
+                user_name: "validate",
+            },
+        ];
+
+        let overrides: Vec<_> = self
+            .client
+            .template_names()
+            .filter(|name| {
+                name.starts_with(Self::TEMPLATE_SESSION_ROLE_PREFIX)
+                    || name.starts_with(Self::TEMPLATE_SESSION_USER_PREFIX)
+            })
+            .collect();
+
+        for spec in &specs {
+            let ctx = self.get_context(spec);
+            for name in [
+                Self::TEMPLATE_NAMESPACE_FILENAME,
+                Self::TEMPLATE_SESSION_FILENAME,
+            ]
+            .into_iter()
+            .chain(overrides.iter().copied())
+            {
+                self.client
+                    .render_named(name, &ctx)
+                    .map_err(|error| anyhow!("invalid session template {name:?}: {error}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the field manager of the label patches (default: `vine-session`).
+    pub fn with_field_manager(mut self, field_manager: impl Into<String>) -> Self {
+        self.field_manager = field_manager.into();
+        self
+    }
+
+    /// Set the absolute lifetime of the sessions, regardless of their activity (default: unlimited).
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Reap the expired persistent sessions as well (default: `false`).
+    pub fn with_reap_persistent(mut self, reap_persistent: bool) -> Self {
+        self.reap_persistent = reap_persistent;
+        self
+    }
 }
 
 impl SessionManager {
     const TEMPLATE_NAMESPACE_FILENAME: &'static str = "user-session-namespace.yaml.j2";
     const TEMPLATE_SESSION_FILENAME: &'static str = "user-session.yaml.j2";
+    const TEMPLATE_SESSION_ROLE_PREFIX: &'static str = "user-session-role-";
+    const TEMPLATE_SESSION_USER_PREFIX: &'static str = "user-session-user-";
+    const TEMPLATE_SUFFIX: &'static str = ".yaml.j2";
 
     const THRESHOLD_SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60); // 30 minutes
 
+    /// Create a session.
+    ///
+    /// If `dry_run` is set, the apiserver only validates the requests
+    /// without persisting them, and the would-be-applied objects are returned.
+    /// The namespaced objects of a first-time user are skipped then, as
+    /// their namespace is still pending.
+    /// Otherwise, the returned objects are empty.
     #[instrument(level = Level::INFO, skip(self, spec), fields(node_name = %spec.node.name_any(), user_name = %spec.user_name), err(Display))]
     pub async fn try_create(
         &self,
         spec: &SessionContextSpec<'_>,
         delete_on_fail: bool,
-    ) -> Result<()> {
-        match self.create(spec).await {
-            Ok(()) => Ok(()),
+        dry_run: bool,
+    ) -> Result<Vec<DynamicObject>> {
+        match self.create(spec, dry_run).await {
+            Ok(objects) => Ok(objects),
             Err(error_create) => {
-                if delete_on_fail {
+                // NOTE: nothing has been persisted on dry-run
+                if delete_on_fail && !dry_run {
                     match self.delete(spec).await {
                         Ok(()) => Err(error_create),
                         Err(error_revert) => bail!("{error_create}\n{error_revert}"),
@@ -87,6 +236,11 @@ impl SessionManager {
         }
     }
 
+    /// Return the user currently bound to the node, if any.
+    pub fn current_binding(&self, node: &Node) -> Option<BindingInfo> {
+        BindingInfo::from_labels(node.labels())
+    }
+
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
     pub async fn try_delete(&self, node: &Node) -> Result<Option<String>> {
         match node
@@ -95,7 +249,9 @@ impl SessionManager {
         {
             Ok(SessionRef { user_name, .. }) => {
                 let spec = SessionContextSpec {
+                    accelerators: &[],
                     box_quota: None,
+                    extra: &BTreeMap::new(),
                     node,
                     persistence: is_persistent(node),
                     role: None,
@@ -140,17 +296,141 @@ impl SessionManager {
         }
     }
 
+    /// Migrate a persistent session from a (e.g. drained) node to another one.
+    ///
+    /// The session resources are deleted on `from` and recreated on `to.node`,
+    /// reusing the shared PVCs so that the user data persists.
+    /// The persistent binding moves along with the session.
+    #[instrument(level = Level::INFO, skip(self, from, to), fields(from = %from.name_any(), to = %to.node.name_any(), user_name = %to.user_name), err(Display))]
+    pub async fn migrate(&self, from: &Node, to: &SessionContextSpec<'_>) -> Result<()> {
+        ensure_migratable(from, to.node, to.user_name, to.accelerators)?;
+
+        // NOTE: the role is left unknown, so that the original session is
+        // resolved by the template recorded on binding the original node
+        let from_spec = SessionContextSpec {
+            node: from,
+            persistence: true,
+            role: None,
+            ..to.clone()
+        };
+
+        // Step 1. Unbind the original node, releasing its persistent binding
+        let unbound = with_persistence(from, false);
+        let unbind = SessionContextSpec {
+            node: &unbound,
+            persistence: false,
+            ..from_spec.clone()
+        };
+        self.delete(&unbind).await?;
+
+        // Step 2. Bind the new node persistently
+        let node = with_persistence(to.node, true);
+        let spec = SessionContextSpec {
+            node: &node,
+            persistence: true,
+            ..to.clone()
+        };
+        match self.create(&spec, false).await {
+            Ok(_) => Ok(()),
+            Err(error_migrate) => {
+                // Revert the new node, and restore the original session
+                let revert = SessionContextSpec {
+                    node: to.node,
+                    ..spec
+                };
+                match self
+                    .delete(&revert)
+                    .and_then(|()| self.create(&from_spec, false))
+                    .await
+                {
+                    Ok(_) => Err(error_migrate),
+                    Err(error_revert) => bail!("{error_migrate}\n{error_revert}"),
+                }
+            }
+        }
+    }
+
+    /// Delete the sessions of this namespace which outlived the max lifetime,
+    /// returning the reaped user names.
+    ///
+    /// Unlike [`try_delete`](Self::try_delete), the sessions are deleted regardless of
+    /// their activity. The persistent sessions are kept unless `reap_persistent` is set.
+    #[instrument(level = Level::INFO, skip(self), err(Display))]
+    pub async fn reap_expired(&self) -> Result<Vec<String>> {
+        let max_lifetime = match self.max_lifetime {
+            Some(max_lifetime) => max_lifetime,
+            None => return Ok(Vec::default()),
+        };
+
+        let api = Api::<Node>::all(self.client.kube.clone());
+        let lp = ListParams {
+            label_selector: Some(format!("{}=true", ::ark_api::consts::LABEL_BIND_STATUS)),
+            ..Default::default()
+        };
+
+        let mut reaped = Vec::default();
+        for node in api.list(&lp).await?.items {
+            let BindingInfo {
+                namespace,
+                persistence,
+                user_name,
+                ..
+            } = match BindingInfo::from_labels(node.labels()) {
+                Some(info) => info,
+                None => continue,
+            };
+            if namespace != self.client.namespace()
+                || (persistence && !self.reap_persistent)
+                || !session_age(&node).is_some_and(|age| age > max_lifetime)
+            {
+                continue;
+            }
+
+            let spec = SessionContextSpec {
+                accelerators: &[],
+                box_quota: None,
+                extra: &BTreeMap::new(),
+                node: &node,
+                persistence,
+                role: None,
+                user_name: &user_name,
+            };
+            match self.delete(&spec).await {
+                Ok(()) => reaped.push(user_name),
+                Err(error) => warn!(
+                    "failed to reap the expired session ({node_name}): {error}",
+                    node_name = node.name_any(),
+                ),
+            }
+        }
+        Ok(reaped)
+    }
+
     #[instrument(level = Level::INFO, skip(self, spec), fields(node_name = %spec.node.name_any(), user_name = %spec.user_name), err(Display))]
-    async fn create(&self, spec: &SessionContextSpec<'_>) -> Result<()> {
+    async fn create(
+        &self,
+        spec: &SessionContextSpec<'_>,
+        dry_run: bool,
+    ) -> Result<Vec<DynamicObject>> {
         let ctx = self.get_context(spec);
 
-        self.label_node(ctx.spec.node, Some(ctx.spec.user_name))
-            .and_then(|()| self.label_namespace(&ctx, Some(ctx.spec.user_name)))
-            .and_then(|()| self.label_user(ctx.spec.node, ctx.spec.user_name, true))
-            .and_then(|()| self.try_label_box(ctx.spec.node, Some(ctx.spec.user_name)))
-            .and_then(|()| self.create_shared_pvc(&ctx))
-            .and_then(|()| self.create_template(&ctx))
-            .await
+        let mut objects = self
+            .label_all(&ctx, Some(ctx.spec.user_name), dry_run)
+            .await?;
+        if dry_run {
+            // NOTE: the shared PVCs are not validated on dry-run
+            let mut templates = self
+                .client
+                .dry_run_named(&self.session_template_name(&ctx), &ctx)
+                .await?;
+            objects.append(&mut templates);
+            Ok(objects)
+        } else {
+            self.create_shared_pvc(&ctx)
+                .and_then(|()| self.create_template(&ctx))
+                .await
+                .map(|()| Vec::default())
+        }
     }
 
     #[instrument(level = Level::INFO, skip(self, spec), fields(node_name = %spec.node.name_any(), user_name = %spec.user_name), err(Display))]
@@ -159,10 +439,7 @@ impl SessionManager {
 
         self.delete_template(&ctx)
             .and_then(|()| self.delete_pods(&ctx))
-            .and_then(|()| self.try_label_box(ctx.spec.node, None))
-            .and_then(|()| self.label_user(ctx.spec.node, ctx.spec.user_name, false))
-            .and_then(|()| self.label_namespace(&ctx, None))
-            .and_then(|()| self.label_node(ctx.spec.node, None))
+            .and_then(|()| self.label_all(&ctx, None, false).map_ok(|_| ()))
             .await
     }
 
@@ -179,7 +456,7 @@ impl SessionManager {
     )]
     async fn exists_template(&self, ctx: &SessionContext<'_>) -> Result<bool> {
         self.client
-            .exists_named(Self::TEMPLATE_SESSION_FILENAME, ctx)
+            .exists_named(&self.session_template_name(ctx), ctx)
             .await
     }
 
@@ -194,11 +471,21 @@ impl SessionManager {
         ),
         err(Display),
     )]
-    async fn create_namespace(&self, ctx: &SessionContext<'_>) -> Result<()> {
-        self.client
-            .create_named(Self::TEMPLATE_NAMESPACE_FILENAME, ctx)
-            .await
-            .map(|_| ())
+    async fn create_namespace(
+        &self,
+        ctx: &SessionContext<'_>,
+        dry_run: bool,
+    ) -> Result<Vec<DynamicObject>> {
+        if dry_run {
+            self.client
+                .dry_run_named(Self::TEMPLATE_NAMESPACE_FILENAME, ctx)
+                .await
+        } else {
+            self.client
+                .create_named(Self::TEMPLATE_NAMESPACE_FILENAME, ctx)
+                .await
+                .map(|_| Vec::default())
+        }
     }
 
     #[instrument(
@@ -213,9 +500,15 @@ impl SessionManager {
         err(Display),
     )]
     async fn create_shared_pvc(&self, ctx: &SessionContext<'_>) -> Result<()> {
-        ::vine_storage::get_or_create_shared_pvcs(&self.client.kube, &ctx.metadata.namespace)
-            .await
-            .map(|_| ())
+        let ::vine_storage::SharedPvcs { pvcs: _, failures } =
+            ::vine_storage::get_or_create_shared_pvcs(&self.client.kube, &ctx.metadata.namespace)
+                .await?;
+
+        // NOTE: proceed with the available volumes
+        for ::vine_storage::SharedPvcFailure { name, error } in failures {
+            warn!("shared PVC is temporarily unavailable ({name}): {error}");
+        }
+        Ok(())
     }
 
     #[instrument(
@@ -230,10 +523,13 @@ impl SessionManager {
         err(Display),
     )]
     async fn create_template(&self, ctx: &SessionContext<'_>) -> Result<()> {
-        self.client
-            .create_named(Self::TEMPLATE_SESSION_FILENAME, ctx)
-            .await
-            .map(|_| ())
+        let name = self.session_template_name(ctx);
+
+        // NOTE: skip re-applying the unchanged templates on every reconcile
+        if self.client.is_applied_named(&name, ctx).await? {
+            return Ok(());
+        }
+        self.client.create_named(&name, ctx).await.map(|_| ())
     }
 
     #[instrument(
@@ -249,7 +545,7 @@ impl SessionManager {
     )]
     async fn delete_template(&self, ctx: &SessionContext<'_>) -> Result<()> {
         self.client
-            .delete_named(Self::TEMPLATE_SESSION_FILENAME, ctx)
+            .delete_named(&self.session_template_name(ctx), ctx)
             .await
             .map(|_| ())
     }
@@ -278,22 +574,146 @@ impl SessionManager {
             .map_err(Into::into)
     }
 
+    /// Label (or unlabel) the node, namespace, user and box of the session.
+    ///
+    /// The node label is the lock of the session, so it is taken first when
+    /// binding and released last when unbinding. The remaining patches do
+    /// not depend on each other, so they are applied concurrently: a session
+    /// waits for the node patch plus the slowest of the other three, instead
+    /// of four sequential round-trips.
+    /// Note that the namespace is still created before it is labeled.
+    ///
+    /// A partially applied binding is rolled back, and the failed objects
+    /// are reported in the error.
+    #[instrument(
+        level = Level::INFO,
+        skip(self, ctx),
+        fields(
+            name = %ctx.metadata.name,
+            namespace = %ctx.metadata.namespace,
+            node_name = %ctx.spec.node.name_any(),
+            user_name = %ctx.spec.user_name,
+        ),
+        err(Display),
+    )]
+    async fn label_all(
+        &self,
+        ctx: &SessionContext<'_>,
+        user_name: Option<&str>,
+        dry_run: bool,
+    ) -> Result<Vec<DynamicObject>> {
+        let node = ctx.spec.node;
+        let timestamp = Instant::now();
+        let template = user_name.map(|_| self.session_template_name(ctx));
+        let template = template.as_deref();
+        let user_name = user_name.map(|user_name| BindingUser {
+            accelerators: ctx.spec.accelerators,
+            user_name,
+        });
+
+        let node_object = match user_name {
+            Some(_) => Some(self.label_node(node, user_name, template, dry_run).await?),
+            None => None,
+        };
+        let (namespace_objects, user_object, box_object) = join!(
+            self.label_namespace(ctx, user_name, dry_run),
+            self.label_user(node, ctx.spec.user_name, user_name.is_some(), dry_run),
+            self.try_label_box(node, user_name, dry_run),
+        );
+
+        let failures: Vec<_> = [
+            ("namespace", namespace_objects.as_ref().err()),
+            ("user", user_object.as_ref().err()),
+            ("box", box_object.as_ref().err()),
+        ]
+        .into_iter()
+        .filter_map(|(kind, error)| error.map(|error| format!("{kind}: {error}")))
+        .collect();
+        if !failures.is_empty() {
+            if user_name.is_some() && !dry_run {
+                self.rollback_label_all(ctx).await;
+            }
+            bail!(
+                "failed to label the session objects: {failures}",
+                failures = failures.join(", "),
+            )
+        }
+        let node_object = match node_object {
+            Some(object) => object,
+            None => self.label_node(node, user_name, template, dry_run).await?,
+        };
+
+        info!(
+            elapsed_ms = timestamp.elapsed().as_millis() as u64,
+            "labeled the session objects",
+        );
+
+        let mut objects = vec![node_object];
+        objects.append(&mut namespace_objects?);
+        objects.push(user_object?);
+        objects.extend(box_object?);
+        Ok(objects)
+    }
+
+    /// Release a partially applied binding, unlocking the node at last.
+    ///
+    /// The failures are only logged, as the original error is reported instead.
+    async fn rollback_label_all(&self, ctx: &SessionContext<'_>) {
+        let node = ctx.spec.node;
+        warn!(
+            "rolling back the partial session binding: {node_name:?}",
+            node_name = node.name_any(),
+        );
+
+        let (namespace, user, r#box) = join!(
+            self.label::<Namespace>(self.client.namespace(), node, None, false),
+            self.label_user(node, ctx.spec.user_name, false, false),
+            self.try_label_box(node, None, false),
+        );
+        let node = self.label_node(node, None, None, false).await;
+
+        for (kind, error) in [
+            ("namespace", namespace.err()),
+            ("user", user.err()),
+            ("box", r#box.err()),
+            ("node", node.err()),
+        ] {
+            if let Some(error) = error {
+                warn!("failed to roll back the {kind} binding: {error}");
+            }
+        }
+    }
+
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
-    async fn try_label_box(&self, node: &Node, user_name: Option<&str>) -> Result<()> {
+    async fn try_label_box(
+        &self,
+        node: &Node,
+        user_name: Option<BindingUser<'_>>,
+        dry_run: bool,
+    ) -> Result<Option<DynamicObject>> {
         let name = node.name_any();
-        self.try_label::<BoxCrd>(&name, node, user_name).await
+        self.try_label::<BoxCrd>(&name, node, user_name, dry_run)
+            .await
     }
 
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
-    async fn try_label<K>(&self, name: &str, node: &Node, user_name: Option<&str>) -> Result<()>
+    async fn try_label<K>(
+        &self,
+        name: &str,
+        node: &Node,
+        user_name: Option<BindingUser<'_>>,
+        dry_run: bool,
+    ) -> Result<Option<DynamicObject>>
     where
-        K: Clone + fmt::Debug + DeserializeOwned + Resource<DynamicType = ()>,
+        K: Clone + fmt::Debug + DeserializeOwned + Serialize + Resource<DynamicType = ()>,
     {
         let api = Api::<K>::all(self.client.kube.clone());
         if api.get_opt(name).await?.is_some() {
-            self.label_with_api(api, name, node, user_name).await
+            self.label_with_api(api, name, node, user_name, None, dry_run)
+                .await
+                .map(Some)
         } else {
-            Ok(())
+            Ok(None)
         }
     }
 
@@ -310,34 +730,70 @@ impl SessionManager {
     async fn label_namespace(
         &self,
         ctx: &SessionContext<'_>,
-        user_name: Option<&str>,
-    ) -> Result<()> {
-        self.create_namespace(ctx).await?;
+        user_name: Option<BindingUser<'_>>,
+        dry_run: bool,
+    ) -> Result<Vec<DynamicObject>> {
+        let mut objects = self.create_namespace(ctx, dry_run).await?;
 
         let name = self.client.namespace();
-        self.label::<Namespace>(name, ctx.spec.node, user_name)
-            .await
+        let object = self
+            .label::<Namespace>(name, ctx.spec.node, user_name, dry_run)
+            .await?;
+        objects.push(object);
+        Ok(objects)
     }
 
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
-    async fn label_node(&self, node: &Node, user_name: Option<&str>) -> Result<()> {
+    async fn label_node(
+        &self,
+        node: &Node,
+        user_name: Option<BindingUser<'_>>,
+        template: Option<&str>,
+        dry_run: bool,
+    ) -> Result<DynamicObject> {
         let name = node.name_any();
-        self.label::<Node>(&name, node, user_name).await
+        let api = Api::<Node>::all(self.client.kube.clone());
+        let annotations = json!({
+            ::ark_api::consts::ANNOTATION_BIND_TEMPLATE: template,
+        });
+        self.label_with_api(api, &name, node, user_name, Some(annotations), dry_run)
+            .await
     }
 
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
-    async fn label_user(&self, node: &Node, user_name: &str, create: bool) -> Result<()> {
-        self.label::<UserCrd>(user_name, node, if create { Some(user_name) } else { None })
+    async fn label_user(
+        &self,
+        node: &Node,
+        user_name: &str,
+        create: bool,
+        dry_run: bool,
+    ) -> Result<DynamicObject> {
+        let binding = if create {
+            Some(BindingUser {
+                accelerators: &[],
+                user_name,
+            })
+        } else {
+            None
+        };
+        self.label::<UserCrd>(user_name, node, binding, dry_run)
             .await
     }
 
     #[instrument(level = Level::INFO, skip(self, node), fields(node_name = %node.name_any()), err(Display))]
-    async fn label<K>(&self, name: &str, node: &Node, user_name: Option<&str>) -> Result<()>
+    async fn label<K>(
+        &self,
+        name: &str,
+        node: &Node,
+        user_name: Option<BindingUser<'_>>,
+        dry_run: bool,
+    ) -> Result<DynamicObject>
     where
-        K: Clone + fmt::Debug + DeserializeOwned + Resource<DynamicType = ()>,
+        K: Clone + fmt::Debug + DeserializeOwned + Serialize + Resource<DynamicType = ()>,
     {
         let api = Api::<K>::all(self.client.kube.clone());
-        self.label_with_api(api, name, node, user_name).await
+        self.label_with_api(api, name, node, user_name, None, dry_run)
+            .await
     }
 
     #[instrument(level = Level::INFO, skip(self, api, node), fields(node_name = %node.name_any()), err(Display))]
@@ -346,37 +802,92 @@ impl SessionManager {
         api: Api<K>,
         name: &str,
         node: &Node,
-        user_name: Option<&str>,
-    ) -> Result<()>
+        user_name: Option<BindingUser<'_>>,
+        annotations: Option<Value>,
+        dry_run: bool,
+    ) -> Result<DynamicObject>
     where
-        K: Clone + fmt::Debug + DeserializeOwned + Resource<DynamicType = ()>,
+        K: Clone + fmt::Debug + DeserializeOwned + Serialize + Resource<DynamicType = ()>,
     {
         let pp = PatchParams {
-            field_manager: Some(self::consts::NAME.into()),
+            dry_run,
+            field_manager: Some(self.field_manager.clone()),
             force: true,
             ..Default::default()
         };
 
         let node_name = node.name_any();
-        let persistence = node
-            .labels()
-            .get(::ark_api::consts::LABEL_BIND_PERSISTENT)
-            .and_then(|value| value.parse().ok())
-            .unwrap_or_default();
+        let persistence = is_persistent(node);
+        let mut metadata = json!({
+            "name": name,
+            "labels": get_label(&node_name, user_name, persistence),
+        });
+        if let Some(annotations) = annotations {
+            metadata["annotations"] = annotations;
+        }
         let patch = Patch::Apply(json!({
             "apiVersion": K::api_version(&()),
             "kind": K::kind(&()),
-            "metadata": {
-                "name": name,
-                "labels": get_label(&node_name, user_name, persistence),
-            },
+            "metadata": metadata,
         }));
-        api.patch(name, &pp, &patch)
-            .await
-            .map(|_| ())
+        let object = api.patch(name, &pp, &patch).await?;
+        ::serde_json::to_value(object)
+            .and_then(::serde_json::from_value)
             .map_err(Into::into)
     }
 
+    /// Resolve the most specific session template, in order of:
+    ///
+    /// 1. `user-session-user-{user_name}.yaml.j2`
+    /// 2. `user-session-role-{role}.yaml.j2`, trying `admin`, `ops` and `dev` in order
+    /// 3. `user-session.yaml.j2`
+    ///
+    /// The role is unknown when unbinding a node (e.g. on logout), so the
+    /// template recorded on the node at binding is used instead, if still loaded.
+    fn session_template_name(&self, ctx: &SessionContext<'_>) -> String {
+        let SessionContextSpec {
+            node,
+            role,
+            user_name,
+            ..
+        } = *ctx.spec;
+
+        if role.is_none() {
+            if let Some(name) = bound_template(node, user_name)
+                .filter(|&name| self.client.template_names().any(|loaded| loaded == name))
+            {
+                return name.into();
+            }
+        }
+
+        let user = Some(format!(
+            "{prefix}{user_name}{suffix}",
+            prefix = Self::TEMPLATE_SESSION_USER_PREFIX,
+            suffix = Self::TEMPLATE_SUFFIX,
+        ));
+        let roles = role.into_iter().flat_map(|role| {
+            [
+                (role.is_admin, "admin"),
+                (role.is_ops, "ops"),
+                (role.is_dev, "dev"),
+            ]
+            .into_iter()
+            .filter(|&(enabled, _)| enabled)
+            .map(|(_, role)| {
+                format!(
+                    "{prefix}{role}{suffix}",
+                    prefix = Self::TEMPLATE_SESSION_ROLE_PREFIX,
+                    suffix = Self::TEMPLATE_SUFFIX,
+                )
+            })
+        });
+
+        user.into_iter()
+            .chain(roles)
+            .find(|name| self.client.template_names().any(|loaded| loaded == name))
+            .unwrap_or_else(|| Self::TEMPLATE_SESSION_FILENAME.into())
+    }
+
     fn get_context<'a>(&self, spec: &'a SessionContextSpec<'a>) -> SessionContext<'a> {
         SessionContext {
             metadata: SessionContextMetadata {
@@ -393,7 +904,9 @@ pub type SessionContext<'a> = ::dash_provider_api::SessionContext<&'a SessionCon
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionContextSpecOwned {
+    pub accelerators: Vec<String>,
     pub box_quota: Option<UserBoxQuotaSpec>,
+    pub extra: BTreeMap<String, Value>,
     pub node: Node,
     pub persistence: bool,
     pub role: Option<UserRoleSpec>,
@@ -403,7 +916,9 @@ pub struct SessionContextSpecOwned {
 impl SessionContextSpecOwned {
     pub fn as_ref(&self) -> SessionContextSpec {
         SessionContextSpec {
+            accelerators: &self.accelerators,
             box_quota: self.box_quota.as_ref(),
+            extra: &self.extra,
             node: &self.node,
             persistence: self.persistence,
             role: self.role.as_ref(),
@@ -415,15 +930,128 @@ impl SessionContextSpecOwned {
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionContextSpec<'a> {
+    /// Identifiers of the accelerators (e.g. GPUs) reserved by the session.
+    pub accelerators: &'a [String],
     pub box_quota: Option<&'a UserBoxQuotaSpec>,
+    /// Site-specific values (e.g. timezone, proxy URL) exposed to the templates as `spec.extra`.
+    pub extra: &'a BTreeMap<String, Value>,
     pub node: &'a Node,
     pub persistence: bool,
     pub role: Option<&'a UserRoleSpec>,
     pub user_name: &'a str,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingInfo {
+    pub namespace: String,
+    pub persistence: bool,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub user_name: String,
+}
+
+impl BindingInfo {
+    pub fn from_labels(labels: &BTreeMap<String, String>) -> Option<Self> {
+        if labels.get(::ark_api::consts::LABEL_BIND_STATUS)? != "true" {
+            return None;
+        }
+
+        let user_name = labels
+            .get(::ark_api::consts::LABEL_BIND_BY_USER)
+            .filter(|user_name| !user_name.is_empty())?
+            .clone();
+        let namespace = labels
+            .get(::ark_api::consts::LABEL_BIND_NAMESPACE)
+            .cloned()
+            .unwrap_or_else(|| UserCrd::user_namespace_with(&user_name));
+        let timestamp = labels
+            .get(::ark_api::consts::LABEL_BIND_TIMESTAMP)
+            .and_then(|value| value.parse().ok())
+            .and_then(DateTime::from_timestamp_millis);
+
+        Some(Self {
+            namespace,
+            persistence: get_persistence(labels),
+            timestamp,
+            user_name,
+        })
+    }
+}
+
+/// Load the site-specific template values from `VINE_SESSION_EXTRA_CONTEXT`,
+/// a JSON object (default: empty).
+pub fn load_extra_context() -> Result<BTreeMap<String, Value>> {
+    match env::infer::<_, String>("VINE_SESSION_EXTRA_CONTEXT") {
+        Ok(extra) => ::serde_json::from_str(&extra).map_err(|error| {
+            anyhow!("failed to parse the environment variable: VINE_SESSION_EXTRA_CONTEXT: {error}")
+        }),
+        Err(_) => Ok(BTreeMap::default()),
+    }
+}
+
+/// Return the elapsed time since the node was bound to its session.
+///
+/// Returns `None` if the node is unbound or its bind timestamp is missing or malformed.
+pub fn session_age(node: &Node) -> Option<Duration> {
+    let timestamp = BindingInfo::from_labels(node.labels())?.timestamp?;
+    (Utc::now() - timestamp).to_std().ok()
+}
+
+/// Return the session template recorded on binding the node to the user, if any.
+fn bound_template<'a>(node: &'a Node, user_name: &str) -> Option<&'a str> {
+    let labels = node.labels();
+    if labels.get(::ark_api::consts::LABEL_BIND_BY_USER)? != user_name {
+        return None;
+    }
+    node.annotations()
+        .get(::ark_api::consts::ANNOTATION_BIND_TEMPLATE)
+        .map(String::as_str)
+}
+
+/// Ensure the persistent session of the user can be moved from `from` to `to`.
+fn ensure_migratable(
+    from: &Node,
+    to: &Node,
+    user_name: &str,
+    accelerators: &[String],
+) -> Result<()> {
+    if !is_persistent_by(from, user_name) {
+        bail!(
+            "node {from:?} is not persistently allocated to {user_name:?}",
+            from = from.name_any(),
+        );
+    }
+
+    let to_name = to.name_any();
+    match is_allocable(to.labels(), Some(&to_name), user_name, accelerators) {
+        AllocationState::AllocatedByMyself | AllocationState::NotAllocated => Ok(()),
+        AllocationState::AcceleratorsClaimed { accelerators } => {
+            bail!("the accelerators are already claimed on {to_name:?}: {accelerators:?}")
+        }
+        AllocationState::AllocatedByOtherNode { node_name } => {
+            bail!("node {to_name:?} is already allocated to {node_name:?}")
+        }
+        AllocationState::AllocatedByOtherUser { user_name } => {
+            bail!("node {to_name:?} is already allocated to {user_name:?}")
+        }
+    }
+}
+
+fn with_persistence(node: &Node, persistence: bool) -> Node {
+    let mut node = node.clone();
+    node.labels_mut().insert(
+        ::ark_api::consts::LABEL_BIND_PERSISTENT.into(),
+        persistence.to_string(),
+    );
+    node
+}
+
 pub fn is_persistent(node: &Node) -> bool {
-    node.labels()
+    get_persistence(node.labels())
+}
+
+fn get_persistence(labels: &BTreeMap<String, String>) -> bool {
+    labels
         .get(::ark_api::consts::LABEL_BIND_PERSISTENT)
         .and_then(|value| value.parse().ok())
         .unwrap_or_default()
@@ -438,9 +1066,22 @@ pub fn is_persistent_by(node: &Node, user_name: &str) -> bool {
             .unwrap_or_default()
 }
 
-fn get_label(node_name: &str, user_name: Option<&str>, persistent: bool) -> Value {
+#[derive(Copy, Clone, Debug)]
+struct BindingUser<'a> {
+    accelerators: &'a [String],
+    user_name: &'a str,
+}
+
+fn get_label(node_name: &str, user: Option<BindingUser>, persistent: bool) -> Value {
+    let user_name = user.map(|user| user.user_name);
+    let accelerators = user
+        .map(|user| user.accelerators)
+        .filter(|accelerators| !accelerators.is_empty())
+        .map(|accelerators| accelerators.join(ACCELERATOR_DELIMITER));
+
     json!({
         ::ark_api::consts::LABEL_BIND_BY_USER: user_name,
+        ::ark_api::consts::LABEL_BIND_GPU: accelerators,
         ::ark_api::consts::LABEL_BIND_NAMESPACE: user_name.map(UserCrd::user_namespace_with),
         ::ark_api::consts::LABEL_BIND_NODE: node_name,
         ::ark_api::consts::LABEL_BIND_PERSISTENT: persistent.to_string(),
@@ -449,10 +1090,45 @@ fn get_label(node_name: &str, user_name: Option<&str>, persistent: bool) -> Valu
     })
 }
 
+/// Resolve the templates directory, waiting for it to be mounted if `timeout` is given.
+async fn resolve_templates_home(path: PathBuf, timeout: Duration) -> Result<PathBuf> {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    // NOTE: the absolute path is only for the error messages
+    let absolute = ::std::env::current_dir()
+        .map(|cwd| cwd.join(&path))
+        .unwrap_or_else(|_| path.clone());
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match fs::canonicalize(&path) {
+            Ok(path) => break Ok(path),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                if Instant::now() >= deadline {
+                    bail!(
+                        "session templates directory does not exist (VINE_SESSION_TEMPLATES_HOME): {absolute:?}"
+                    )
+                }
+                warn!("waiting for session templates directory to be mounted: {absolute:?}");
+                sleep(INTERVAL).await
+            }
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => bail!(
+                "session templates directory is not readable (VINE_SESSION_TEMPLATES_HOME): {absolute:?}: {error}"
+            ),
+            Err(error) => bail!(
+                "failed to resolve session templates directory (VINE_SESSION_TEMPLATES_HOME): {absolute:?}: {error}"
+            ),
+        }
+    }
+}
+
+const ACCELERATOR_DELIMITER: &str = ".";
+
 pub fn is_allocable<'a>(
     labels: &'a BTreeMap<String, String>,
     node_name: Option<&str>,
     user_name: &str,
+    accelerators: &[String],
 ) -> AllocationState<'a> {
     let check_by_key = |key: &str, value: Option<&str>| {
         value.and_then(|value| labels.get(key).filter(|&label_value| label_value != value))
@@ -462,6 +1138,18 @@ pub fn is_allocable<'a>(
         AllocationState::NotAllocated
     } else if let Some(node_name) = check_by_key(::ark_api::consts::LABEL_BIND_NODE, node_name) {
         AllocationState::AllocatedByOtherNode { node_name }
+    } else if let Some(accelerators) = labels
+        .get(::ark_api::consts::LABEL_BIND_GPU)
+        .filter(|_| check_by_key(::ark_api::consts::LABEL_BIND_BY_USER, Some(user_name)).is_some())
+        .filter(|claimed| {
+            claimed.split(ACCELERATOR_DELIMITER).any(|claimed| {
+                accelerators
+                    .iter()
+                    .any(|accelerator| accelerator == claimed)
+            })
+        })
+    {
+        AllocationState::AcceleratorsClaimed { accelerators }
     } else if let Some(user_name) =
         check_by_key(::ark_api::consts::LABEL_BIND_BY_USER, Some(user_name))
     {
@@ -472,8 +1160,98 @@ pub fn is_allocable<'a>(
 }
 
 pub enum AllocationState<'a> {
+    AcceleratorsClaimed { accelerators: &'a str },
     AllocatedByMyself,
     AllocatedByOtherNode { node_name: &'a str },
     AllocatedByOtherUser { user_name: &'a str },
     NotAllocated,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_node(name: &str, labels: &[(&str, &str)]) -> Node {
+        Node {
+            metadata: ObjectMeta {
+                name: Some(name.into()),
+                labels: Some(
+                    labels
+                        .iter()
+                        .map(|&(key, value)| (key.into(), value.into()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn build_bound_node(name: &str, user_name: &str, persistent: bool) -> Node {
+        let persistent = persistent.to_string();
+        build_node(
+            name,
+            &[
+                (::ark_api::consts::LABEL_BIND_BY_USER, user_name),
+                (::ark_api::consts::LABEL_BIND_NODE, name),
+                (::ark_api::consts::LABEL_BIND_PERSISTENT, &persistent),
+                (::ark_api::consts::LABEL_BIND_STATUS, "true"),
+            ],
+        )
+    }
+
+    #[test]
+    fn migrate_onto_free_node() {
+        let from = build_bound_node("from", "alice", true);
+
+        let to = build_node("to", &[]);
+        assert!(ensure_migratable(&from, &to, "alice", &[]).is_ok());
+
+        let to = build_node("to", &[(::ark_api::consts::LABEL_BIND_STATUS, "false")]);
+        assert!(ensure_migratable(&from, &to, "alice", &[]).is_ok());
+    }
+
+    #[test]
+    fn migrate_reject_not_persistent() {
+        let to = build_node("to", &[]);
+
+        let from = build_bound_node("from", "alice", false);
+        assert!(ensure_migratable(&from, &to, "alice", &[]).is_err());
+
+        let from = build_bound_node("from", "bob", true);
+        assert!(ensure_migratable(&from, &to, "alice", &[]).is_err());
+    }
+
+    #[test]
+    fn migrate_reject_allocated_node() {
+        let from = build_bound_node("from", "alice", true);
+
+        let to = build_bound_node("to", "bob", false);
+        let error = ensure_migratable(&from, &to, "alice", &[]).expect_err("should be rejected");
+        assert!(error.to_string().contains("\"bob\""));
+
+        let mut to = build_bound_node("to", "alice", false);
+        to.labels_mut()
+            .insert(::ark_api::consts::LABEL_BIND_NODE.into(), "other".into());
+        let error = ensure_migratable(&from, &to, "alice", &[]).expect_err("should be rejected");
+        assert!(error.to_string().contains("\"other\""));
+    }
+
+    #[test]
+    fn migrate_reject_claimed_accelerators() {
+        let from = build_bound_node("from", "alice", true);
+
+        let mut to = build_bound_node("to", "bob", false);
+        to.labels_mut()
+            .insert(::ark_api::consts::LABEL_BIND_GPU.into(), "gpu0.gpu1".into());
+
+        // The accelerators are unknown yet
+        let error = ensure_migratable(&from, &to, "alice", &[]).expect_err("should be rejected");
+        assert!(error.to_string().contains("\"bob\""));
+
+        let accelerators = ["gpu1".to_string()];
+        let error =
+            ensure_migratable(&from, &to, "alice", &accelerators).expect_err("should be rejected");
+        assert!(error.to_string().contains("gpu0.gpu1"));
+    }
+}
@@ -1,5 +1,8 @@
+use std::time::{Duration, Instant};
+
 use anyhow::{anyhow, bail, Result};
-use futures::{stream::FuturesUnordered, TryStreamExt};
+use ark_core::kube::is_retryable;
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
 use k8s_openapi::{
     api::core::v1::{
         CSIPersistentVolumeSource, PersistentVolume, PersistentVolumeClaim,
@@ -8,42 +11,83 @@ use k8s_openapi::{
     Metadata,
 };
 use kube::{
-    api::{ListParams, PostParams},
+    api::{DeleteParams, ListParams, PostParams},
     core::ObjectMeta,
     Api, Client, ResourceExt,
 };
 use maplit::btreemap;
-use tracing::{instrument, Level};
+use tokio::time::sleep;
+use tracing::{debug, instrument, warn, Level};
 
 pub(crate) mod consts {
     pub const NAME: &str = "vine-storage";
     pub const NAMESPACE_SHARED: &str = "vine-guest";
 
+    pub const LABEL_SHARED: &str = "vine.ulagbulag.io/shared";
+    pub const LABEL_SHARED_READ_ONLY: &str = "vine.ulagbulag.io/shared-read-only";
+
     pub const SECRET_ROOK_CSI_CEPHFS_NODE_NAME: &str = "rook-csi-cephfs-node";
     pub const SECRET_ROOK_CSI_CEPHFS_USER_NAME: &str = "rook-csi-cephfs-user";
     pub const PV_PERSISTENT_VOLUME_RECLAIM_POLICY: &str = "Retain";
+    pub const PVC_ACCESS_MODE_READ_ONLY_MANY: &str = "ReadOnlyMany";
+    pub const PVC_ACCESS_MODE_READ_WRITE_MANY: &str = "ReadWriteMany";
+
+    pub const PVC_BOUND_INTERVAL: ::std::time::Duration = ::std::time::Duration::from_secs(2);
+    pub const PVC_BOUND_TIMEOUT: ::std::time::Duration = ::std::time::Duration::from_secs(60);
 }
 
+#[derive(Debug, Default)]
+pub struct SharedPvcs {
+    pub pvcs: Vec<PersistentVolumeClaim>,
+    pub failures: Vec<SharedPvcFailure>,
+}
+
+#[derive(Debug)]
+pub struct SharedPvcFailure {
+    pub name: String,
+    pub error: ::anyhow::Error,
+}
+
+/// Clone all sharable PVCs into the target namespace.
+///
+/// A failure on cloning a PVC does not abort the others;
+/// it is reported in [`SharedPvcs::failures`] instead.
+///
+/// The clones of the PVCs labeled with `vine.ulagbulag.io/shared-read-only=true`
+/// are forced to be `ReadOnlyMany`. The existing clones are recreated
+/// once the label of their source is changed.
 #[instrument(level = Level::INFO, skip(kube), err(Display))]
 pub async fn get_or_create_shared_pvcs(
     kube: &Client,
     target_namespace: &str,
-) -> Result<Vec<PersistentVolumeClaim>> {
+) -> Result<SharedPvcs> {
     // search sharable PVCs
     let source_namespace = self::consts::NAMESPACE_SHARED;
-    let api = Api::namespaced(kube.clone(), source_namespace);
+    let api = Api::<PersistentVolumeClaim>::namespaced(kube.clone(), source_namespace);
     let lp = ListParams {
-        label_selector: Some("vine.ulagbulag.io/shared=true".into()),
+        label_selector: Some(format!("{}=true", self::consts::LABEL_SHARED)),
         ..Default::default()
     };
     match api.list(&lp).await {
-        Ok(pvcs) => {
-            pvcs.into_iter()
-                .map(|pvc| clone_pvc(kube, source_namespace, target_namespace, pvc))
-                .collect::<FuturesUnordered<_>>()
-                .try_collect()
-                .await
-        }
+        Ok(pvcs) => Ok(pvcs
+            .into_iter()
+            .map(|pvc| {
+                let name = pvc.name_any();
+                clone_pvc(kube, source_namespace, target_namespace, pvc)
+                    .map(|result| (name, result))
+            })
+            .collect::<FuturesUnordered<_>>()
+            .fold(
+                SharedPvcs::default(),
+                |mut results, (name, result)| async move {
+                    match result {
+                        Ok(pvc) => results.pvcs.push(pvc),
+                        Err(error) => results.failures.push(SharedPvcFailure { name, error }),
+                    }
+                    results
+                },
+            )
+            .await),
         Err(error) => {
             bail!("failed to get shared PVCs ({source_namespace} => {target_namespace}): {error}")
         }
@@ -57,18 +101,40 @@ async fn clone_pvc(
     target_namespace: &str,
     pvc: PersistentVolumeClaim,
 ) -> Result<PersistentVolumeClaim> {
+    let name = pvc.name_any();
+    let read_only = is_read_only(&pvc);
+
     // skip creating if the PVC already exists
     let target_api = Api::<PersistentVolumeClaim>::namespaced(kube.clone(), target_namespace);
-    let name = pvc.name_any();
-    if let Some(pvc) = target_api.get_opt(&name).await? {
-        return Ok(pvc);
+    if let Some(target) = target_api.get_opt(&name).await? {
+        if target.metadata.deletion_timestamp.is_some() {
+            bail!("shared PVC clone is being deleted: {target_namespace}/{name}")
+        }
+        if is_read_only(&target) == read_only {
+            return Ok(target);
+        }
+
+        // NOTE: the access modes are immutable, so the clone should be recreated
+        delete_pvc_clone(kube, &target_api, target).await?;
+        bail!("shared PVC clone is being recreated with the new access mode: {target_namespace}/{name}")
     }
 
     // get original PVC
+    let source_api = Api::<PersistentVolumeClaim>::namespaced(kube.clone(), source_namespace);
+    let pvc = wait_until_bound(
+        &source_api,
+        source_namespace,
+        pvc,
+        self::consts::PVC_BOUND_TIMEOUT,
+    )
+    .await?;
     let pv_name = match pvc.spec.as_ref().and_then(|spec| spec.volume_name.as_ref()) {
         Some(pv_name) => pv_name,
         None => bail!("shared PVC is not ready: {source_namespace}/{name}"),
     };
+    if read_only && !supports_read_only_many(&pvc) {
+        bail!("shared PVC does not support read-only many access: {source_namespace}/{name}")
+    }
 
     let pp = PostParams {
         field_manager: Some(self::consts::NAME.into()),
@@ -76,7 +142,7 @@ async fn clone_pvc(
     };
 
     // try to clone PV
-    let pv = clone_pv(kube, target_namespace, pv_name, &pp).await?;
+    let pv = clone_pv(kube, target_namespace, pv_name, read_only, &pp).await?;
 
     let ObjectMeta {
         annotations,
@@ -100,7 +166,11 @@ async fn clone_pvc(
             ..Default::default()
         },
         spec: Some(PersistentVolumeClaimSpec {
-            access_modes,
+            access_modes: if read_only {
+                Some(vec![self::consts::PVC_ACCESS_MODE_READ_ONLY_MANY.into()])
+            } else {
+                access_modes
+            },
             storage_class_name,
             resources,
             volume_mode,
@@ -118,6 +188,104 @@ async fn clone_pvc(
         ))
 }
 
+/// Return `true` if the shared PVC (or its clone) should be mounted read-only.
+fn is_read_only(pvc: &PersistentVolumeClaim) -> bool {
+    pvc.labels()
+        .get(self::consts::LABEL_SHARED_READ_ONLY)
+        .is_some_and(|value| value == "true")
+}
+
+/// Delete the cloned PVC along with its static PV.
+///
+/// The data is kept, as the cloned PV retains the source volume on delete.
+#[instrument(level = Level::INFO, skip(kube, api, pvc), fields(pvc.name = %pvc.name_any(), pvc.namespace = pvc.namespace()), err(Display))]
+async fn delete_pvc_clone(
+    kube: &Client,
+    api: &Api<PersistentVolumeClaim>,
+    pvc: PersistentVolumeClaim,
+) -> Result<()> {
+    let name = pvc.name_any();
+    let dp = DeleteParams::background();
+    api.delete(&name, &dp)
+        .await
+        .map_err(|error| anyhow!("failed to delete the PVC clone ({name}): {error}"))?;
+
+    if let Some(pv_name) = pvc.spec.and_then(|spec| spec.volume_name) {
+        let api = Api::<PersistentVolume>::all(kube.clone());
+        api.delete(&pv_name, &dp)
+            .await
+            .map_err(|error| anyhow!("failed to delete the PV clone ({pv_name}): {error}"))?;
+    }
+    Ok(())
+}
+
+/// Return `true` if the PVC can be mounted by multiple nodes at once.
+fn supports_read_only_many(pvc: &PersistentVolumeClaim) -> bool {
+    pvc.spec
+        .as_ref()
+        .and_then(|spec| spec.access_modes.as_ref())
+        .map(|access_modes| {
+            access_modes.iter().any(|mode| {
+                mode == self::consts::PVC_ACCESS_MODE_READ_ONLY_MANY
+                    || mode == self::consts::PVC_ACCESS_MODE_READ_WRITE_MANY
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Poll the source PVC until it is bound to a PV, or the timeout expires.
+#[instrument(level = Level::INFO, skip(api, pvc), fields(pvc.name = %pvc.name_any()), err(Display))]
+async fn wait_until_bound(
+    api: &Api<PersistentVolumeClaim>,
+    namespace: &str,
+    mut pvc: PersistentVolumeClaim,
+    timeout: Duration,
+) -> Result<PersistentVolumeClaim> {
+    let name = pvc.name_any();
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let phase = pvc
+            .status
+            .as_ref()
+            .and_then(|status| status.phase.as_deref())
+            .unwrap_or("Unknown");
+        let is_bound = phase == "Bound"
+            && pvc
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.volume_name.as_ref())
+                .is_some();
+
+        match phase {
+            _ if is_bound => return Ok(pvc),
+            // NOTE: a lost PVC never becomes bound again
+            "Lost" => bail!("shared PVC has lost its PV: {namespace}/{name}"),
+            _ if Instant::now() >= deadline => bail!(
+                "timed out waiting for the shared PVC to be bound ({namespace}/{name}): phase is {phase}"
+            ),
+            _ => {
+                debug!("waiting for the shared PVC to be bound ({namespace}/{name}): phase is {phase}");
+                sleep(self::consts::PVC_BOUND_INTERVAL).await;
+            }
+        }
+
+        pvc = loop {
+            match api.get(&name).await {
+                Ok(pvc) => break pvc,
+                // NOTE: keep polling through the transient failures until the deadline
+                Err(error) if is_retryable(&error) && Instant::now() < deadline => {
+                    warn!("failed to get the shared PVC ({namespace}/{name}); retrying: {error}");
+                    sleep(self::consts::PVC_BOUND_INTERVAL).await;
+                }
+                Err(error) => {
+                    bail!("failed to get the shared PVC ({namespace}/{name}): {error}")
+                }
+            }
+        };
+    }
+}
+
 /// Copy the YAML content of the PV, and create a new static PV
 /// with the same information and some modifications.
 ///
@@ -141,12 +309,16 @@ async fn clone_pv(
     kube: &Client,
     target_namespace: &str,
     source_name: &str,
+    read_only: bool,
     pp: &PostParams,
 ) -> Result<PersistentVolume> {
     // skip creating if the PV already exists
     let api = Api::<PersistentVolume>::all(kube.clone());
     let target_name = format!("{source_name}-{target_namespace}");
     if let Some(pv) = api.get_opt(&target_name).await? {
+        if pv.metadata.deletion_timestamp.is_some() {
+            bail!("shared PV clone is being deleted: {target_name}")
+        }
         return release_pv(&api, pv, pp).await;
     }
 
@@ -190,11 +362,16 @@ async fn clone_pv(
             ..Default::default()
         },
         spec: Some(PersistentVolumeSpec {
-            access_modes,
+            access_modes: if read_only {
+                Some(vec![self::consts::PVC_ACCESS_MODE_READ_ONLY_MANY.into()])
+            } else {
+                access_modes
+            },
             capacity,
             csi: Some(CSIPersistentVolumeSource {
                 driver,
                 node_stage_secret_ref: Some(secret_ref),
+                read_only: Some(read_only),
                 volume_attributes: Some({
                     let mut volume_attributes = volume_attributes.unwrap_or_default();
                     volume_attributes.insert(